@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use prost::Message;
 use common_base::errors::RobustMQError;
-use protocol::kv::{CommonReply, DeleteRequest, ExistsReply, ExistsRequest, GetReply, GetRequest, SetRequest};
+use protocol::kv::{BatchDeleteRequest, BatchSetRequest, CommonReply, CompareAndSwapReply, CompareAndSwapRequest, DeleteRequest, ExistsReply, ExistsRequest, GetReply, GetRequest, LeaseGrantReply, LeaseGrantRequest, LeaseRevokeRequest, ListReply, ListRequest, NamespaceGetReply, NamespaceGetRequest, NamespaceSetRequest, SetRequest, TxnReply, TxnRequest};
 use crate::placement::{retry_call, PlacementCenterInterface, PlacementCenterService};
 use crate::poll::ClientPool;
 
@@ -87,4 +87,193 @@ pub async fn placement_exists(
         },
         Err(e) => Err(e),
     }
-}
\ No newline at end of file
+}
+
+pub async fn placement_compare_and_swap(
+    client_pool: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: CompareAndSwapRequest,
+) -> Result<CompareAndSwapReply, RobustMQError> {
+    let request_data = CompareAndSwapRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::Kv,
+        PlacementCenterInterface::CompareAndSwap,
+        client_pool,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match CompareAndSwapReply::decode(data.as_ref()) {
+            Ok(reply) => Ok(reply),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn placement_lease_grant(
+    client_pool: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: LeaseGrantRequest,
+) -> Result<LeaseGrantReply, RobustMQError> {
+    let request_data = LeaseGrantRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::Kv,
+        PlacementCenterInterface::LeaseGrant,
+        client_pool,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match LeaseGrantReply::decode(data.as_ref()) {
+            Ok(reply) => Ok(reply),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn placement_lease_revoke(
+    client_pool: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: LeaseRevokeRequest,
+) -> Result<CommonReply, RobustMQError> {
+    let request_data = LeaseRevokeRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::Kv,
+        PlacementCenterInterface::LeaseRevoke,
+        client_pool,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match CommonReply::decode(data.as_ref()) {
+            Ok(reply) => Ok(reply),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn placement_list(
+    client_poll: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: ListRequest,
+) -> Result<ListReply, RobustMQError> {
+    let request_data = ListRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::Kv,
+        PlacementCenterInterface::List,
+        client_poll,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match ListReply::decode(data.as_ref()) {
+            Ok(reply) => Ok(reply),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn placement_txn(
+    client_poll: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: TxnRequest,
+) -> Result<TxnReply, RobustMQError> {
+    let request_data = TxnRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::Kv,
+        PlacementCenterInterface::Txn,
+        client_poll,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match TxnReply::decode(data.as_ref()) {
+            Ok(reply) => Ok(reply),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn placement_batch_set(
+    client_poll: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: BatchSetRequest,
+) -> Result<CommonReply, RobustMQError> {
+    let request_data = BatchSetRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::Kv,
+        PlacementCenterInterface::BatchSet,
+        client_poll,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match CommonReply::decode(data.as_ref()) {
+            Ok(reply) => Ok(reply),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn placement_batch_delete(
+    client_poll: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: BatchDeleteRequest,
+) -> Result<CommonReply, RobustMQError> {
+    let request_data = BatchDeleteRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::Kv,
+        PlacementCenterInterface::BatchDelete,
+        client_poll,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match CommonReply::decode(data.as_ref()) {
+            Ok(reply) => Ok(reply),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn placement_namespace_set(
+    client_poll: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: NamespaceSetRequest,
+) -> Result<CommonReply, RobustMQError> {
+    let request_data = NamespaceSetRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::Kv,
+        PlacementCenterInterface::NamespaceSet,
+        client_poll,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match CommonReply::decode(data.as_ref()) {
+            Ok(reply) => Ok(reply),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn placement_namespace_get(
+    client_poll: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: NamespaceGetRequest,
+) -> Result<NamespaceGetReply, RobustMQError> {
+    let request_data = NamespaceGetRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::Kv,
+        PlacementCenterInterface::NamespaceGet,
+        client_poll,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match NamespaceGetReply::decode(data.as_ref()) {
+            Ok(reply) => Ok(reply),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
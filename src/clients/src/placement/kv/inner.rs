@@ -1,15 +1,19 @@
 use crate::placement::kv::KvServiceManager;
+use crate::placement::kv_request;
 use common_base::errors::RobustMQError;
 use mobc::Connection;
 use prost::{DecodeError, Message};
-use protocol::kv::{CommonReply, DeleteRequest, ExistsReply, ExistsRequest, GetReply, GetRequest, SetRequest};
+use tonic::codegen::tokio_stream::Stream;
+use tonic::Streaming;
+use protocol::kv::{BatchDeleteRequest, BatchSetRequest, CommonReply, CompareAndSwapReply, CompareAndSwapRequest, DeleteRequest, ExistsReply, ExistsRequest, GetReply, GetRequest, LeaseGrantReply, LeaseGrantRequest, LeaseKeepAliveReply, LeaseKeepAliveRequest, LeaseRevokeRequest, ListReply, ListRequest, NamespaceGetReply, NamespaceGetRequest, NamespaceSetRequest, SetRequest, TxnReply, TxnRequest};
 
 pub(crate) async fn inner_get(
     mut client: Connection<KvServiceManager>,
     request: Vec<u8>,
+    forwarded: bool,
 ) -> Result<Vec<u8>, RobustMQError> {
     match GetRequest::decode(request.as_ref()) {
-        Ok(request) => match client.get(request).await {
+        Ok(request) => match client.get(kv_request(request, forwarded)).await {
             Ok(result) => Ok(GetReply::encode_to_vec(&result.into_inner())),
             Err(e) => Err(RobustMQError::GrpcServerStatus(e))
         },
@@ -20,9 +24,10 @@ pub(crate) async fn inner_get(
 pub(crate) async fn inner_set(
     mut client: Connection<KvServiceManager>,
     request: Vec<u8>,
+    forwarded: bool,
 ) -> Result<Vec<u8>, RobustMQError> {
     match SetRequest::decode(request.as_ref()) {
-        Ok(request) => match client.set(request).await {
+        Ok(request) => match client.set(kv_request(request, forwarded)).await {
             Ok(result) => Ok(CommonReply::encode_to_vec(&result.into_inner())), // Set operation doesn't return a response
             Err(e) => Err(RobustMQError::GrpcServerStatus(e))
         },
@@ -33,9 +38,10 @@ pub(crate) async fn inner_set(
 pub(crate) async fn inner_delete(
     mut client: Connection<KvServiceManager>,
     request: Vec<u8>,
+    forwarded: bool,
 ) -> Result<Vec<u8>, RobustMQError> {
     match DeleteRequest::decode(request.as_ref()) {
-        Ok(request) => match client.delete(request).await {
+        Ok(request) => match client.delete(kv_request(request, forwarded)).await {
             Ok(result) => Ok(CommonReply::encode_to_vec(&result.into_inner())),
             Err(e) => Err(RobustMQError::GrpcServerStatus(e))
         },
@@ -46,12 +52,153 @@ pub(crate) async fn inner_delete(
 pub(crate) async fn inner_exists(
     mut client: Connection<KvServiceManager>,
     request: Vec<u8>,
+    forwarded: bool,
 ) -> Result<Vec<u8>, RobustMQError> {
     match ExistsRequest::decode(request.as_ref()) {
-        Ok(request) => match client.exists(request).await {
+        Ok(request) => match client.exists(kv_request(request, forwarded)).await {
             Ok(result) => Ok(ExistsReply::encode_to_vec(&result.into_inner())),
             Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
         },
         Err(e) => Err(RobustMQError::CommonError(e.to_string()))
     }
-}
\ No newline at end of file
+}
+
+pub(crate) async fn inner_compare_and_swap(
+    mut client: Connection<KvServiceManager>,
+    request: Vec<u8>,
+    forwarded: bool,
+) -> Result<Vec<u8>, RobustMQError> {
+    match CompareAndSwapRequest::decode(request.as_ref()) {
+        Ok(request) => match client.compare_and_swap(kv_request(request, forwarded)).await {
+            Ok(result) => Ok(CompareAndSwapReply::encode_to_vec(&result.into_inner())),
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => Err(RobustMQError::CommonError(e.to_string()))
+    }
+}
+
+pub(crate) async fn inner_lease_grant(
+    mut client: Connection<KvServiceManager>,
+    request: Vec<u8>,
+    forwarded: bool,
+) -> Result<Vec<u8>, RobustMQError> {
+    match LeaseGrantRequest::decode(request.as_ref()) {
+        Ok(request) => match client.lease_grant(kv_request(request, forwarded)).await {
+            Ok(result) => Ok(LeaseGrantReply::encode_to_vec(&result.into_inner())),
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => Err(RobustMQError::CommonError(e.to_string()))
+    }
+}
+
+pub(crate) async fn inner_lease_keep_alive<S>(
+    mut client: Connection<KvServiceManager>,
+    requests: S,
+    forwarded: bool,
+) -> Result<Streaming<LeaseKeepAliveReply>, RobustMQError>
+where
+    S: Stream<Item = LeaseKeepAliveRequest> + Send + 'static,
+{
+    match client.lease_keep_alive(kv_request(requests, forwarded)).await {
+        Ok(result) => Ok(result.into_inner()),
+        Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+    }
+}
+
+pub(crate) async fn inner_lease_revoke(
+    mut client: Connection<KvServiceManager>,
+    request: Vec<u8>,
+    forwarded: bool,
+) -> Result<Vec<u8>, RobustMQError> {
+    match LeaseRevokeRequest::decode(request.as_ref()) {
+        Ok(request) => match client.lease_revoke(kv_request(request, forwarded)).await {
+            Ok(result) => Ok(CommonReply::encode_to_vec(&result.into_inner())),
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => Err(RobustMQError::CommonError(e.to_string()))
+    }
+}
+
+pub(crate) async fn inner_list(
+    mut client: Connection<KvServiceManager>,
+    request: Vec<u8>,
+    forwarded: bool,
+) -> Result<Vec<u8>, RobustMQError> {
+    match ListRequest::decode(request.as_ref()) {
+        Ok(request) => match client.list(kv_request(request, forwarded)).await {
+            Ok(result) => Ok(ListReply::encode_to_vec(&result.into_inner())),
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => Err(RobustMQError::CommonError(e.to_string()))
+    }
+}
+
+pub(crate) async fn inner_txn(
+    mut client: Connection<KvServiceManager>,
+    request: Vec<u8>,
+    forwarded: bool,
+) -> Result<Vec<u8>, RobustMQError> {
+    match TxnRequest::decode(request.as_ref()) {
+        Ok(request) => match client.txn(kv_request(request, forwarded)).await {
+            Ok(result) => Ok(TxnReply::encode_to_vec(&result.into_inner())),
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => Err(RobustMQError::CommonError(e.to_string()))
+    }
+}
+
+pub(crate) async fn inner_batch_set(
+    mut client: Connection<KvServiceManager>,
+    request: Vec<u8>,
+    forwarded: bool,
+) -> Result<Vec<u8>, RobustMQError> {
+    match BatchSetRequest::decode(request.as_ref()) {
+        Ok(request) => match client.batch_set(kv_request(request, forwarded)).await {
+            Ok(result) => Ok(CommonReply::encode_to_vec(&result.into_inner())),
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => Err(RobustMQError::CommonError(e.to_string()))
+    }
+}
+
+pub(crate) async fn inner_batch_delete(
+    mut client: Connection<KvServiceManager>,
+    request: Vec<u8>,
+    forwarded: bool,
+) -> Result<Vec<u8>, RobustMQError> {
+    match BatchDeleteRequest::decode(request.as_ref()) {
+        Ok(request) => match client.batch_delete(kv_request(request, forwarded)).await {
+            Ok(result) => Ok(CommonReply::encode_to_vec(&result.into_inner())),
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => Err(RobustMQError::CommonError(e.to_string()))
+    }
+}
+
+pub(crate) async fn inner_namespace_set(
+    mut client: Connection<KvServiceManager>,
+    request: Vec<u8>,
+    forwarded: bool,
+) -> Result<Vec<u8>, RobustMQError> {
+    match NamespaceSetRequest::decode(request.as_ref()) {
+        Ok(request) => match client.namespace_set(kv_request(request, forwarded)).await {
+            Ok(result) => Ok(CommonReply::encode_to_vec(&result.into_inner())),
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => Err(RobustMQError::CommonError(e.to_string()))
+    }
+}
+
+pub(crate) async fn inner_namespace_get(
+    mut client: Connection<KvServiceManager>,
+    request: Vec<u8>,
+    forwarded: bool,
+) -> Result<Vec<u8>, RobustMQError> {
+    match NamespaceGetRequest::decode(request.as_ref()) {
+        Ok(request) => match client.namespace_get(kv_request(request, forwarded)).await {
+            Ok(result) => Ok(NamespaceGetReply::encode_to_vec(&result.into_inner())),
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => Err(RobustMQError::CommonError(e.to_string()))
+    }
+}
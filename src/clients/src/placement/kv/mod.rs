@@ -1,8 +1,11 @@
 use std::sync::Arc;
 use mobc::{Connection, Manager};
+use tonic::codegen::tokio_stream::Stream;
 use tonic::transport::{Channel, Error};
+use tonic::Streaming;
 use common_base::errors::RobustMQError;
 use protocol::kv::kv_service_client::KvServiceClient;
+use protocol::kv::{LeaseKeepAliveReply, LeaseKeepAliveRequest};
 use crate::placement::PlacementCenterInterface;
 use crate::poll::ClientPool;
 
@@ -15,14 +18,24 @@ pub(crate) async fn kv_interface_call(
     client_poll: Arc<ClientPool>,
     addr: String,
     request: Vec<u8>,
+    forwarded: bool,
 ) -> Result<Vec<u8>, RobustMQError> {
     match kv_client(client_poll.clone(), addr.clone()).await {
         Ok(client) => {
             let result = match interface {
-                PlacementCenterInterface::Set => inner::inner_set(client, request.clone()).await,
-                PlacementCenterInterface::Delete => inner::inner_delete(client, request.clone()).await,
-                PlacementCenterInterface::Get => inner::inner_get(client, request.clone()).await,
-                PlacementCenterInterface::Exists => inner::inner_exists(client, request.clone()).await,
+                PlacementCenterInterface::Set => inner::inner_set(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::Delete => inner::inner_delete(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::Get => inner::inner_get(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::Exists => inner::inner_exists(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::CompareAndSwap => inner::inner_compare_and_swap(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::LeaseGrant => inner::inner_lease_grant(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::LeaseRevoke => inner::inner_lease_revoke(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::List => inner::inner_list(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::Txn => inner::inner_txn(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::BatchSet => inner::inner_batch_set(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::BatchDelete => inner::inner_batch_delete(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::NamespaceSet => inner::inner_namespace_set(client, request.clone(), forwarded).await,
+                PlacementCenterInterface::NamespaceGet => inner::inner_namespace_get(client, request.clone(), forwarded).await,
                 _ => Err(RobustMQError::CommonError(format!(
                     "kv service does not support service interfaces {:?}",
                     interface
@@ -37,6 +50,19 @@ pub(crate) async fn kv_interface_call(
     }
 }
 
+pub(crate) async fn kv_lease_keep_alive<S>(
+    client_poll: Arc<ClientPool>,
+    addr: String,
+    requests: S,
+    forwarded: bool,
+) -> Result<Streaming<LeaseKeepAliveReply>, RobustMQError>
+where
+    S: Stream<Item = LeaseKeepAliveRequest> + Send + 'static,
+{
+    let client = kv_client(client_poll, addr).await?;
+    inner::inner_lease_keep_alive(client, requests, forwarded).await
+}
+
 async fn kv_client(
     client_poll: Arc<ClientPool>,
     addr: String,
@@ -2,8 +2,12 @@ use std::sync::Arc;
 use std::time::Duration;
 use log::error;
 use tokio::time::sleep;
-use common_base::errors::RobustMQError;
-use crate::placement::kv::kv_interface_call;
+use tonic::codegen::tokio_stream::Stream;
+use tonic::metadata::MetadataValue;
+use tonic::Streaming;
+use common_base::errors::{status_leader_addr, RobustMQError};
+use protocol::kv::{LeaseKeepAliveReply, LeaseKeepAliveRequest};
+use crate::placement::kv::{kv_interface_call, kv_lease_keep_alive};
 use crate::placement::openraft::openraft_interface_call;
 use crate::poll::ClientPool;
 use crate::{retry_sleep_time, retry_times};
@@ -24,10 +28,66 @@ pub enum PlacementCenterInterface {
     Get,
     Delete,
     Exists,
+    CompareAndSwap,
+    LeaseGrant,
+    LeaseRevoke,
+    List,
+    Txn,
+    BatchSet,
+    BatchDelete,
+    NamespaceSet,
+    NamespaceGet,
 
     Vote,
     Append,
-    Snapshot,
+    SnapshotOffset,
+    AddLearner,
+    PromoteVoter,
+    Membership,
+    ReadIndex,
+}
+
+/// gRPC metadata of a request a placement center node forwards to its leader. A node never
+/// forwards such a request again, it fails with a NotLeader status when it does not lead.
+pub const FORWARDED_METADATA: &str = "placement-forwarded";
+
+pub(crate) fn kv_request<T>(request: T, forwarded: bool) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(request);
+    if forwarded {
+        request
+            .metadata_mut()
+            .insert(FORWARDED_METADATA, MetadataValue::from_static("true"));
+    }
+    request
+}
+
+/// Whether the request was forwarded by another placement center node.
+pub fn is_forwarded<T>(request: &tonic::Request<T>) -> bool {
+    request.metadata().contains_key(FORWARDED_METADATA)
+}
+
+/// Send a request of the KV service to the leader once, on behalf of a node that is not the
+/// leader. Errors are returned as they are, a NotLeader status included.
+pub async fn forward_call(
+    interface: PlacementCenterInterface,
+    client_poll: Arc<ClientPool>,
+    leader_addr: String,
+    request: Vec<u8>,
+) -> Result<Vec<u8>, RobustMQError> {
+    kv_interface_call(interface, client_poll, leader_addr, request, true).await
+}
+
+/// Open a lease keep alive stream to the leader on behalf of a node that is not the leader.
+/// The keep alive requests of the client are sent on it and the leader answers each of them.
+pub async fn forward_lease_keep_alive<S>(
+    client_poll: Arc<ClientPool>,
+    leader_addr: String,
+    requests: S,
+) -> Result<Streaming<LeaseKeepAliveReply>, RobustMQError>
+where
+    S: Stream<Item = LeaseKeepAliveRequest> + Send + 'static,
+{
+    kv_lease_keep_alive(client_poll, leader_addr, requests, true).await
 }
 
 async fn retry_call(
@@ -38,9 +98,16 @@ async fn retry_call(
     request: Vec<u8>,
 ) -> Result<Vec<u8>, RobustMQError> {
     let mut times = 1usize;
+    // Leader named by the last NotLeader status, it is tried before the next address
+    let mut leader_addr: Option<String> = None;
     loop {
-        let index = times % addrs.len();
-        let addr = addrs.get(index).unwrap().clone();
+        let addr = match leader_addr.take() {
+            Some(addr) => addr,
+            None => {
+                let index = times % addrs.len();
+                addrs.get(index).unwrap().clone()
+            }
+        };
         let result = match service {
             PlacementCenterService::Kv => {
                 kv_interface_call(
@@ -48,6 +115,7 @@ async fn retry_call(
                     client_poll.clone(),
                     addr.clone(),
                     request.clone(),
+                    false,
                 ).await
             }
             PlacementCenterService::OpenRaft => {
@@ -76,8 +144,14 @@ async fn retry_call(
                     return Err(e);
                 }
                 times += 1;
+                if let RobustMQError::GrpcServerStatus(status) = &e {
+                    leader_addr = status_leader_addr(status).filter(|leader| *leader != addr);
+                }
             }
         }
-        sleep(Duration::from_secs(retry_sleep_time(times))).await;
+        // The leader is retried at once, the node that named it is up
+        if leader_addr.is_none() {
+            sleep(Duration::from_secs(retry_sleep_time(times))).await;
+        }
     }
 }
\ No newline at end of file
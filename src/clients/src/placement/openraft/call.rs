@@ -2,7 +2,7 @@ use std::future::Future;
 use std::sync::Arc;
 use prost::{DecodeError, Message};
 use common_base::errors::RobustMQError;
-use protocol::openraft::{AppendReply, AppendRequest, SnapshotReply, SnapshotRequest, VoteReply, VoteRequest};
+use protocol::openraft::{AddLearnerRequest, AppendReply, AppendRequest, MembershipReply, MembershipRequest, PromoteVoterRequest, ReadIndexReply, ReadIndexRequest, SnapshotOffsetReply, SnapshotOffsetRequest, VoteReply, VoteRequest};
 use crate::placement::{retry_call, PlacementCenterInterface, PlacementCenterService};
 use crate::poll::ClientPool;
 
@@ -48,23 +48,107 @@ pub async fn placement_openraft_append(
     }
 }
 
-pub async fn placement_openraft_snapshot(
+pub async fn placement_openraft_snapshot_offset(
     client_pool: Arc<ClientPool>,
     addrs: Vec<String>,
-    request: SnapshotRequest,
-) -> Result<SnapshotReply, RobustMQError> {
-    let request_data = SnapshotRequest::encode_to_vec(&request);
+    request: SnapshotOffsetRequest,
+) -> Result<SnapshotOffsetReply, RobustMQError> {
+    let request_data = SnapshotOffsetRequest::encode_to_vec(&request);
     match retry_call(
         PlacementCenterService::OpenRaft,
-        PlacementCenterInterface::Snapshot,
+        PlacementCenterInterface::SnapshotOffset,
         client_pool,
         addrs,
         request_data,
     ).await {
-        Ok(data) => match SnapshotReply::decode(data.as_ref()) {
+        Ok(data) => match SnapshotOffsetReply::decode(data.as_ref()) {
             Ok(da) => Ok(da),
             Err(e) => Err(RobustMQError::CommonError(e.to_string())),
         },
         Err(e) => Err(e),
     }
-}
\ No newline at end of file
+}
+
+pub async fn placement_openraft_add_learner(
+    client_pool: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: AddLearnerRequest,
+) -> Result<MembershipReply, RobustMQError> {
+    let request_data = AddLearnerRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::OpenRaft,
+        PlacementCenterInterface::AddLearner,
+        client_pool,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match MembershipReply::decode(data.as_ref()) {
+            Ok(da) => Ok(da),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn placement_openraft_promote_voter(
+    client_pool: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: PromoteVoterRequest,
+) -> Result<MembershipReply, RobustMQError> {
+    let request_data = PromoteVoterRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::OpenRaft,
+        PlacementCenterInterface::PromoteVoter,
+        client_pool,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match MembershipReply::decode(data.as_ref()) {
+            Ok(da) => Ok(da),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn placement_openraft_membership(
+    client_pool: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: MembershipRequest,
+) -> Result<MembershipReply, RobustMQError> {
+    let request_data = MembershipRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::OpenRaft,
+        PlacementCenterInterface::Membership,
+        client_pool,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match MembershipReply::decode(data.as_ref()) {
+            Ok(da) => Ok(da),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn placement_openraft_read_index(
+    client_pool: Arc<ClientPool>,
+    addrs: Vec<String>,
+    request: ReadIndexRequest,
+) -> Result<ReadIndexReply, RobustMQError> {
+    let request_data = ReadIndexRequest::encode_to_vec(&request);
+    match retry_call(
+        PlacementCenterService::OpenRaft,
+        PlacementCenterInterface::ReadIndex,
+        client_pool,
+        addrs,
+        request_data,
+    ).await {
+        Ok(data) => match ReadIndexReply::decode(data.as_ref()) {
+            Ok(da) => Ok(da),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Err(e) => Err(e),
+    }
+}
@@ -1,7 +1,7 @@
 use mobc::Connection;
 use prost::{DecodeError, Message};
 use common_base::errors::RobustMQError;
-use protocol::openraft::{AppendReply, AppendRequest, SnapshotReply, SnapshotRequest, VoteReply, VoteRequest};
+use protocol::openraft::{AddLearnerRequest, AppendReply, AppendRequest, MembershipReply, MembershipRequest, PromoteVoterRequest, ReadIndexReply, ReadIndexRequest, SnapshotOffsetReply, SnapshotOffsetRequest, VoteReply, VoteRequest};
 use crate::placement::openraft::OpenRaftServiceManager;
 
 pub(crate) async fn inner_vote(
@@ -34,14 +34,14 @@ pub(crate) async fn inner_append(
     }
 }
 
-pub(crate) async fn inner_snapshot(
+pub(crate) async fn inner_snapshot_offset(
     mut client: Connection<OpenRaftServiceManager>,
     request: Vec<u8>,
 ) -> Result<Vec<u8>, RobustMQError> {
-    match SnapshotRequest::decode(request.as_ref()) {
-        Ok(request) => match client.snapshot(request).await {
+    match SnapshotOffsetRequest::decode(request.as_ref()) {
+        Ok(request) => match client.snapshot_offset(request).await {
             Ok(result) => {
-                Ok(SnapshotReply::encode_to_vec(&result.into_inner()))
+                Ok(SnapshotOffsetReply::encode_to_vec(&result.into_inner()))
             }
             Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
         },
@@ -49,4 +49,72 @@ pub(crate) async fn inner_snapshot(
             Err(RobustMQError::CommonError(e.to_string()))
         }
     }
-}
\ No newline at end of file
+}
+
+pub(crate) async fn inner_add_learner(
+    mut client: Connection<OpenRaftServiceManager>,
+    request: Vec<u8>,
+) -> Result<Vec<u8>, RobustMQError> {
+    match AddLearnerRequest::decode(request.as_ref()) {
+        Ok(request) => match client.add_learner(request).await {
+            Ok(result) => {
+                Ok(MembershipReply::encode_to_vec(&result.into_inner()))
+            }
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => {
+            Err(RobustMQError::CommonError(e.to_string()))
+        }
+    }
+}
+
+pub(crate) async fn inner_promote_voter(
+    mut client: Connection<OpenRaftServiceManager>,
+    request: Vec<u8>,
+) -> Result<Vec<u8>, RobustMQError> {
+    match PromoteVoterRequest::decode(request.as_ref()) {
+        Ok(request) => match client.promote_voter(request).await {
+            Ok(result) => {
+                Ok(MembershipReply::encode_to_vec(&result.into_inner()))
+            }
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => {
+            Err(RobustMQError::CommonError(e.to_string()))
+        }
+    }
+}
+
+pub(crate) async fn inner_membership(
+    mut client: Connection<OpenRaftServiceManager>,
+    request: Vec<u8>,
+) -> Result<Vec<u8>, RobustMQError> {
+    match MembershipRequest::decode(request.as_ref()) {
+        Ok(request) => match client.membership(request).await {
+            Ok(result) => {
+                Ok(MembershipReply::encode_to_vec(&result.into_inner()))
+            }
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => {
+            Err(RobustMQError::CommonError(e.to_string()))
+        }
+    }
+}
+
+pub(crate) async fn inner_read_index(
+    mut client: Connection<OpenRaftServiceManager>,
+    request: Vec<u8>,
+) -> Result<Vec<u8>, RobustMQError> {
+    match ReadIndexRequest::decode(request.as_ref()) {
+        Ok(request) => match client.read_index(request).await {
+            Ok(result) => {
+                Ok(ReadIndexReply::encode_to_vec(&result.into_inner()))
+            }
+            Err(e) => Err(RobustMQError::GrpcServerStatus(e)),
+        },
+        Err(e) => {
+            Err(RobustMQError::CommonError(e.to_string()))
+        }
+    }
+}
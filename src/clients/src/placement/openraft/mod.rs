@@ -3,7 +3,7 @@ use common_base::errors::RobustMQError;
 use mobc::{Connection, Manager};
 use protocol::openraft::open_raft_service_client::OpenRaftServiceClient;
 use tonic::transport::Channel;
-use crate::placement::openraft::inner::{inner_append, inner_snapshot, inner_vote};
+use crate::placement::openraft::inner::{inner_add_learner, inner_append, inner_membership, inner_promote_voter, inner_read_index, inner_snapshot_offset, inner_vote};
 use crate::placement::PlacementCenterInterface;
 use crate::poll::ClientPool;
 
@@ -21,7 +21,11 @@ pub(crate) async fn openraft_interface_call(
             let result = match interface {
                 PlacementCenterInterface::Vote => inner_vote(client, request.clone()).await,
                 PlacementCenterInterface::Append => inner_append(client, request.clone()).await,
-                PlacementCenterInterface::Snapshot => inner_snapshot(client, request.clone()).await,
+                PlacementCenterInterface::SnapshotOffset => inner_snapshot_offset(client, request.clone()).await,
+                PlacementCenterInterface::AddLearner => inner_add_learner(client, request.clone()).await,
+                PlacementCenterInterface::PromoteVoter => inner_promote_voter(client, request.clone()).await,
+                PlacementCenterInterface::Membership => inner_membership(client, request.clone()).await,
+                PlacementCenterInterface::ReadIndex => inner_read_index(client, request.clone()).await,
                 _ => Err(RobustMQError::CommonError(format!(
                     "openraft service does not support service interface [{:?}]",
                     interface,
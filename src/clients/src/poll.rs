@@ -29,7 +29,7 @@ impl ClientPool {
         let module = "KvServices".to_owned();
         let key = format!("{}_{}_{}", "PlacementCenter", module, addr);
 
-        if !self.placement_center_openraft_service_pools.contains_key(&key) {
+        if !self.placement_center_kv_service_pools.contains_key(&key) {
             let manager = KvServiceManager::new(addr.clone());
             let pool = Pool::builder()
                 .max_open(self.max_open_connection)
@@ -1,15 +1,22 @@
 
 
 use clap::command;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use common_base::config::placement_center::{
+    init_placement_center_conf,
     init_placement_center_conf_by_path,
+    load_placement_center_conf,
     placement_center_conf,
 };
 use common_base::log::placement_center::init_placement_center_log;
 use log::info;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
 use tokio::sync::broadcast;
+use placement_center::openraft::join::resolve_node_id;
 use placement_center::start_server;
+use placement_center::storage::backup::restore_backup;
 
 // 定义默认的配置路径，即当命令行没传配置路径时，默认的配置文件路径
 pub const DEFAULT_PLACEMENT_CENTER_CONFIG: &str = "config/placement-center.toml";
@@ -22,6 +29,130 @@ pub const DEFAULT_PLACEMENT_CENTER_CONFIG: &str = "config/placement-center.toml"
 struct ArgsParams {
     #[arg(short, long, default_value_t=String::from(DEFAULT_PLACEMENT_CENTER_CONFIG))]
     conf: String,
+
+    // 从备份目录初始化一个新的单节点集群，数据目录必须为空
+    #[arg(long)]
+    restore_from: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+// 子命令，不带子命令时启动 Placement Center
+#[derive(Subcommand, Debug)]
+enum Command {
+    // 通过运行中节点的 HTTP 接口创建在线备份
+    Backup {
+        // 备份目录，位于节点所在的机器上，且必须不存在
+        #[arg(long)]
+        target: String,
+
+        // 节点的 HTTP 地址，默认是配置文件中的 addr:http_port
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 以 JSON Lines 格式导出所有 KV 和元数据
+    Export {
+        // 导出文件，默认输出到标准输出
+        #[arg(long)]
+        output: Option<String>,
+
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 通过 raft 分批导入 export 生成的文件
+    Import {
+        #[arg(long)]
+        input: String,
+
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 轮换密钥后，用当前密钥重新加密节点上的数据，需要在每个节点上执行
+    Reencrypt {
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 在节点上立即生成一次 raft 快照
+    Snapshot {
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 清理节点上已经包含在快照中的 raft 日志，默认清理到最近一次快照
+    Purge {
+        #[arg(long)]
+        upto: Option<u64>,
+
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 查看节点使用的共识引擎、角色和 leader
+    Consensus {
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 查看 raft 集群的成员
+    Members {
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 添加 learner，等到它追上 leader 的日志后返回
+    AddLearner {
+        #[arg(long)]
+        node_id: u64,
+
+        // 新节点的 gRPC 地址
+        #[arg(long)]
+        rpc_addr: String,
+
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 把 learner 提升为 voter
+    PromoteVoter {
+        #[arg(long)]
+        node_id: u64,
+
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 从集群中移除一个节点
+    RemoveNode {
+        #[arg(long)]
+        node_id: u64,
+
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 把 leader 转移到其他节点，需要发给当前的 leader，滚动重启前使用
+    TransferLeader {
+        // 目标节点，默认选择日志最新的 voter
+        #[arg(long)]
+        target: Option<u64>,
+
+        #[arg(long)]
+        addr: Option<String>,
+    },
+
+    // 手动压缩一个列族，不指定列族时查看所有列族的压缩进度
+    Compact {
+        #[arg(long)]
+        family: Option<String>,
+
+        #[arg(long)]
+        addr: Option<String>,
+    },
 }
 
 
@@ -29,12 +160,165 @@ struct ArgsParams {
 async fn main() {
     // 解析命令行参数
     let args = ArgsParams::parse();
-    init_placement_center_conf_by_path(&args.conf);
+
+    if let Some(command) = args.command {
+        init_placement_center_conf_by_path(&args.conf);
+        if let Err(e) = run_command(command).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // 加入已有集群的节点，先确定并持久化自己的节点 ID
+    let mut config = load_placement_center_conf(&args.conf);
+    if !config.join.is_empty() {
+        match resolve_node_id(&config).await {
+            Ok(node_id) => config.node_id = node_id,
+            Err(e) => panic!("Failed to resolve the node id to join {:?}: {}", config.join, e),
+        }
+    }
+    init_placement_center_conf(config);
+
     init_placement_center_log();
 
     let conf = placement_center_conf();
     info!("{:?}", conf);
 
+    if let Some(backup_dir) = args.restore_from {
+        match restore_backup(conf, &backup_dir) {
+            Ok(metadata) => info!("Restored from backup {}: {:?}", backup_dir, metadata),
+            Err(e) => panic!("Restore from backup {} failed: {}", backup_dir, e),
+        }
+    }
+
     let (stop_send, _) = broadcast::channel(2);
     start_server(stop_send).await;
-}
\ No newline at end of file
+}
+
+// 执行子命令，子命令都通过运行中节点的 HTTP 接口完成
+async fn run_command(command: Command) -> Result<(), std::io::Error> {
+    let conf = placement_center_conf();
+    let default_addr = format!("{}:{}", conf.addr, conf.http_port);
+    let mut stdout = tokio::io::stdout();
+    match command {
+        Command::Backup { target, addr } => {
+            let body = serde_json::json!({ "target_dir": target }).to_string();
+            let addr = addr.unwrap_or(default_addr);
+            http_call(&addr, "POST", "/v1/backup/create", "application/json", body.len() as u64, body.as_bytes(), &mut stdout).await?;
+        }
+        Command::Export { output, addr } => {
+            let addr = addr.unwrap_or(default_addr);
+            let mut output: Box<dyn AsyncWrite + Unpin + Send> = match output {
+                Some(path) => Box::new(File::create(path).await?),
+                None => Box::new(stdout),
+            };
+            http_call(&addr, "GET", "/v1/export/list", "application/x-ndjson", 0, tokio::io::empty(), &mut output).await?;
+            return Ok(());
+        }
+        Command::Import { input, addr } => {
+            let addr = addr.unwrap_or(default_addr);
+            let file = File::open(&input).await?;
+            let len = file.metadata().await?.len();
+            http_call(&addr, "POST", "/v1/import/create", "application/x-ndjson", len, file, &mut stdout).await?;
+        }
+        Command::Reencrypt { addr } => {
+            let addr = addr.unwrap_or(default_addr);
+            http_call(&addr, "PUT", "/v1/encryption/update", "application/json", 0, tokio::io::empty(), &mut stdout).await?;
+        }
+        Command::Snapshot { addr } => {
+            let addr = addr.unwrap_or(default_addr);
+            http_call(&addr, "POST", "/v1/snapshot/create", "application/json", 0, tokio::io::empty(), &mut stdout).await?;
+        }
+        Command::Purge { upto, addr } => {
+            let body = serde_json::json!({ "upto": upto }).to_string();
+            let addr = addr.unwrap_or(default_addr);
+            http_call(&addr, "POST", "/v1/purge/create", "application/json", body.len() as u64, body.as_bytes(), &mut stdout).await?;
+        }
+        Command::Consensus { addr } => {
+            let addr = addr.unwrap_or(default_addr);
+            http_call(&addr, "GET", "/v1/consensus/list", "application/json", 0, tokio::io::empty(), &mut stdout).await?;
+        }
+        Command::Members { addr } => {
+            let addr = addr.unwrap_or(default_addr);
+            http_call(&addr, "GET", "/v1/membership/list", "application/json", 0, tokio::io::empty(), &mut stdout).await?;
+        }
+        Command::AddLearner { node_id, rpc_addr, addr } => {
+            let body = serde_json::json!({ "node_id": node_id, "rpc_addr": rpc_addr }).to_string();
+            let addr = addr.unwrap_or(default_addr);
+            http_call(&addr, "POST", "/v1/learner/create", "application/json", body.len() as u64, body.as_bytes(), &mut stdout).await?;
+        }
+        Command::PromoteVoter { node_id, addr } => {
+            let body = serde_json::json!({ "node_id": node_id }).to_string();
+            let addr = addr.unwrap_or(default_addr);
+            http_call(&addr, "POST", "/v1/voter/create", "application/json", body.len() as u64, body.as_bytes(), &mut stdout).await?;
+        }
+        Command::RemoveNode { node_id, addr } => {
+            let body = serde_json::json!({ "node_id": node_id }).to_string();
+            let addr = addr.unwrap_or(default_addr);
+            http_call(&addr, "DELETE", "/v1/membership/delete", "application/json", body.len() as u64, body.as_bytes(), &mut stdout).await?;
+        }
+        Command::TransferLeader { target, addr } => {
+            let body = serde_json::json!({ "target": target }).to_string();
+            let addr = addr.unwrap_or(default_addr);
+            http_call(&addr, "PUT", "/v1/leader/update", "application/json", body.len() as u64, body.as_bytes(), &mut stdout).await?;
+        }
+        Command::Compact { family, addr } => {
+            let addr = addr.unwrap_or(default_addr);
+            match family {
+                Some(family) => {
+                    let body = serde_json::json!({ "family": family }).to_string();
+                    http_call(&addr, "POST", "/v1/compaction/create", "application/json", body.len() as u64, body.as_bytes(), &mut stdout).await?;
+                }
+                None => {
+                    http_call(&addr, "GET", "/v1/compaction/list", "application/json", 0, tokio::io::empty(), &mut stdout).await?;
+                }
+            }
+        }
+    }
+    stdout.write_all(b"\n").await?;
+    stdout.flush().await
+}
+
+// 发送 HTTP/1.0 请求，请求体和响应体都以流的方式读写，响应体写入 output
+async fn http_call<R, W>(
+    addr: &str,
+    method: &str,
+    path: &str,
+    content_type: &str,
+    content_length: u64,
+    mut body: R,
+    output: &mut W,
+) -> Result<(), std::io::Error>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut stream = TcpStream::connect(addr).await?;
+    let header = format!(
+        "{} {} HTTP/1.0\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+        method, path, addr, content_type, content_length
+    );
+    stream.write_all(header.as_bytes()).await?;
+    tokio::io::copy(&mut body, &mut stream).await?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status = String::new();
+    reader.read_line(&mut status).await?;
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("{} {} failed: {}", method, path, status.trim()),
+        ));
+    }
+    // 跳过响应头
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 || line == "\r\n" {
+            break;
+        }
+    }
+    tokio::io::copy(&mut reader, output).await?;
+    output.flush().await
+}
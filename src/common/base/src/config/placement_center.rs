@@ -1,4 +1,5 @@
 
+use crate::errors::RobustMQError;
 use crate::tools::read_file;
 use serde::Deserialize;
 use toml::Table;
@@ -13,12 +14,134 @@ pub struct PlacementCenterConfig {
     pub node_id: u64,
     #[serde(default = "default_grpc_port")]
     pub grpc_port: usize,
+    /// Nodes of a cluster formed at its first start, the node with the lowest id initializes it
+    #[serde(default)]
     pub nodes: Table,
+    /// gRPC addresses of nodes of a running cluster. A node started with seeds joins their
+    /// cluster instead of forming one from `nodes`, a node_id of 0 asks the cluster for an id.
+    #[serde(default)]
+    pub join: Vec<String>,
     pub http_port: usize,
     pub data_path: String,
+    /// Key file of the encryption of stored values, encryption is disabled when it is empty.
+    /// Every node of a cluster has to use the same keys, snapshots are sent encrypted.
+    #[serde(default)]
+    pub encryption_key_file: String,
+    #[serde(default)]
+    pub rocksdb: PlacementCenterRocksDB,
+    #[serde(default)]
+    pub raft: PlacementCenterRaft,
     pub log: PlacementCenterLog,
 }
 
+/// Options of the RocksDB of the placement center. The column families of the raft log and of
+/// the KV service size their memtables themselves, the other options apply to every family.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PlacementCenterRocksDB {
+    #[serde(default = "default_rocksdb_max_open_files")]
+    pub max_open_files: i32,
+    #[serde(default = "default_rocksdb_write_buffer_size")]
+    pub write_buffer_size: usize,
+    #[serde(default = "default_rocksdb_max_write_buffer_number")]
+    pub max_write_buffer_number: i32,
+    #[serde(default = "default_rocksdb_min_write_buffer_number_to_merge")]
+    pub min_write_buffer_number_to_merge: i32,
+    #[serde(default = "default_rocksdb_target_file_size_base")]
+    pub target_file_size_base: u64,
+    #[serde(default = "default_rocksdb_level_zero_slowdown_writes_trigger")]
+    pub level_zero_slowdown_writes_trigger: i32,
+    #[serde(default = "default_rocksdb_level_zero_stop_writes_trigger")]
+    pub level_zero_stop_writes_trigger: i32,
+    /// "universal" or "level"
+    #[serde(default = "default_rocksdb_compaction_style")]
+    pub compaction_style: String,
+    #[serde(default = "default_rocksdb_disable_auto_compactions")]
+    pub disable_auto_compactions: bool,
+    /// Seconds between two scheduled compactions of every column family, 0 disables them
+    #[serde(default = "default_rocksdb_compaction_interval_sec")]
+    pub compaction_interval_sec: u64,
+}
+
+/// Compaction styles of the `compaction_style` option of the `[rocksdb]` section.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RocksDBCompactionStyle {
+    Universal,
+    Level,
+}
+
+impl RocksDBCompactionStyle {
+    pub fn from_name(name: &str) -> Result<Self, RobustMQError> {
+        match name {
+            "universal" => Ok(RocksDBCompactionStyle::Universal),
+            "level" => Ok(RocksDBCompactionStyle::Level),
+            _ => Err(RobustMQError::UnknownCompactionStyle(name.to_string())),
+        }
+    }
+}
+
+impl Default for PlacementCenterRocksDB {
+    fn default() -> Self {
+        PlacementCenterRocksDB {
+            max_open_files: default_rocksdb_max_open_files(),
+            write_buffer_size: default_rocksdb_write_buffer_size(),
+            max_write_buffer_number: default_rocksdb_max_write_buffer_number(),
+            min_write_buffer_number_to_merge: default_rocksdb_min_write_buffer_number_to_merge(),
+            target_file_size_base: default_rocksdb_target_file_size_base(),
+            level_zero_slowdown_writes_trigger: default_rocksdb_level_zero_slowdown_writes_trigger(),
+            level_zero_stop_writes_trigger: default_rocksdb_level_zero_stop_writes_trigger(),
+            compaction_style: default_rocksdb_compaction_style(),
+            disable_auto_compactions: default_rocksdb_disable_auto_compactions(),
+            compaction_interval_sec: default_rocksdb_compaction_interval_sec(),
+        }
+    }
+}
+
+/// Consensus engine of the placement center, and the snapshot, log retention and replication
+/// options of the openraft node.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PlacementCenterRaft {
+    /// Consensus engine replicating the writes, "openraft" or "raft-rs"
+    #[serde(default = "default_raft_engine")]
+    pub engine: String,
+    /// Build a snapshot once this many logs were applied since the last one, 0 disables it
+    #[serde(default = "default_raft_snapshot_logs_since_last")]
+    pub snapshot_logs_since_last: u64,
+    /// Seconds between two scheduled snapshots, 0 disables them. A scheduled snapshot is
+    /// skipped when no log was applied since the last one.
+    #[serde(default)]
+    pub snapshot_interval_sec: u64,
+    /// Logs kept after they are included in a snapshot, for the followers lagging behind
+    #[serde(default = "default_raft_max_in_snapshot_log_to_keep")]
+    pub max_in_snapshot_log_to_keep: u64,
+    /// Logs deleted at once when purging
+    #[serde(default = "default_raft_purge_batch_size")]
+    pub purge_batch_size: u64,
+    /// Logs sent to a follower in one append request
+    #[serde(default = "default_raft_max_payload_entries")]
+    pub max_payload_entries: u64,
+    /// Logs a follower may lag behind before it is sent a snapshot instead
+    #[serde(default = "default_raft_replication_lag_threshold")]
+    pub replication_lag_threshold: u64,
+    /// Bytes of a snapshot sent to a follower in one chunk, kept under the 4 MiB message limit of gRPC
+    #[serde(default = "default_raft_snapshot_max_chunk_size")]
+    pub snapshot_max_chunk_size: u64,
+}
+
+impl Default for PlacementCenterRaft {
+    fn default() -> Self {
+        PlacementCenterRaft {
+            engine: default_raft_engine(),
+            snapshot_logs_since_last: default_raft_snapshot_logs_since_last(),
+            snapshot_interval_sec: 0,
+            max_in_snapshot_log_to_keep: default_raft_max_in_snapshot_log_to_keep(),
+            purge_batch_size: default_raft_purge_batch_size(),
+            max_payload_entries: default_raft_max_payload_entries(),
+            replication_lag_threshold: default_raft_replication_lag_threshold(),
+            snapshot_max_chunk_size: default_raft_snapshot_max_chunk_size(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone, Default)]
 pub struct PlacementCenterLog {
     pub log_config: String,
@@ -33,20 +156,104 @@ pub fn default_grpc_port() -> usize {
     9982
 }
 
+pub fn default_rocksdb_max_open_files() -> i32 {
+    1000
+}
+
+pub fn default_rocksdb_write_buffer_size() -> usize {
+    536870912
+}
+
+pub fn default_rocksdb_max_write_buffer_number() -> i32 {
+    32
+}
+
+pub fn default_rocksdb_min_write_buffer_number_to_merge() -> i32 {
+    4
+}
+
+pub fn default_rocksdb_target_file_size_base() -> u64 {
+    1073741824
+}
+
+pub fn default_rocksdb_level_zero_slowdown_writes_trigger() -> i32 {
+    0
+}
+
+pub fn default_rocksdb_level_zero_stop_writes_trigger() -> i32 {
+    2000
+}
+
+pub fn default_rocksdb_compaction_style() -> String {
+    "universal".to_string()
+}
+
+pub fn default_rocksdb_disable_auto_compactions() -> bool {
+    true
+}
+
+pub fn default_rocksdb_compaction_interval_sec() -> u64 {
+    21600
+}
+
+pub fn default_raft_engine() -> String {
+    "openraft".to_string()
+}
+
+pub fn default_raft_snapshot_logs_since_last() -> u64 {
+    5000
+}
+
+pub fn default_raft_max_in_snapshot_log_to_keep() -> u64 {
+    1000
+}
+
+pub fn default_raft_purge_batch_size() -> u64 {
+    1
+}
+
+pub fn default_raft_max_payload_entries() -> u64 {
+    300
+}
+
+pub fn default_raft_replication_lag_threshold() -> u64 {
+    5000
+}
+
+pub fn default_raft_snapshot_max_chunk_size() -> u64 {
+    3 * 1024 * 1024
+}
+
 
 static PLACEMENT_CENTER_CONF: OnceLock<PlacementCenterConfig> = OnceLock::new();
 
 pub fn init_placement_center_conf_by_path(config_path: &String) -> &'static PlacementCenterConfig {
-    PLACEMENT_CENTER_CONF.get_or_init(|| {
-        let content = match read_file(config_path) {
-            Ok(data) => data,
-            Err(err) => {
-                panic!("{}", err.to_string());
-            }
-        };
-        let pc_config: PlacementCenterConfig = toml::from_str(&content).unwrap();
-        pc_config
-    })
+    PLACEMENT_CENTER_CONF.get_or_init(|| load_placement_center_conf(config_path))
+}
+
+/// Parse the configuration file without making it the configuration of the process, for the
+/// settings only known once the node is started, like the id of a joining node.
+pub fn load_placement_center_conf(config_path: &String) -> PlacementCenterConfig {
+    let content = match read_file(config_path) {
+        Ok(data) => data,
+        Err(err) => {
+            panic!("{}", err.to_string());
+        }
+    };
+    let pc_config: PlacementCenterConfig = toml::from_str(&content).unwrap();
+    // Checked before the RocksDB is opened with it
+    if let Err(e) = RocksDBCompactionStyle::from_name(&pc_config.rocksdb.compaction_style) {
+        panic!("{}", e);
+    }
+    // A snapshot is streamed in chunks of this size, it cannot be empty
+    if pc_config.raft.snapshot_max_chunk_size == 0 {
+        panic!("snapshot_max_chunk_size of [raft] must be greater than 0");
+    }
+    pc_config
+}
+
+pub fn init_placement_center_conf(config: PlacementCenterConfig) -> &'static PlacementCenterConfig {
+    PLACEMENT_CENTER_CONF.get_or_init(|| config)
 }
 
 pub fn placement_center_conf() -> &'static PlacementCenterConfig {
@@ -65,7 +272,9 @@ mod tests {
     use crate::config::placement_center::{
         init_placement_center_conf_by_path,
         placement_center_conf,
+        RocksDBCompactionStyle,
     };
+    use crate::errors::RobustMQError;
 
     #[test]
     fn config_init_test() {
@@ -80,4 +289,17 @@ mod tests {
         assert_eq!(config.node_id, 1);
         assert_eq!(config.grpc_port, 1228);
     }
+
+    #[test]
+    fn compaction_style_test() {
+        assert_eq!(RocksDBCompactionStyle::from_name("level").unwrap(), RocksDBCompactionStyle::Level);
+        assert_eq!(
+            RocksDBCompactionStyle::from_name("universal").unwrap(),
+            RocksDBCompactionStyle::Universal
+        );
+        match RocksDBCompactionStyle::from_name("levle") {
+            Err(RobustMQError::UnknownCompactionStyle(name)) => assert_eq!(name, "levle"),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
 }
\ No newline at end of file
@@ -1,8 +1,13 @@
 
 use std::io;
-use tonic::Status;
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Status};
 use thiserror::Error;
 
+/// gRPC metadata of a NotLeader status holding the address of the leader, it is left out
+/// when the node does not know the leader.
+pub const LEADER_ADDR_METADATA: &str = "leader-addr";
+
 #[derive(Error, Debug)]
 pub enum RobustMQError {
     #[error("io error")]
@@ -31,4 +36,143 @@ pub enum RobustMQError {
 
     #[error("Grpc call of the node failed,Grpc status was {0}")]
     GrpcServerStatus(Status),
-}
\ No newline at end of file
+
+    #[error("Lease {0} does not exist")]
+    LeaseNotFound(u64),
+
+    #[error("Lease {0} already exists")]
+    LeaseAlreadyExists(u64),
+
+    #[error("The current node is not the leader, leader is [{0}]")]
+    NotLeader(String),
+
+    #[error("Revision {0} has been compacted, the oldest revision that can be watched is {1}")]
+    WatchRevisionCompacted(u64, u64),
+
+    #[error("Snapshot chunk at offset {0} failed the checksum check")]
+    SnapshotChecksumMismatch(u64),
+
+    #[error("Snapshot chunk starts at offset {0}, but {1} bytes have been received")]
+    SnapshotOffsetMismatch(u64, u64),
+
+    #[error("Raft log value was written in format version {0}, which this node cannot read")]
+    UnsupportedLogFormat(u8),
+
+    #[error("Namespace {0} does not exist")]
+    NamespaceNotFound(String),
+
+    #[error("Namespace {0} exceeded its {1} limit of {2}")]
+    NamespaceQuotaExceeded(String, String, u64),
+
+    #[error("Invalid namespace name {0}")]
+    InvalidNamespace(String),
+
+    #[error("Storage schema version {0} is newer than version {1} supported by this binary, refusing to start")]
+    SchemaVersionTooNew(u64, u64),
+
+    #[error("Storage migration {0} ({1}) failed, error message: {2}")]
+    MigrationFailed(u64, String, String),
+
+    #[error("Column family {0} does not exist")]
+    ColumnFamilyNotFound(String),
+
+    #[error("Backup target directory {0} already exists")]
+    BackupTargetExists(String),
+
+    #[error("Cannot restore into {0}, the data directory already holds a placement center")]
+    RestoreTargetNotEmpty(String),
+
+    #[error("Invalid backup {0}, error message: {1}")]
+    InvalidBackup(String, String),
+
+    #[error("Invalid export entry on line {0}, error message: {1}")]
+    InvalidExportEntry(u64, String),
+
+    #[error("Invalid encryption key file {0}, error message: {1}")]
+    InvalidEncryptionKeyFile(String, String),
+
+    #[error("Encryption key {0} is not in the key file")]
+    EncryptionKeyNotFound(u32),
+
+    #[error("Encryption of stored values is not enabled")]
+    EncryptionNotEnabled,
+
+    #[error("Failed to decrypt a stored value, error message: {0}")]
+    DecryptionFailed(String),
+
+    #[error("Invalid snapshot id {0}")]
+    InvalidSnapshotId(String),
+
+    #[error("Unsupported snapshot codec {0}")]
+    UnsupportedSnapshotCodec(u8),
+
+    #[error("Unsupported snapshot file version {0}")]
+    UnsupportedSnapshotVersion(u8),
+
+    #[error("Snapshot file ends before its last frame")]
+    SnapshotTruncated,
+
+    #[error("A compaction of column family {0} is already running")]
+    CompactionRunning(String),
+
+    #[error("Raft node has no snapshot, logs can only be purged once they are in a snapshot")]
+    RaftSnapshotNotFound,
+
+    #[error("Node {0} is not a member of the raft cluster")]
+    RaftNodeNotFound(u64),
+
+    #[error("Node {0} is already a member of the raft cluster with address {1}")]
+    RaftNodeAlreadyExists(u64, String),
+
+    #[error("Leadership transfer failed, error message: {0}")]
+    LeaderTransferFailed(String),
+
+    #[error("Unknown consensus engine {0}, expected openraft or raft-rs")]
+    UnknownConsensusEngine(String),
+
+    #[error("Unknown compaction_style {0} of the [rocksdb] section, expected universal or level")]
+    UnknownCompactionStyle(String),
+
+    #[error("{1} is not supported by the {0} consensus engine")]
+    UnsupportedByConsensusEngine(String, String),
+
+    #[error("The raft state machine is not running, the {0} request could not be sent to it")]
+    RaftStateMachineStopped(String),
+}
+
+/// Status of a request that only the leader can serve, sent to another node. Clients retry
+/// the request on the leader named by the status.
+pub fn not_leader_status(leader_addr: &str) -> Status {
+    let mut status = Status::failed_precondition(RobustMQError::NotLeader(leader_addr.to_string()).to_string());
+    if let Ok(value) = MetadataValue::try_from(leader_addr) {
+        if !leader_addr.is_empty() {
+            status.metadata_mut().insert(LEADER_ADDR_METADATA, value);
+        }
+    }
+    status
+}
+
+/// The leader named by a status built by `not_leader_status`, None for any other status or
+/// when the node did not know the leader.
+pub fn status_leader_addr(status: &Status) -> Option<String> {
+    if status.code() != Code::FailedPrecondition {
+        return None;
+    }
+    match status.metadata().get(LEADER_ADDR_METADATA) {
+        Some(value) => value.to_str().ok().map(|addr| addr.to_string()),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Status;
+    use super::{not_leader_status, status_leader_addr};
+
+    #[test]
+    fn not_leader_status_names_the_leader() {
+        assert_eq!(status_leader_addr(&not_leader_status("127.0.0.1:1228")), Some("127.0.0.1:1228".to_string()));
+        assert_eq!(status_leader_addr(&not_leader_status("")), None);
+        assert_eq!(status_leader_addr(&Status::cancelled("leader-addr")), None);
+    }
+}
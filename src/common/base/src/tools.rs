@@ -32,3 +32,17 @@ pub fn now_second() -> u64 {
         .unwrap()
         .as_secs()
 }
+
+pub fn now_mills() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+pub fn now_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos()
+}
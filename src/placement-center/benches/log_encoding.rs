@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use openraft::{CommittedLeaderId, Entry, EntryPayload, LogId};
+use common_base::config::placement_center::PlacementCenterConfig;
+use placement_center::openraft::route::AppRequestData;
+use placement_center::openraft::sotre::log_store::LogStore;
+use placement_center::openraft::sotre::open_raft_db;
+use placement_center::openraft::typeconfig::TypeConfig;
+use placement_center::storage::rocksdb::RocksDBEngine;
+
+const ENTRIES_PER_APPEND: u64 = 1000;
+
+fn entries(start: u64) -> Vec<Entry<TypeConfig>> {
+    let leader_id = CommittedLeaderId::new(1, 1);
+    (start..start + ENTRIES_PER_APPEND)
+        .map(|index| Entry {
+            log_id: LogId::new(leader_id, index),
+            payload: EntryPayload::Normal(AppRequestData::Set {
+                key: format!("/broker/cluster-1/node-{}", index),
+                value: "{\"ip\":\"127.0.0.1\",\"port\":1883,\"status\":\"running\"}".to_string(),
+                lease_id: 0,
+            }),
+        })
+        .collect()
+}
+
+/// Append throughput of the log store with the binary encoding, compared with writing
+/// the same entries as serde_json the way the log store did before.
+fn append(c: &mut Criterion) {
+    let db_path = "/tmp/tmp_bench_log_encoding";
+    let _ = std::fs::remove_dir_all(db_path);
+    let mut config = PlacementCenterConfig::default();
+    config.data_path = db_path.to_string();
+    let db = Arc::new(open_raft_db(format!("{}/openraft", db_path)));
+    let log_store = LogStore::new(db.clone(), Arc::new(RocksDBEngine::new(&config))).unwrap();
+
+    let mut group = c.benchmark_group("log_append");
+    group.throughput(Throughput::Elements(ENTRIES_PER_APPEND));
+
+    group.bench_function("json", |b| {
+        b.iter_batched(
+            || entries(1),
+            |entries| {
+                let logs = db.cf_handle("_raft_logs").unwrap();
+                for entry in entries {
+                    db.put_cf(
+                        logs,
+                        entry.log_id.index.to_be_bytes(),
+                        serde_json::to_vec(&entry).unwrap(),
+                    )
+                    .unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("binary", |b| {
+        b.iter_batched(
+            || entries(1),
+            |entries| log_store.append_(entries).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+    drop(log_store);
+    drop(db);
+    let _ = std::fs::remove_dir_all(db_path);
+}
+
+criterion_group!(benches, append);
+criterion_main!(benches);
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use common_base::errors::RobustMQError;
+use crate::raft::apply::StorageData;
+
+pub mod openraft_engine;
+pub mod raft_rs_engine;
+
+pub const ENGINE_OPENRAFT: &str = "openraft";
+pub const ENGINE_RAFT_RS: &str = "raft-rs";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsensusEngineType {
+    OpenRaft,
+    RaftRs,
+}
+
+impl ConsensusEngineType {
+    /// The engine named by the `engine` option of the `[raft]` section.
+    pub fn from_name(name: &str) -> Result<Self, RobustMQError> {
+        match name {
+            ENGINE_OPENRAFT => Ok(ConsensusEngineType::OpenRaft),
+            ENGINE_RAFT_RS => Ok(ConsensusEngineType::RaftRs),
+            _ => Err(RobustMQError::UnknownConsensusEngine(name.to_string())),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConsensusEngineType::OpenRaft => ENGINE_OPENRAFT,
+            ConsensusEngineType::RaftRs => ENGINE_RAFT_RS,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MembershipNode {
+    pub node_id: u64,
+    pub rpc_addr: String,
+    /// Learners only replicate the log, they do not vote
+    pub voter: bool,
+}
+
+/// Membership config of the cluster, with the index of the log holding it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MembershipConfig {
+    pub log_index: u64,
+    pub nodes: Vec<MembershipNode>,
+}
+
+/// What this node knows about the cluster, as seen by its consensus engine.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConsensusStatus {
+    pub engine: String,
+    pub node_id: u64,
+    /// 0 when no leader is known
+    pub leader_id: u64,
+    /// Empty when no leader is known
+    pub leader_addr: String,
+    pub role: String,
+}
+
+/// Replicates the writes of the placement center. The services only talk to the cluster
+/// through this trait, the engine is chosen by the `engine` option of the `[raft]` section.
+#[tonic::async_trait]
+pub trait ConsensusEngine: Send + Sync {
+    fn engine_type(&self) -> ConsensusEngineType;
+
+    /// Replicate the data and wait until the local state machine applied it. Returns the reply
+    /// of the state machine, as `DataRoute::route` encodes it.
+    async fn propose(&self, data: StorageData, action: &str) -> Result<Vec<u8>, RobustMQError>;
+
+    /// Wait until a read of the local storage observes every write committed before the call.
+    async fn read_index(&self, action: &str) -> Result<(), RobustMQError>;
+
+    fn is_leader(&self) -> bool;
+
+    /// gRPC address of the leader, empty when no leader is known.
+    fn leader_addr(&self) -> String;
+
+    fn membership(&self) -> MembershipConfig;
+
+    /// Add a node that replicates the log without voting. Returns once the node caught up
+    /// with the log of the leader. A node id of 0 asks the leader to assign the next free id,
+    /// the returned config holds the node under its address.
+    async fn add_learner(&self, node_id: u64, rpc_addr: String) -> Result<MembershipConfig, RobustMQError>;
+
+    async fn promote_voter(&self, node_id: u64) -> Result<MembershipConfig, RobustMQError>;
+
+    async fn remove_node(&self, node_id: u64) -> Result<MembershipConfig, RobustMQError>;
+
+    fn status(&self) -> ConsensusStatus;
+}
@@ -0,0 +1,331 @@
+use std::sync::Arc;
+use std::time::Duration;
+use openraft::error::{CheckIsLeaderError, ClientWriteError, RaftError};
+use openraft::Raft;
+use prost::Message;
+use tokio::sync::Mutex;
+use clients::placement::openraft::call::placement_openraft_read_index;
+use clients::poll::ClientPool;
+use common_base::errors::RobustMQError;
+use protocol::kv::{BatchDeleteRequest, BatchSetRequest, CompareAndSwapReply, CompareAndSwapRequest, DeleteRequest, LeaseGrantReply, LeaseGrantRequest, LeaseRevokeRequest, NamespaceSetRequest, SetRequest, TxnReply, TxnRequest};
+use protocol::openraft::ReadIndexRequest;
+use crate::consensus::{ConsensusEngine, ConsensusEngineType, ConsensusStatus, MembershipConfig};
+use crate::openraft::membership::{add_learner, assign_learner, membership_config, promote_voter, remove_node};
+use crate::openraft::raft_node::{Node, NodeId};
+use crate::openraft::route::{AppRequestData, AppResponseData};
+use crate::openraft::typeconfig::TypeConfig;
+use crate::raft::apply::{StorageData, StorageDataType};
+use crate::raft::kv::{kv_compare, txn_batch_ops};
+use crate::storage::export::ImportBatch;
+
+/// How long a read waits for the local state machine to apply the read index of the leader.
+const READ_INDEX_TIMEOUT_MS: u64 = 30000;
+
+/// Consensus engine backed by the openraft node.
+pub struct OpenRaftEngine {
+    raft_node: Raft<TypeConfig>,
+    client_poll: Arc<ClientPool>,
+    /// Last id assigned to a node joining without one, ids are assigned one at a time
+    last_assigned_id: Mutex<NodeId>,
+}
+
+impl OpenRaftEngine {
+    pub fn new(raft_node: Raft<TypeConfig>, client_poll: Arc<ClientPool>) -> Self {
+        OpenRaftEngine {
+            raft_node,
+            client_poll,
+            last_assigned_id: Mutex::new(0),
+        }
+    }
+
+    fn leader(&self) -> Option<Node> {
+        let metrics = self.raft_node.metrics().borrow().clone();
+        let leader_id = metrics.current_leader?;
+        metrics.membership_config.membership().get_node(&leader_id).cloned()
+    }
+}
+
+#[tonic::async_trait]
+impl ConsensusEngine for OpenRaftEngine {
+    fn engine_type(&self) -> ConsensusEngineType {
+        ConsensusEngineType::OpenRaft
+    }
+
+    async fn propose(&self, data: StorageData, _action: &str) -> Result<Vec<u8>, RobustMQError> {
+        let request = app_request_data(data)?;
+        let resp = match self.raft_node.client_write(request.clone()).await {
+            Ok(resp) => resp.data,
+            // Leadership moved after the request was checked
+            Err(RaftError::APIError(ClientWriteError::ForwardToLeader(forward))) => {
+                return Err(RobustMQError::NotLeader(
+                    forward.leader_node.map(|node| node.rpc_addr).unwrap_or_default(),
+                ));
+            }
+            Err(e) => return Err(RobustMQError::CommonError(e.to_string())),
+        };
+        if let Some(e) = resp.error {
+            return Err(RobustMQError::CommonError(e));
+        }
+
+        Ok(app_reply(&request, resp))
+    }
+
+    /// The leader confirms its leadership itself. A follower asks the leader for the read index
+    /// and serves the read once its own state machine applied it.
+    async fn read_index(&self, action: &str) -> Result<(), RobustMQError> {
+        let leader_addr = match self.raft_node.ensure_linearizable().await {
+            Ok(_) => return Ok(()),
+            Err(RaftError::APIError(CheckIsLeaderError::ForwardToLeader(forward))) => {
+                forward.leader_node.map(|node| node.rpc_addr).unwrap_or_default()
+            }
+            Err(e) => return Err(RobustMQError::CommonError(e.to_string())),
+        };
+        if leader_addr.is_empty() {
+            return Err(RobustMQError::NotLeader(leader_addr));
+        }
+
+        let reply = placement_openraft_read_index(
+            self.client_poll.clone(),
+            vec![leader_addr],
+            ReadIndexRequest {},
+        )
+        .await?;
+        match self
+            .raft_node
+            .wait(Some(Duration::from_millis(READ_INDEX_TIMEOUT_MS)))
+            .applied_index_at_least(Some(reply.log_index), action)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(RobustMQError::RaftLogCommitTimeout(action.to_string())),
+        }
+    }
+
+    fn is_leader(&self) -> bool {
+        let metrics = self.raft_node.metrics().borrow().clone();
+        metrics.current_leader == Some(metrics.id)
+    }
+
+    fn leader_addr(&self) -> String {
+        match self.leader() {
+            Some(node) => node.rpc_addr,
+            None => "".to_string(),
+        }
+    }
+
+    fn membership(&self) -> MembershipConfig {
+        membership_config(&self.raft_node)
+    }
+
+    async fn add_learner(&self, node_id: u64, rpc_addr: String) -> Result<MembershipConfig, RobustMQError> {
+        if node_id == 0 {
+            let mut last_assigned_id = self.last_assigned_id.lock().await;
+            return assign_learner(&self.raft_node, &mut last_assigned_id, rpc_addr).await;
+        }
+        add_learner(&self.raft_node, node_id, rpc_addr).await
+    }
+
+    async fn promote_voter(&self, node_id: u64) -> Result<MembershipConfig, RobustMQError> {
+        promote_voter(&self.raft_node, node_id).await
+    }
+
+    async fn remove_node(&self, node_id: u64) -> Result<MembershipConfig, RobustMQError> {
+        remove_node(&self.raft_node, node_id).await
+    }
+
+    fn status(&self) -> ConsensusStatus {
+        let metrics = self.raft_node.metrics().borrow().clone();
+        ConsensusStatus {
+            engine: self.engine_type().name().to_string(),
+            node_id: metrics.id,
+            leader_id: metrics.current_leader.unwrap_or(0),
+            leader_addr: self.leader_addr(),
+            role: format!("{:?}", metrics.state),
+        }
+    }
+}
+
+/// The openraft request of the data proposed by a service. The keys are already namespaced.
+pub fn app_request_data(data: StorageData) -> Result<AppRequestData, RobustMQError> {
+    match data.data_type {
+        StorageDataType::KvSet => {
+            let req: SetRequest = decode(&data.value)?;
+            Ok(AppRequestData::Set {
+                key: req.key,
+                value: req.value,
+                lease_id: req.lease_id,
+            })
+        }
+        StorageDataType::KvDelete => {
+            let req: DeleteRequest = decode(&data.value)?;
+            Ok(AppRequestData::Delete { key: req.key })
+        }
+        StorageDataType::KvCompareAndSwap => {
+            let req: CompareAndSwapRequest = decode(&data.value)?;
+            Ok(AppRequestData::CompareAndSwap {
+                key: req.key,
+                expected_value: req.expected_value,
+                new_value: req.new_value,
+            })
+        }
+        StorageDataType::KvBatchSet => {
+            let req: BatchSetRequest = decode(&data.value)?;
+            Ok(AppRequestData::BatchSet {
+                kvs: req.kvs.into_iter().map(|kv| (kv.key, kv.value)).collect(),
+            })
+        }
+        StorageDataType::KvBatchDelete => {
+            let req: BatchDeleteRequest = decode(&data.value)?;
+            Ok(AppRequestData::BatchDelete { keys: req.keys })
+        }
+        StorageDataType::NamespaceSet => {
+            let req: NamespaceSetRequest = decode(&data.value)?;
+            Ok(AppRequestData::NamespaceSet {
+                namespace: req.namespace,
+                max_keys: req.max_keys,
+                max_bytes: req.max_bytes,
+                max_value_size: req.max_value_size,
+            })
+        }
+        StorageDataType::Import => {
+            let batch: ImportBatch = serde_json::from_slice(&data.value)?;
+            Ok(AppRequestData::Import {
+                namespaces: batch.namespaces,
+                kvs: batch.kvs,
+            })
+        }
+        StorageDataType::KvTxn => {
+            let req: TxnRequest = decode(&data.value)?;
+            Ok(AppRequestData::Txn {
+                compares: req.compare.iter().map(kv_compare).collect(),
+                success: txn_batch_ops(req.success),
+                failure: txn_batch_ops(req.failure),
+            })
+        }
+        StorageDataType::LeaseGrant => {
+            let req: LeaseGrantRequest = decode(&data.value)?;
+            Ok(AppRequestData::LeaseGrant {
+                lease_id: req.lease_id,
+                ttl: req.ttl,
+            })
+        }
+        StorageDataType::LeaseRevoke => {
+            let req: LeaseRevokeRequest = decode(&data.value)?;
+            Ok(AppRequestData::LeaseRevoke {
+                lease_id: req.lease_id,
+            })
+        }
+    }
+}
+
+// The reply of an applied request, encoded the way the raft-rs state machine encodes it
+fn app_reply(request: &AppRequestData, resp: AppResponseData) -> Vec<u8> {
+    match request {
+        AppRequestData::CompareAndSwap { .. } => {
+            let reply = CompareAndSwapReply {
+                success: resp.success,
+                current_value: resp.value.unwrap_or_default(),
+            };
+            CompareAndSwapReply::encode_to_vec(&reply)
+        }
+        AppRequestData::Txn { .. } => {
+            let reply = TxnReply {
+                succeeded: resp.success,
+            };
+            TxnReply::encode_to_vec(&reply)
+        }
+        AppRequestData::LeaseGrant { ttl, .. } => {
+            // The state machine answers with the id it granted the lease with
+            let reply = LeaseGrantReply {
+                lease_id: resp.value.and_then(|id| id.parse().ok()).unwrap_or_default(),
+                ttl: *ttl,
+            };
+            LeaseGrantReply::encode_to_vec(&reply)
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn decode<T: Message + Default>(value: &[u8]) -> Result<T, RobustMQError> {
+    match T::decode(value) {
+        Ok(data) => Ok(data),
+        Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use protocol::openraft::ReadIndexRequest;
+    use protocol::kv::{Compare, LeaseRevokeRequest, SetRequest, TxnOp, TxnRequest};
+    use protocol::kv::txn_op::Op;
+    use crate::openraft::route::AppRequestData;
+    use crate::raft::apply::{StorageData, StorageDataType};
+    use crate::storage::export::ImportBatch;
+    use crate::storage::kv::{KvBatchOp, KvCompareTarget};
+    use crate::storage::namespace::Namespace;
+    use super::app_request_data;
+
+    #[test]
+    fn storage_data_to_app_request() {
+        let req = SetRequest {
+            key: "/kv/default/k1".to_string(),
+            value: "v1".to_string(),
+            lease_id: 7,
+            ..Default::default()
+        };
+        let data = StorageData::new(StorageDataType::KvSet, SetRequest::encode_to_vec(&req));
+        match app_request_data(data) {
+            Ok(AppRequestData::Set { key, value, lease_id }) => {
+                assert_eq!(key, "/kv/default/k1");
+                assert_eq!(value, "v1");
+                assert_eq!(lease_id, 7);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let revoke = LeaseRevokeRequest { lease_id: 7 };
+        let data = StorageData::new(
+            StorageDataType::LeaseRevoke,
+            LeaseRevokeRequest::encode_to_vec(&revoke),
+        );
+        match app_request_data(data) {
+            Ok(AppRequestData::LeaseRevoke { lease_id }) => assert_eq!(lease_id, 7),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let txn = TxnRequest {
+            compare: vec![Compare {
+                key: "/kv/default/k1".to_string(),
+                value: "v1".to_string(),
+                ..Default::default()
+            }],
+            success: vec![TxnOp {
+                op: Some(Op::Set(req)),
+            }],
+            ..Default::default()
+        };
+        let data = StorageData::new(StorageDataType::KvTxn, TxnRequest::encode_to_vec(&txn));
+        match app_request_data(data) {
+            Ok(AppRequestData::Txn { compares, success, failure }) => {
+                assert_eq!(compares[0].target, KvCompareTarget::Value("v1".to_string()));
+                assert!(matches!(&success[0], KvBatchOp::Set { lease_id: 7, .. }));
+                assert!(failure.is_empty());
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let batch = ImportBatch {
+            namespaces: vec![Namespace::new("tenant")],
+            kvs: vec![("/kv/tenant/a".to_string(), "v1".to_string())],
+        };
+        let data = StorageData::new(StorageDataType::Import, serde_json::to_vec(&batch).unwrap());
+        match app_request_data(data) {
+            Ok(AppRequestData::Import { namespaces, kvs }) => {
+                assert_eq!(namespaces, batch.namespaces);
+                assert_eq!(kvs, batch.kvs);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::{Arc, RwLock};
+use bincode::serialize;
+use raft::eraftpb::{ConfChange, ConfChangeType};
+use common_base::errors::RobustMQError;
+use common_base::tools::now_mills;
+use protocol::common::ClusterType;
+use crate::consensus::{ConsensusEngine, ConsensusEngineType, ConsensusStatus, MembershipConfig, MembershipNode};
+use crate::raft::apply::{RaftMachineApply, StorageData};
+use crate::raft::metadata::RaftGroupMetadata;
+use crate::raft::node::BrokerNode;
+
+/// Consensus engine backed by the raft-rs `RaftMachine`.
+///
+/// Learners are added with an AddLearnerNode conf change and promoted with an AddNode conf
+/// change of the same node.
+pub struct RaftRsEngine {
+    placement_center_storage: Arc<RaftMachineApply>,
+    placement_cluster: Arc<RwLock<RaftGroupMetadata>>,
+}
+
+impl RaftRsEngine {
+    pub fn new(
+        placement_center_storage: Arc<RaftMachineApply>,
+        placement_cluster: Arc<RwLock<RaftGroupMetadata>>,
+    ) -> Self {
+        RaftRsEngine {
+            placement_center_storage,
+            placement_cluster,
+        }
+    }
+
+    async fn conf_change(
+        &self,
+        change_type: ConfChangeType,
+        node_id: u64,
+        context: Vec<u8>,
+        action: &str,
+    ) -> Result<MembershipConfig, RobustMQError> {
+        if !self.is_leader() {
+            return Err(RobustMQError::NotLeader(self.leader_addr()));
+        }
+        let change = ConfChange {
+            change_type: change_type as i32,
+            node_id,
+            context: context.into(),
+            ..Default::default()
+        };
+        self.placement_center_storage
+            .apply_conf_raft_message(change, action.to_string())
+            .await?;
+        Ok(self.membership())
+    }
+
+    fn node_context(node: &BrokerNode) -> Result<Vec<u8>, RobustMQError> {
+        match serialize(node) {
+            Ok(data) => Ok(data),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl ConsensusEngine for RaftRsEngine {
+    fn engine_type(&self) -> ConsensusEngineType {
+        ConsensusEngineType::RaftRs
+    }
+
+    async fn propose(&self, data: StorageData, action: &str) -> Result<Vec<u8>, RobustMQError> {
+        self.placement_center_storage
+            .apply_propose_message_with_reply(data, action.to_string())
+            .await
+    }
+
+    async fn read_index(&self, action: &str) -> Result<(), RobustMQError> {
+        self.placement_center_storage.read_index(action.to_string()).await
+    }
+
+    fn is_leader(&self) -> bool {
+        self.placement_cluster.read().unwrap().is_leader()
+    }
+
+    fn leader_addr(&self) -> String {
+        self.placement_cluster.read().unwrap().leader_addr()
+    }
+
+    /// The log index is the one of the last conf change applied since the node started.
+    fn membership(&self) -> MembershipConfig {
+        let cluster = self.placement_cluster.read().unwrap();
+        let mut nodes: Vec<MembershipNode> = cluster
+            .peers
+            .values()
+            .map(|node| MembershipNode {
+                node_id: node.node_id,
+                rpc_addr: node.node_inner_addr.clone(),
+                voter: !cluster.is_learner(node.node_id),
+            })
+            .collect();
+        nodes.sort_by_key(|node| node.node_id);
+        MembershipConfig {
+            log_index: cluster.membership_log_index,
+            nodes,
+        }
+    }
+
+    async fn add_learner(&self, node_id: u64, rpc_addr: String) -> Result<MembershipConfig, RobustMQError> {
+        // Node ids of raft-rs are configured, 0 is the invalid id of raft-rs
+        if node_id == 0 {
+            return Err(RobustMQError::CommonError("Node id 0 is not a valid raft-rs node id".to_string()));
+        }
+        // Adding a node again is a no-op, joining nodes retry until they are added
+        let current = self.placement_cluster.read().unwrap().get_node_by_id(node_id).cloned();
+        if let Some(node) = current {
+            if node.node_inner_addr != rpc_addr {
+                return Err(RobustMQError::RaftNodeAlreadyExists(node_id, node.node_inner_addr));
+            }
+            return Ok(self.membership());
+        }
+
+        let (ip, _) = match rpc_addr.split_once(':') {
+            Some(data) => data,
+            None => return Err(RobustMQError::CommonError(format!("Invalid node address {}", rpc_addr))),
+        };
+        let node = BrokerNode {
+            cluster_name: self.placement_cluster.read().unwrap().local.cluster_name.clone(),
+            cluster_type: ClusterType::PlacementCenter.as_str_name().to_string(),
+            node_id,
+            node_ip: ip.to_string(),
+            node_inner_addr: rpc_addr.clone(),
+            extend: "".to_string(),
+            create_time: now_mills(),
+        };
+        let context = Self::node_context(&node)?;
+        self.conf_change(ConfChangeType::AddLearnerNode, node_id, context, "add_learner")
+            .await
+    }
+
+    /// Promoting a voter again is a no-op.
+    async fn promote_voter(&self, node_id: u64) -> Result<MembershipConfig, RobustMQError> {
+        if !self.is_leader() {
+            return Err(RobustMQError::NotLeader(self.leader_addr()));
+        }
+        let (node, learner) = {
+            let cluster = self.placement_cluster.read().unwrap();
+            match cluster.get_node_by_id(node_id) {
+                Some(node) => (node.clone(), cluster.is_learner(node_id)),
+                None => return Err(RobustMQError::RaftNodeNotFound(node_id)),
+            }
+        };
+        if !learner {
+            return Ok(self.membership());
+        }
+        let context = Self::node_context(&node)?;
+        self.conf_change(ConfChangeType::AddNode, node_id, context, "promote_voter")
+            .await
+    }
+
+    async fn remove_node(&self, node_id: u64) -> Result<MembershipConfig, RobustMQError> {
+        if self.placement_cluster.read().unwrap().get_node_by_id(node_id).is_none() {
+            return Err(RobustMQError::RaftNodeNotFound(node_id));
+        }
+        self.conf_change(ConfChangeType::RemoveNode, node_id, Vec::new(), "remove_node")
+            .await
+    }
+
+    fn status(&self) -> ConsensusStatus {
+        let cluster = self.placement_cluster.read().unwrap();
+        ConsensusStatus {
+            engine: self.engine_type().name().to_string(),
+            node_id: cluster.local.node_id,
+            leader_id: cluster.leader_id,
+            leader_addr: cluster.leader_addr(),
+            role: format!("{:?}", cluster.raft_role),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use dashmap::DashMap;
+use log::{error, info};
+use prost::Message;
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+use common_base::errors::RobustMQError;
+use common_base::tools::now_mills;
+use protocol::kv::LeaseRevokeRequest;
+use crate::consensus::ConsensusEngine;
+use crate::raft::apply::{StorageData, StorageDataType};
+use crate::storage::lease::{Lease, LeaseStorage};
+use crate::storage::rocksdb::RocksDBEngine;
+
+/// How long a lease whose TTL has passed since it was granted is kept after a node becomes
+/// leader, so that its clients can move their keep alive streams to the new leader.
+const LEADER_CHANGE_GRACE_MS: u128 = 5000;
+
+/// Tracks lease deadlines on the leader and revokes expired leases through raft.
+///
+/// Deadlines live in the memory of the leader. When a node becomes leader they are rebuilt
+/// from the persisted leases, each lease expires its TTL after it was granted unless it is
+/// kept alive. Keep alives are not persisted, so a lease that already outlived its TTL gets
+/// a short grace period instead.
+pub struct LeaseManager {
+    // lease_id -> expiration time in milliseconds
+    deadlines: DashMap<u64, u128>,
+    // Whether the deadlines were rebuilt since this node became leader
+    leading: AtomicBool,
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    engine: Arc<dyn ConsensusEngine>,
+}
+
+impl LeaseManager {
+    pub fn new(
+        rocksdb_engine_handler: Arc<RocksDBEngine>,
+        engine: Arc<dyn ConsensusEngine>,
+    ) -> Self {
+        LeaseManager {
+            deadlines: DashMap::with_capacity(8),
+            leading: AtomicBool::new(false),
+            rocksdb_engine_handler,
+            engine,
+        }
+    }
+
+    /// Refresh the deadline of the lease, returns the TTL of the lease or None if it does not exist.
+    pub fn keep_alive(&self, lease_id: u64) -> Result<Option<u64>, RobustMQError> {
+        if !self.is_leader() {
+            return Err(RobustMQError::NotLeader(self.engine.leader_addr()));
+        }
+
+        let lease_storage = LeaseStorage::new(self.rocksdb_engine_handler.clone());
+        match lease_storage.get(lease_id)? {
+            Some(lease) => {
+                self.deadlines
+                    .insert(lease_id, now_mills() + (lease.ttl as u128) * 1000);
+                Ok(Some(lease.ttl))
+            }
+            None => {
+                self.deadlines.remove(&lease_id);
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn remove(&self, lease_id: u64) {
+        self.deadlines.remove(&lease_id);
+    }
+
+    pub async fn start(&self, stop_sx: broadcast::Sender<bool>) {
+        info!("{}", "Lease expiration check thread started successfully");
+        let mut stop_rx = stop_sx.subscribe();
+        loop {
+            select! {
+                val = stop_rx.recv() => {
+                    if let Ok(flag) = val {
+                        if flag {
+                            info!("{}", "Lease expiration check thread stopped successfully");
+                            break;
+                        }
+                    }
+                }
+                _ = sleep(Duration::from_secs(1)) => {
+                    self.check_expired().await;
+                }
+            }
+        }
+    }
+
+    async fn check_expired(&self) {
+        if !self.is_leader() {
+            self.leading.store(false, Ordering::SeqCst);
+            self.deadlines.clear();
+            return;
+        }
+
+        let lease_storage = LeaseStorage::new(self.rocksdb_engine_handler.clone());
+        let leases = match lease_storage.list() {
+            Ok(data) => data,
+            Err(e) => {
+                error!("Failed to list leases with error message: {}", e);
+                return;
+            }
+        };
+
+        let now = now_mills();
+        if !self.leading.swap(true, Ordering::SeqCst) {
+            self.rebuild_deadlines(&leases, now);
+        }
+        let lease_ids: HashSet<u64> = leases.iter().map(|lease| lease.lease_id).collect();
+        self.deadlines.retain(|lease_id, _| lease_ids.contains(lease_id));
+
+        for lease in leases {
+            let deadline = *self
+                .deadlines
+                .entry(lease.lease_id)
+                .or_insert_with(|| lease_deadline(&lease, now));
+            if now < deadline {
+                continue;
+            }
+
+            info!(
+                "Lease {} has expired, revoking it and its {} keys",
+                lease.lease_id,
+                lease.keys.len()
+            );
+            let req = LeaseRevokeRequest {
+                lease_id: lease.lease_id,
+            };
+            let data = StorageData::new(
+                StorageDataType::LeaseRevoke,
+                LeaseRevokeRequest::encode_to_vec(&req),
+            );
+            match self.engine.propose(data, "lease_revoke").await {
+                Ok(_) => {
+                    self.deadlines.remove(&lease.lease_id);
+                }
+                Err(e) => {
+                    error!("Failed to revoke expired lease {} with error message: {}", lease.lease_id, e);
+                }
+            }
+        }
+    }
+
+    /// Set the deadlines of the leases this node did not track while it was not the leader.
+    /// A lease kept alive since the node became leader keeps the deadline of its keep alive.
+    fn rebuild_deadlines(&self, leases: &[Lease], now: u128) {
+        for lease in leases {
+            self.deadlines
+                .entry(lease.lease_id)
+                .or_insert_with(|| lease_deadline(lease, now));
+        }
+        info!("Rebuilt the deadlines of {} leases after becoming leader", leases.len());
+    }
+
+    fn is_leader(&self) -> bool {
+        self.engine.is_leader()
+    }
+}
+
+/// The deadline of a lease from the time it was granted, or after the grace period of a
+/// leader change if that is later.
+fn lease_deadline(lease: &Lease, now: u128) -> u128 {
+    let granted_deadline = (lease.create_time as u128 + lease.ttl as u128) * 1000;
+    granted_deadline.max(now + LEADER_CHANGE_GRACE_MS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lease_deadline, LEADER_CHANGE_GRACE_MS};
+    use crate::storage::lease::Lease;
+
+    #[test]
+    fn deadline_from_grant_time() {
+        let lease = Lease {
+            lease_id: 1,
+            ttl: 60,
+            keys: Vec::new(),
+            create_time: 100,
+        };
+        assert_eq!(lease_deadline(&lease, 110_000), 160_000);
+
+        // A lease that outlived its TTL is kept for the grace period of the leader change
+        assert_eq!(lease_deadline(&lease, 200_000), 200_000 + LEADER_CHANGE_GRACE_MS);
+    }
+}
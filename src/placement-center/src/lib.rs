@@ -7,74 +7,152 @@ use tokio::{
 use tokio::sync::broadcast::error::SendError;
 use clients::poll::ClientPool;
 use common_base::config::placement_center::placement_center_conf;
-use crate::openraft::raft_node::{create_raft_node, start_openraft_node};
+use crate::consensus::openraft_engine::OpenRaftEngine;
+use crate::consensus::raft_rs_engine::RaftRsEngine;
+use crate::consensus::{ConsensusEngine, ConsensusEngineType};
+use crate::lease::LeaseManager;
+use crate::openraft::raft_node::{create_raft_node, start_openraft_node, start_snapshot_scheduler};
 use crate::raft::apply::{RaftMachineApply, RaftMessage};
 use crate::raft::machine::RaftMachine;
 use crate::raft::metadata::RaftGroupMetadata;
-use crate::raft::peer::PeerMessage;
+use crate::raft::peer::{PeerMessage, PeersManager};
 use crate::raft::route::DataRoute;
 use crate::server::grpc::server::start_grpc_server;
 use crate::server::http::server::{start_http_server, HttpServerState};
+use crate::storage::compaction::CompactionManager;
 use crate::storage::raft::RaftMachineStorage;
 use crate::storage::rocksdb::RocksDBEngine;
+use crate::watch::WatchHub;
 
 pub mod server;
 pub mod raft;
 pub mod storage;
 pub mod openraft;
+pub mod lease;
+pub mod watch;
+pub mod consensus;
 mod requests;
 
 pub async fn start_server(stop_sx: broadcast::Sender<bool>) {
     let config = placement_center_conf();
-    let (raft_message_send, raft_message_recv) = mpsc::channel::<RaftMessage>(1000);
-    let (peer_message_send, _) = mpsc::channel::<PeerMessage>(1000);
+    let engine_type = match ConsensusEngineType::from_name(&config.raft.engine) {
+        Ok(engine_type) => engine_type,
+        Err(e) => panic!("{}", e),
+    };
+    let rocksdb_engine_handler: Arc<RocksDBEngine> = Arc::new(RocksDBEngine::new(&config));
 
-    let placement_cache = Arc::new(RwLock::new(RaftGroupMetadata::new()));
+    let watch_hub = Arc::new(WatchHub::new(10000));
 
-    let placement_center_storage = Arc::new(RaftMachineApply::new(raft_message_send));
-    let rocksdb_engine_handler: Arc<RocksDBEngine> = Arc::new(RocksDBEngine::new(&config));
+    let compaction_manager = Arc::new(CompactionManager::new(rocksdb_engine_handler.clone()));
 
-    let raft_machine_storage = Arc::new(RwLock::new(RaftMachineStorage::new(
-        rocksdb_engine_handler.clone(),
-    )));
+    let client_poll = Arc::new(ClientPool::new(3));
 
-    let data_route = Arc::new(RwLock::new(DataRoute::new(rocksdb_engine_handler.clone())));
+    // Only the selected engine is built, the services of the other one are not served
+    info!("Placement center uses the {} consensus engine", engine_type.name());
+    let mut openraft_node = None;
+    let mut openraft_log_store = None;
+    let mut placement_center_storage = None;
+    let engine: Arc<dyn ConsensusEngine> = match engine_type {
+        ConsensusEngineType::OpenRaft => {
+            let (raft_node, log_store) = create_raft_node(
+                client_poll.clone(),
+                rocksdb_engine_handler.clone(),
+                watch_hub.clone(),
+            ).await;
+            openraft_node = Some(raft_node.clone());
+            openraft_log_store = Some(log_store);
+            Arc::new(OpenRaftEngine::new(raft_node, client_poll.clone()))
+        }
+        ConsensusEngineType::RaftRs => {
+            let (raft_message_send, raft_message_recv) = mpsc::channel::<RaftMessage>(1000);
+            let (peer_message_send, peer_message_recv) = mpsc::channel::<PeerMessage>(1000);
 
-    let mut raft: RaftMachine = RaftMachine::new(
-        placement_cache.clone(),
-        data_route,
-        peer_message_send,
-        raft_message_recv,
-        stop_sx.subscribe(),
-        raft_machine_storage.clone(),
-    );
+            let placement_cache = Arc::new(RwLock::new(RaftGroupMetadata::new()));
 
-    let client_poll = Arc::new(ClientPool::new(3));
+            let raft_machine_apply = Arc::new(RaftMachineApply::new(raft_message_send));
 
-    let (openraft_node, kvs) = create_raft_node(client_poll.clone()).await;
+            let raft_machine_storage = Arc::new(RwLock::new(RaftMachineStorage::new(
+                rocksdb_engine_handler.clone(),
+            )));
+
+            let data_route = Arc::new(RwLock::new(DataRoute::new(
+                rocksdb_engine_handler.clone(),
+                watch_hub.clone(),
+            )));
+
+            let mut raft: RaftMachine = RaftMachine::new(
+                placement_cache.clone(),
+                data_route,
+                peer_message_send,
+                raft_message_recv,
+                stop_sx.subscribe(),
+                raft_machine_storage,
+            );
+            tokio::spawn(async move {
+                raft.run().await;
+            });
+            tokio::spawn(async move {
+                PeersManager::new(peer_message_recv).start().await;
+            });
+            placement_center_storage = Some(raft_machine_apply.clone());
+            Arc::new(RaftRsEngine::new(raft_machine_apply, placement_cache))
+        }
+    };
+
+    let lease_manager = Arc::new(LeaseManager::new(
+        rocksdb_engine_handler.clone(),
+        engine.clone(),
+    ));
 
     let raw_stop_sx = stop_sx.clone();
     let tmp_openraft_node = openraft_node.clone();
+    let http_rocksdb_engine_handler = rocksdb_engine_handler.clone();
+    let lease_manager_handler = lease_manager.clone();
+    let grpc_client_poll = client_poll.clone();
+    let grpc_engine = engine.clone();
     tokio::spawn(async move {
         start_grpc_server(
-            client_poll,
+            grpc_client_poll,
             tmp_openraft_node,
             placement_center_storage,
+            grpc_engine,
             rocksdb_engine_handler,
-            placement_cache,
+            lease_manager_handler,
+            watch_hub,
             raw_stop_sx,
         )
             .await;
     });
 
-    let tmp_openraft_node = openraft_node.clone();
+    let raw_stop_sx = stop_sx.clone();
     tokio::spawn(async move {
-        start_openraft_node(openraft_node).await;
+        lease_manager.start(raw_stop_sx).await;
     });
 
+    let raw_stop_sx = stop_sx.clone();
+    let compaction_manager_handler = compaction_manager.clone();
+    tokio::spawn(async move {
+        compaction_manager_handler
+            .start(raw_stop_sx, config.rocksdb.compaction_interval_sec)
+            .await;
+    });
+
+    let tmp_openraft_node = openraft_node.clone();
+    if let Some(openraft_node) = openraft_node {
+        let raw_stop_sx = stop_sx.clone();
+        let snapshot_openraft_node = openraft_node.clone();
+        tokio::spawn(async move {
+            start_snapshot_scheduler(snapshot_openraft_node, raw_stop_sx, config.raft.snapshot_interval_sec).await;
+        });
+
+        tokio::spawn(async move {
+            start_openraft_node(openraft_node, client_poll).await;
+        });
+    }
+
     let raw_stop_sx = stop_sx.clone();
     tokio::spawn(async move {
-        let state = HttpServerState::new(tmp_openraft_node, kvs);
+        let state = HttpServerState::new(tmp_openraft_node, openraft_log_store, engine, http_rocksdb_engine_handler, compaction_manager);
         start_http_server(state, raw_stop_sx).await;
     });
 
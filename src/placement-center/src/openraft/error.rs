@@ -1,6 +1,6 @@
 use crate::openraft::typeconfig::TypeConfig;
 use common_base::errors::RobustMQError;
-use openraft::error::{RPCError, Unreachable};
+use openraft::error::{Fatal, RPCError, StreamingError, Unreachable};
 use std::fmt::Display;
 
 #[derive(Debug)]
@@ -18,4 +18,8 @@ pub fn to_error<E: std::error::Error + 'static + Clone>(
     e: RobustMQError,
 ) -> RPCError<TypeConfig, E> {
     RPCError::Unreachable(Unreachable::new(&e))
-}
\ No newline at end of file
+}
+
+pub fn to_streaming_error(e: RobustMQError) -> StreamingError<TypeConfig, Fatal<TypeConfig>> {
+    StreamingError::Unreachable(Unreachable::new(&e))
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+use std::time::Duration;
+use log::{error, info};
+use tokio::time::sleep;
+use clients::placement::openraft::call::{placement_openraft_add_learner, placement_openraft_promote_voter};
+use clients::poll::ClientPool;
+use common_base::config::placement_center::PlacementCenterConfig;
+use common_base::errors::RobustMQError;
+use common_base::tools::{create_fold, file_exists, read_file};
+use protocol::openraft::{AddLearnerRequest, PromoteVoterRequest};
+use crate::openraft::raft_node::{ExampleRaft, NodeId};
+
+/// Seconds between two attempts to join the cluster.
+const JOIN_RETRY_SEC: u64 = 5;
+
+/// File of the data path holding the id of a node that joined a cluster.
+pub fn node_identity_path(data_path: &str) -> String {
+    format!("{}/node_identity", data_path)
+}
+
+/// The gRPC address other nodes reach this node at.
+pub fn local_rpc_addr(config: &PlacementCenterConfig) -> String {
+    format!("{}:{}", config.addr, config.grpc_port)
+}
+
+/// The id of a node started in join mode. The id persisted by an earlier start wins, then the
+/// configured id. A node configured with id 0 asks the leader to add it as a learner under an
+/// id the leader assigns, the leader hands out the same id again to a retry from the same
+/// address. The id is persisted before the node joins, so it keeps it across restarts.
+pub async fn resolve_node_id(config: &PlacementCenterConfig) -> Result<NodeId, RobustMQError> {
+    let path = node_identity_path(&config.data_path);
+    if file_exists(&path) {
+        let content = read_file(&path)?;
+        return match content.trim().parse::<NodeId>() {
+            Ok(node_id) => Ok(node_id),
+            Err(e) => Err(RobustMQError::CommonError(format!("Invalid node identity in {}: {}", path, e))),
+        };
+    }
+
+    let node_id = if config.node_id != 0 {
+        config.node_id
+    } else {
+        let rpc_addr = local_rpc_addr(config);
+        let client_poll = Arc::new(ClientPool::new(1));
+        let request = AddLearnerRequest {
+            node_id: 0,
+            rpc_addr: rpc_addr.clone(),
+        };
+        let reply = placement_openraft_add_learner(client_poll, config.join.clone(), request).await?;
+        match reply.nodes.iter().find(|node| node.rpc_addr == rpc_addr) {
+            Some(node) => node.node_id,
+            None => {
+                return Err(RobustMQError::CommonError(format!(
+                    "The cluster did not assign an id to the node at {}",
+                    rpc_addr
+                )))
+            }
+        }
+    };
+
+    create_fold(&config.data_path)?;
+    std::fs::write(&path, node_id.to_string())?;
+    info!("Node identity {} was persisted in {}", node_id, path);
+    Ok(node_id)
+}
+
+/// Join the cluster of the seed nodes: ask the leader to add this node as a learner, which
+/// returns once it has caught up, then to promote it to a voter. The seeds forward the
+/// requests to the leader. Retries until the node is a voter.
+pub async fn join_cluster(raft_node: ExampleRaft, client_poll: Arc<ClientPool>, config: &PlacementCenterConfig) {
+    let node_id = config.node_id;
+    let rpc_addr = local_rpc_addr(config);
+    loop {
+        let metrics = raft_node.metrics().borrow().clone();
+        if metrics.membership_config.membership().voter_ids().any(|voter| voter == node_id) {
+            info!("Node {} is a voter of the cluster", node_id);
+            return;
+        }
+
+        match join_once(client_poll.clone(), config, node_id, &rpc_addr).await {
+            Ok(()) => {
+                info!("Node {} joined the cluster through {:?}", node_id, config.join);
+                return;
+            }
+            Err(e) => error!("Node {} failed to join the cluster, error message: {}", node_id, e),
+        }
+        sleep(Duration::from_secs(JOIN_RETRY_SEC)).await;
+    }
+}
+
+async fn join_once(
+    client_poll: Arc<ClientPool>,
+    config: &PlacementCenterConfig,
+    node_id: NodeId,
+    rpc_addr: &str,
+) -> Result<(), RobustMQError> {
+    let request = AddLearnerRequest {
+        node_id,
+        rpc_addr: rpc_addr.to_string(),
+    };
+    placement_openraft_add_learner(client_poll.clone(), config.join.clone(), request).await?;
+    placement_openraft_promote_voter(client_poll, config.join.clone(), PromoteVoterRequest { node_id }).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::test_tools::TempDir;
+    use super::{node_identity_path, resolve_node_id};
+
+    #[tokio::test]
+    async fn node_identity_is_persisted() {
+        let dir = TempDir::new();
+        let mut config = dir.config();
+        config.join = vec!["127.0.0.1:1228".to_string()];
+        config.node_id = 5;
+
+        assert_eq!(resolve_node_id(&config).await.unwrap(), 5);
+        assert_eq!(std::fs::read_to_string(node_identity_path(&config.data_path)).unwrap(), "5");
+
+        // The persisted id wins over the configuration on the next start
+        config.node_id = 7;
+        assert_eq!(resolve_node_id(&config).await.unwrap(), 5);
+    }
+}
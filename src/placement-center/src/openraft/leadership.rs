@@ -0,0 +1,91 @@
+use std::time::Duration;
+use openraft::Raft;
+use serde::{Deserialize, Serialize};
+use common_base::errors::RobustMQError;
+use crate::openraft::raft_node::NodeId;
+use crate::openraft::typeconfig::TypeConfig;
+
+/// Time the new leader has to be elected after the leadership was handed over.
+const TRANSFER_TIMEOUT_MS: u64 = 10000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LeaderInfo {
+    pub leader_id: NodeId,
+    pub rpc_addr: String,
+}
+
+/// Move the leadership of this node to `target`, or to the voter whose log is the most up to
+/// date when no target is given. Returns once a new leader is confirmed, which is the target
+/// unless another voter won the election.
+pub async fn transfer_leader(
+    raft_node: &Raft<TypeConfig>,
+    target: Option<NodeId>,
+) -> Result<LeaderInfo, RobustMQError> {
+    let metrics = raft_node.metrics().borrow().clone();
+    let membership = metrics.membership_config.membership().clone();
+    if metrics.current_leader != Some(metrics.id) {
+        let leader_addr = metrics
+            .current_leader
+            .and_then(|leader_id| membership.get_node(&leader_id))
+            .map(|node| node.rpc_addr.clone())
+            .unwrap_or_default();
+        return Err(RobustMQError::NotLeader(leader_addr));
+    }
+
+    let target = match target {
+        Some(target) => {
+            if target == metrics.id {
+                return Err(RobustMQError::LeaderTransferFailed(format!(
+                    "node {} is already the leader",
+                    target
+                )));
+            }
+            if !membership.voter_ids().any(|voter| voter == target) {
+                return Err(RobustMQError::RaftNodeNotFound(target));
+            }
+            target
+        }
+        None => {
+            // The voter with the most replicated log catches up the fastest
+            let replication = metrics.replication.clone().unwrap_or_default();
+            let candidate = membership
+                .voter_ids()
+                .filter(|voter| *voter != metrics.id)
+                .max_by_key(|voter| replication.get(voter).cloned().flatten().map(|log_id| log_id.index));
+            match candidate {
+                Some(candidate) => candidate,
+                None => {
+                    return Err(RobustMQError::LeaderTransferFailed(
+                        "there is no other voter to transfer the leadership to".to_string(),
+                    ))
+                }
+            }
+        }
+    };
+
+    if let Err(e) = raft_node.trigger().transfer_leader(target).await {
+        return Err(RobustMQError::LeaderTransferFailed(e.to_string()));
+    }
+
+    let local_id = metrics.id;
+    let metrics = match raft_node
+        .wait(Some(Duration::from_millis(TRANSFER_TIMEOUT_MS)))
+        .metrics(
+            |metrics| metrics.current_leader.is_some() && metrics.current_leader != Some(local_id),
+            "new leader elected",
+        )
+        .await
+    {
+        Ok(metrics) => metrics,
+        Err(e) => return Err(RobustMQError::LeaderTransferFailed(e.to_string())),
+    };
+
+    let leader_id = metrics.current_leader.unwrap_or_default();
+    let rpc_addr = metrics
+        .membership_config
+        .membership()
+        .get_node(&leader_id)
+        .map(|node| node.rpc_addr.clone())
+        .unwrap_or_default();
+    Ok(LeaderInfo { leader_id, rpc_addr })
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeSet;
+use openraft::error::{ClientWriteError, RaftError};
+use openraft::{ChangeMembers, Membership, Raft};
+use common_base::errors::RobustMQError;
+use crate::consensus::{MembershipConfig, MembershipNode};
+use crate::openraft::raft_node::{Node, NodeId};
+use crate::openraft::typeconfig::TypeConfig;
+
+impl MembershipConfig {
+    fn new(log_index: u64, membership: &Membership<TypeConfig>) -> Self {
+        let voters: BTreeSet<NodeId> = membership.voter_ids().collect();
+        let nodes = membership
+            .nodes()
+            .map(|(node_id, node)| MembershipNode {
+                node_id: *node_id,
+                rpc_addr: node.rpc_addr.clone(),
+                voter: voters.contains(node_id),
+            })
+            .collect();
+        MembershipConfig { log_index, nodes }
+    }
+}
+
+/// The membership config last seen by this node.
+pub fn membership_config(raft_node: &Raft<TypeConfig>) -> MembershipConfig {
+    let metrics = raft_node.metrics().borrow().clone();
+    let stored = metrics.membership_config;
+    let log_index = stored.log_id().map(|log_id| log_id.index).unwrap_or(0);
+    MembershipConfig::new(log_index, stored.membership())
+}
+
+/// Add a node as a learner. Returns once the learner has caught up with the log of the leader,
+/// a learner can only be promoted then.
+pub async fn add_learner(
+    raft_node: &Raft<TypeConfig>,
+    node_id: NodeId,
+    rpc_addr: String,
+) -> Result<MembershipConfig, RobustMQError> {
+    // Adding a node again is a no-op, joining nodes retry until they are added
+    let current = membership_config(raft_node);
+    if let Some(node) = current.nodes.iter().find(|node| node.node_id == node_id) {
+        if node.rpc_addr != rpc_addr {
+            return Err(RobustMQError::RaftNodeAlreadyExists(node_id, node.rpc_addr.clone()));
+        }
+    }
+    let node = Node { node_id, rpc_addr };
+    match raft_node.add_learner(node_id, node, true).await {
+        Ok(resp) => Ok(changed_config(raft_node, resp)),
+        Err(e) => Err(write_error(e)),
+    }
+}
+
+/// Add a node joining without an id as a learner under the id following `last_assigned` and
+/// the largest id of the cluster. A node retrying with the same address gets the id it was
+/// assigned. The learner is not waited for, the node only starts once it knows its id.
+/// Callers assign one id at a time, so concurrent joins get distinct ids.
+pub async fn assign_learner(
+    raft_node: &Raft<TypeConfig>,
+    last_assigned: &mut NodeId,
+    rpc_addr: String,
+) -> Result<MembershipConfig, RobustMQError> {
+    let current = membership_config(raft_node);
+    if current.nodes.iter().any(|node| node.rpc_addr == rpc_addr) {
+        return Ok(current);
+    }
+    let max_id = current.nodes.iter().map(|node| node.node_id).max().unwrap_or(0);
+    let node_id = max_id.max(*last_assigned) + 1;
+    let node = Node {
+        node_id,
+        rpc_addr,
+    };
+    match raft_node.add_learner(node_id, node, false).await {
+        Ok(resp) => {
+            *last_assigned = node_id;
+            Ok(changed_config(raft_node, resp))
+        }
+        Err(e) => Err(write_error(e)),
+    }
+}
+
+/// Make a learner a voter.
+pub async fn promote_voter(raft_node: &Raft<TypeConfig>, node_id: NodeId) -> Result<MembershipConfig, RobustMQError> {
+    let change = ChangeMembers::AddVoterIds(BTreeSet::from([node_id]));
+    match raft_node.change_membership(change, false).await {
+        Ok(resp) => Ok(changed_config(raft_node, resp)),
+        Err(e) => Err(write_error(e)),
+    }
+}
+
+/// Remove a voter or a learner from the cluster.
+pub async fn remove_node(raft_node: &Raft<TypeConfig>, node_id: NodeId) -> Result<MembershipConfig, RobustMQError> {
+    let current = membership_config(raft_node);
+    let change = match current.nodes.iter().find(|node| node.node_id == node_id) {
+        Some(node) if node.voter => ChangeMembers::RemoveVoters(BTreeSet::from([node_id])),
+        Some(_) => ChangeMembers::RemoveNodes(BTreeSet::from([node_id])),
+        None => return Err(RobustMQError::RaftNodeNotFound(node_id)),
+    };
+    // A removed voter is not kept as a learner
+    match raft_node.change_membership(change, false).await {
+        Ok(resp) => Ok(changed_config(raft_node, resp)),
+        Err(e) => Err(write_error(e)),
+    }
+}
+
+fn changed_config(
+    raft_node: &Raft<TypeConfig>,
+    resp: openraft::raft::ClientWriteResponse<TypeConfig>,
+) -> MembershipConfig {
+    match resp.membership {
+        Some(membership) => MembershipConfig::new(resp.log_id.index, &membership),
+        None => membership_config(raft_node),
+    }
+}
+
+/// A membership change sent to a follower fails with NotLeader, naming the leader to retry on.
+fn write_error(e: RaftError<TypeConfig, ClientWriteError<TypeConfig>>) -> RobustMQError {
+    match e {
+        RaftError::APIError(ClientWriteError::ForwardToLeader(forward)) => RobustMQError::NotLeader(
+            forward.leader_node.map(|node| node.rpc_addr).unwrap_or_default(),
+        ),
+        e => RobustMQError::CommonError(e.to_string()),
+    }
+}
@@ -3,4 +3,7 @@ pub mod route;
 pub mod sotre;
 pub mod error;
 pub mod typeconfig;
-pub mod raft_node;
\ No newline at end of file
+pub mod raft_node;
+pub mod membership;
+pub mod leadership;
+pub mod join;
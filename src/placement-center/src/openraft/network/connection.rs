@@ -2,31 +2,37 @@ use std::future::Future;
 use std::sync::Arc;
 use bincode::{deserialize, serialize};
 use mobc::Connection;
-use openraft::error::{InstallSnapshotError, RPCError, RaftError};
+use openraft::error::{Fatal, RPCError, RaftError, ReplicationClosed, StreamingError};
 use openraft::network::RPCOption;
-use openraft::raft::{AppendEntriesRequest, AppendEntriesResponse, InstallSnapshotRequest, InstallSnapshotResponse, VoteRequest, VoteResponse};
-use openraft::RaftNetwork;
+use openraft::raft::{AppendEntriesRequest, AppendEntriesResponse, SnapshotResponse, VoteRequest, VoteResponse};
+use openraft::{OptionalSend, RaftNetwork, Snapshot, Vote};
+use tokio::select;
+use tokio::sync::mpsc;
+use tonic::codegen::tokio_stream::wrappers::ReceiverStream;
 use clients::placement::openraft::OpenRaftServiceManager;
 use clients::poll::ClientPool;
 use common_base::errors::RobustMQError;
-use protocol::openraft::{AppendRequest, SnapshotRequest};
-use crate::openraft::error::to_error;
+use protocol::openraft::{AppendRequest, SnapshotOffsetRequest};
+use crate::openraft::error::{to_error, to_streaming_error};
 use crate::openraft::raft_node::NodeId;
+use crate::openraft::sotre::snapshot::send_snapshot_chunks;
 use crate::openraft::typeconfig::TypeConfig;
 
 pub struct NetworkConnection {
     addr: String,
     client_poll: Arc<ClientPool>,
     target: NodeId,
+    snapshot_chunk_size: u64,
 }
 
 impl NetworkConnection {
 
-    pub fn new(addr: String, client_poll: Arc<ClientPool>, target: NodeId) -> Self {
+    pub fn new(addr: String, client_poll: Arc<ClientPool>, target: NodeId, snapshot_chunk_size: u64) -> Self {
         NetworkConnection {
             addr,
             client_poll,
-            target
+            target,
+            snapshot_chunk_size,
         }
     }
 
@@ -71,36 +77,44 @@ impl RaftNetwork<TypeConfig> for NetworkConnection {
         Ok(result)
     }
 
-    async fn install_snapshot(
+    async fn full_snapshot(
         &mut self,
-        req: InstallSnapshotRequest<TypeConfig>,
-        _option: RPCOption
-    ) -> Result<
-        InstallSnapshotResponse<TypeConfig>,
-        RPCError<TypeConfig, RaftError<TypeConfig, InstallSnapshotError>>,
-    >
+        vote: Vote<NodeId>,
+        snapshot: Snapshot<TypeConfig>,
+        cancel: impl Future<Output = ReplicationClosed> + OptionalSend + 'static,
+        _option: RPCOption,
+    ) -> Result<SnapshotResponse<TypeConfig>, StreamingError<TypeConfig, Fatal<TypeConfig>>>
     {
-        tracing::debug!(req = debug(&req), "install_snapshot");
+        tracing::debug!(meta = debug(&snapshot.meta), "full_snapshot");
 
         let mut c = match self.c().await {
             Ok(conn) => conn,
-            Err(e) => return Err(to_error(e)),
+            Err(e) => return Err(to_streaming_error(e)),
         };
 
-        let value = match serialize(&req) {
-            Ok(data) => data,
-            Err(e) => return Err(to_error(RobustMQError::CommonError(e.to_string()))),
+        // Resume from what the target kept of an earlier, interrupted transfer of this snapshot
+        let request = SnapshotOffsetRequest {
+            snapshot_id: snapshot.meta.snapshot_id.clone(),
+        };
+        let offset = match c.snapshot_offset(request).await {
+            Ok(reply) => reply.into_inner().offset,
+            Err(e) => return Err(to_streaming_error(RobustMQError::CommonError(e.to_string()))),
         };
 
-        let request = SnapshotRequest { value };
+        let (sx, rx) = mpsc::channel(2);
+        tokio::spawn(send_snapshot_chunks(vote, snapshot, offset, self.snapshot_chunk_size, sx));
 
-        let reply = match c.snapshot(request).await {
+        let reply = select! {
+            closed = cancel => return Err(StreamingError::Closed(closed)),
+            reply = c.snapshot_stream(ReceiverStream::new(rx)) => reply,
+        };
+        let reply = match reply {
             Ok(reply) => reply.into_inner(),
-            Err(e) => return Err(to_error(RobustMQError::CommonError(e.to_string()))),
+            Err(e) => return Err(to_streaming_error(RobustMQError::CommonError(e.to_string()))),
         };
         let result = match deserialize(&reply.value) {
             Ok(data) => data,
-            Err(e) => return Err(to_error(RobustMQError::CommonError(e.to_string()))),
+            Err(e) => return Err(to_streaming_error(RobustMQError::CommonError(e.to_string()))),
         };
 
         Ok(result)
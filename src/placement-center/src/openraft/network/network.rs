@@ -8,12 +8,15 @@ use crate::openraft::typeconfig::TypeConfig;
 
 pub struct Network {
     client_poll: Arc<ClientPool>,
+    // `snapshot_max_chunk_size` of `[raft]`
+    snapshot_chunk_size: u64,
 }
 
 impl Network {
-    pub fn new(client_poll: Arc<ClientPool>) -> Network {
+    pub fn new(client_poll: Arc<ClientPool>, snapshot_chunk_size: u64) -> Network {
         Network {
             client_poll,
+            snapshot_chunk_size,
         }
     }
 }
@@ -26,6 +29,6 @@ impl RaftNetworkFactory<TypeConfig> for Network {
     #[tracing::instrument(level = "debug", skip_all)]
     async fn new_client(&mut self, target: NodeId, node: &Node) -> Self::Network {
         let addr = format!("{}", node.rpc_addr);
-        NetworkConnection::new(addr, self.client_poll.clone(), target)
+        NetworkConnection::new(addr, self.client_poll.clone(), target, self.snapshot_chunk_size)
     }
 }
\ No newline at end of file
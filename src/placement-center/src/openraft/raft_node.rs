@@ -2,15 +2,23 @@ use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 use std::path::Path;
 use std::sync::{Arc};
-use log::info;
-use openraft::{Config, Raft};
+use std::time::Duration;
+use log::{error, info};
+use openraft::{Config, Raft, SnapshotPolicy};
 use openraft::error::{Fatal, InitializeError, RaftError};
-use tokio::sync::RwLock;
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
 use clients::poll::ClientPool;
-use common_base::config::placement_center::placement_center_conf;
+use common_base::config::placement_center::{placement_center_conf, PlacementCenterRaft};
+use crate::openraft::join::join_cluster;
 use crate::openraft::network::network::Network;
+use crate::openraft::sotre::log_store::LogStore;
 use crate::openraft::sotre::new_storage;
+use crate::openraft::sotre::snapshot::SnapshotFiles;
 use crate::openraft::typeconfig::TypeConfig;
+use crate::storage::rocksdb::RocksDBEngine;
+use crate::watch::WatchHub;
 
 pub type NodeId = u64;
 
@@ -54,8 +62,14 @@ pub mod typ {
 
 pub type ExampleRaft = openraft::Raft<TypeConfig>;
 
-pub async fn start_openraft_node(raft_node: ExampleRaft) {
+pub async fn start_openraft_node(raft_node: ExampleRaft, client_poll: Arc<ClientPool>) {
     let conf = placement_center_conf();
+    // A node started with seed addresses joins their cluster instead of forming one from `nodes`
+    if !conf.join.is_empty() {
+        join_cluster(raft_node, client_poll, conf).await;
+        return;
+    }
+
     let mut nodes = BTreeMap::new();
     for (node_id, addr) in conf.nodes.clone() {
         let mut addr = addr.to_string();
@@ -97,30 +111,111 @@ pub fn calc_init_node(nodes: &BTreeMap<u64, Node>) -> u64 {
     node_ids.first().unwrap().clone()
 }
 
-pub async fn create_raft_node(
-    client_poll: Arc<ClientPool>,
-) -> (Raft<TypeConfig>, Arc<RwLock<BTreeMap<String, String>>>) {
-    let config = Config {
+/// Directory of the RocksDB holding the openraft log and vote.
+pub fn openraft_db_path(data_path: &str) -> String {
+    format!("{}/_engine_storage", data_path)
+}
+
+/// Openraft configuration of the node, snapshot and replication options come from `[raft]`.
+pub fn raft_config(raft_conf: &PlacementCenterRaft) -> Config {
+    let snapshot_policy = if raft_conf.snapshot_logs_since_last == 0 {
+        SnapshotPolicy::Never
+    } else {
+        SnapshotPolicy::LogsSinceLast(raft_conf.snapshot_logs_since_last)
+    };
+    Config {
         heartbeat_interval: 250,
         election_timeout_min: 299,
+        snapshot_policy,
+        max_in_snapshot_log_to_keep: raft_conf.max_in_snapshot_log_to_keep,
+        purge_batch_size: raft_conf.purge_batch_size,
+        max_payload_entries: raft_conf.max_payload_entries,
+        replication_lag_threshold: raft_conf.replication_lag_threshold,
+        snapshot_max_chunk_size: raft_conf.snapshot_max_chunk_size,
         ..Default::default()
-    };
+    }
+}
 
-    let config = Arc::new(config.validate().unwrap());
+/// Create the openraft node. Its log store is returned with it, to re-encrypt the log after
+/// a key rotation.
+pub async fn create_raft_node(
+    client_poll: Arc<ClientPool>,
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    watch_hub: Arc<WatchHub>,
+) -> (Raft<TypeConfig>, LogStore) {
     let conf = placement_center_conf();
-    let path = format!("{}/_engine_storage", conf.data_path.clone());
+    let config = match raft_config(&conf.raft).validate() {
+        Ok(config) => Arc::new(config),
+        Err(e) => panic!("Invalid [raft] configuration, {}", e),
+    };
+    let path = openraft_db_path(&conf.data_path);
     let dir = Path::new(&path);
-    let (log_store, state_machine_store) = new_storage(&dir).await;
-    let kvs = state_machine_store.data.kvs.clone();
+    let snapshot_files = SnapshotFiles::new(&conf.data_path);
+    let (log_store, state_machine_store) =
+        new_storage(&dir, snapshot_files, rocksdb_engine_handler, watch_hub).await;
 
-    let network = Network::new(client_poll);
+    let network = Network::new(client_poll, conf.raft.snapshot_max_chunk_size);
     let raft = openraft::Raft::new(
         conf.node_id,
         config.clone(),
         network,
-        log_store,
+        log_store.clone(),
         state_machine_store,
     ).await.unwrap();
 
-    (raft, kvs)
-}
\ No newline at end of file
+    (raft, log_store)
+}
+
+/// Build a snapshot every `interval_sec` seconds, on top of the snapshots built every
+/// `snapshot_logs_since_last` logs. Nothing is built when no log was applied since the last one.
+pub async fn start_snapshot_scheduler(raft_node: ExampleRaft, stop_sx: broadcast::Sender<bool>, interval_sec: u64) {
+    if interval_sec == 0 {
+        return;
+    }
+    info!("{}", "Scheduled snapshot thread started successfully");
+    let mut stop_rx = stop_sx.subscribe();
+    loop {
+        select! {
+            val = stop_rx.recv() => {
+                if let Ok(flag) = val {
+                    if flag {
+                        info!("{}", "Scheduled snapshot thread stopped successfully");
+                        break;
+                    }
+                }
+            }
+            _ = sleep(Duration::from_secs(interval_sec)) => {
+                let metrics = raft_node.metrics().borrow().clone();
+                let last_applied = metrics.last_applied.map(|log_id| log_id.index);
+                let snapshot = metrics.snapshot.map(|log_id| log_id.index);
+                if last_applied <= snapshot {
+                    continue;
+                }
+                if let Err(e) = raft_node.trigger().snapshot().await {
+                    error!("Scheduled snapshot failed, error message: {}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openraft::SnapshotPolicy;
+    use common_base::config::placement_center::PlacementCenterRaft;
+    use super::raft_config;
+
+    #[test]
+    fn raft_config_from_conf() {
+        let config = raft_config(&PlacementCenterRaft::default()).validate().unwrap();
+        assert_eq!(config.snapshot_policy, SnapshotPolicy::LogsSinceLast(5000));
+        assert_eq!(config.max_in_snapshot_log_to_keep, 1000);
+        assert_eq!(config.max_payload_entries, 300);
+
+        let mut raft_conf = PlacementCenterRaft::default();
+        raft_conf.snapshot_logs_since_last = 0;
+        raft_conf.max_payload_entries = 0;
+        assert_eq!(raft_config(&raft_conf).snapshot_policy, SnapshotPolicy::Never);
+        assert!(raft_config(&raft_conf).validate().is_err());
+    }
+}
@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use crate::storage::kv::{KvBatchOp, KvCompare};
+use crate::storage::namespace::Namespace;
 
 /**
  * Here you will set the types of request that will interact with the raft nodes.
@@ -8,9 +10,34 @@ use serde::{Deserialize, Serialize};
  */
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum AppRequestData {
-    Set { key: String, value: String },
+    /// A non-zero `lease_id` attaches the key to that lease.
+    Set {
+        key: String,
+        value: String,
+        #[serde(default)]
+        lease_id: u64,
+    },
 
     Delete { key: String },
+
+    CompareAndSwap { key: String, expected_value: String, new_value: String },
+
+    BatchSet { kvs: Vec<(String, String)> },
+
+    BatchDelete { keys: Vec<String> },
+
+    NamespaceSet { namespace: String, max_keys: u64, max_bytes: u64, max_value_size: u64 },
+
+    /// A batch of a logical import, see `storage::export`. It holds either namespaces or keys.
+    Import { namespaces: Vec<Namespace>, kvs: Vec<(String, String)> },
+
+    /// Applies `success` when every compare holds and `failure` otherwise, in one write batch.
+    Txn { compares: Vec<KvCompare>, success: Vec<KvBatchOp>, failure: Vec<KvBatchOp> },
+
+    LeaseGrant { lease_id: u64, ttl: u64 },
+
+    /// Deletes the lease together with the keys still attached to it.
+    LeaseRevoke { lease_id: u64 },
 }
 
 /**
@@ -24,4 +51,11 @@ pub enum AppRequestData {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AppResponseData {
     pub value: Option<String>,
+
+    /// Whether a conditional request such as `CompareAndSwap` took effect.
+    pub success: bool,
+
+    /// Why the request was rejected without being applied, e.g. a namespace quota.
+    #[serde(default)]
+    pub error: Option<String>,
 }
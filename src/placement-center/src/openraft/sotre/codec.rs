@@ -0,0 +1,75 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use common_base::errors::RobustMQError;
+
+/// First byte of every value written by this codec. It can never start a JSON document,
+/// which tells the values apart from those written before the header existed.
+pub const FORMAT_MAGIC: u8 = 0xFE;
+
+/// Encoding of the values that follow the header.
+/// 1: bincode
+pub const LOG_FORMAT_VERSION: u8 = 1;
+
+/// Encode a log entry, vote or log id as a two byte header followed by the bincode body.
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, RobustMQError> {
+    let mut buf = vec![FORMAT_MAGIC, LOG_FORMAT_VERSION];
+    match bincode::serialize_into(&mut buf, value) {
+        Ok(()) => Ok(buf),
+        Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+    }
+}
+
+/// Decode a value written by `encode`, or a serde_json value written before the header existed.
+pub fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, RobustMQError> {
+    if !is_encoded(data) {
+        return Ok(serde_json::from_slice(data)?);
+    }
+    match data.get(1) {
+        Some(&LOG_FORMAT_VERSION) => match bincode::deserialize(&data[2..]) {
+            Ok(value) => Ok(value),
+            Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+        },
+        Some(version) => Err(RobustMQError::UnsupportedLogFormat(*version)),
+        None => Err(RobustMQError::CommonError(
+            "Log value is missing the format version".to_string(),
+        )),
+    }
+}
+
+/// Whether the value starts with the header of the current format.
+pub fn is_current(data: &[u8]) -> bool {
+    is_encoded(data) && data.get(1) == Some(&LOG_FORMAT_VERSION)
+}
+
+fn is_encoded(data: &[u8]) -> bool {
+    data.first() == Some(&FORMAT_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use openraft::{CommittedLeaderId, LogId};
+    use common_base::errors::RobustMQError;
+    use super::{decode, encode, is_current};
+
+    #[test]
+    fn encode_decode() {
+        let log_id = LogId::new(CommittedLeaderId::new(3, 1), 42);
+        let data = encode(&Some(log_id)).unwrap();
+        assert!(is_current(&data));
+        let decoded: Option<LogId<u64>> = decode(&data).unwrap();
+        assert_eq!(decoded, Some(log_id));
+
+        // Values written before the header existed are serde_json
+        let legacy = serde_json::to_vec(&log_id).unwrap();
+        assert!(!is_current(&legacy));
+        let decoded: LogId<u64> = decode(&legacy).unwrap();
+        assert_eq!(decoded, log_id);
+
+        let mut unknown = data.clone();
+        unknown[1] = 200;
+        match decode::<Option<LogId<u64>>>(&unknown) {
+            Err(RobustMQError::UnsupportedLogFormat(200)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -5,19 +5,117 @@ use std::ops::RangeBounds;
 use std::sync::Arc;
 use openraft::{AnyError, Entry, ErrorSubject, ErrorVerb, LogId, LogState, OptionalSend, RaftLogReader, StorageError, Vote};
 use openraft::storage::{IOFlushed, RaftLogStorage};
-use rocksdb::{ColumnFamily, Direction, DB};
+use rocksdb::{ColumnFamily, Direction, WriteBatch, DB};
 use tonic::codegen::Body;
 use tonic::codegen::tokio_stream::StreamExt;
+use common_base::errors::RobustMQError;
 use crate::openraft::raft_node::NodeId;
+use crate::openraft::sotre::codec::{decode, encode, is_current, LOG_FORMAT_VERSION};
 use crate::openraft::sotre::{bin_to_id, id_to_bin, StorageResult};
 use crate::openraft::typeconfig::TypeConfig;
+use crate::storage::rocksdb::RocksDBEngine;
 
-#[derive(Debug, Clone)]
+/// Number of log entries rewritten by one write batch of the format migration.
+const MIGRATE_BATCH_SIZE: usize = 1000;
+
+#[derive(Clone)]
 pub struct LogStore {
     pub db: Arc<DB>,
+
+    /// Encrypts the log entries like the values of the storage they are applied to.
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
 }
 
 impl LogStore {
+    /// Open the log store, rewriting the values of an older format first.
+    pub fn new(db: Arc<DB>, rocksdb_engine_handler: Arc<RocksDBEngine>) -> StorageResult<LogStore> {
+        let log_store = LogStore {
+            db,
+            rocksdb_engine_handler,
+        };
+        log_store.migrate_()?;
+        Ok(log_store)
+    }
+
+    /// Rewrite every log entry, the vote, the committed and the last purged log id to the
+    /// current format, in batches of at most `MIGRATE_BATCH_SIZE` entries. Values already in the
+    /// current format are skipped, so a migration that stopped halfway resumes where it was.
+    /// The format marker is written last, nothing is written once the store is up to date.
+    fn migrate_(&self) -> StorageResult<()> {
+        let format = self
+            .db
+            .get_cf(self.store(), b"log_format")
+            .map_err(|e| StorageError::read(&e))?;
+        if format.as_deref() == Some(&[LOG_FORMAT_VERSION][..]) {
+            return Ok(());
+        }
+
+        let mut batch = WriteBatch::default();
+        for res in self.db.iterator_cf(self.logs(), rocksdb::IteratorMode::Start) {
+            let (id, val) = res.map_err(|e| StorageError::read_logs(&e))?;
+            let val = self
+                .rocksdb_engine_handler
+                .decrypt(&val)
+                .map_err(|e| StorageError::read_logs(&e))?;
+            if is_current(&val) {
+                continue;
+            }
+            let entry: Entry<TypeConfig> = decode(&val).map_err(|e| StorageError::read_logs(&e))?;
+            batch.put_cf(self.logs(), id, self.encode_entry(&entry)?);
+            if batch.len() >= MIGRATE_BATCH_SIZE {
+                self.db
+                    .write(std::mem::take(&mut batch))
+                    .map_err(|e| StorageError::write_logs(&e))?;
+            }
+        }
+
+        for key in [&b"vote"[..], b"committed", b"last_purged_log_id"] {
+            let val = match self.db.get_cf(self.store(), key).map_err(|e| StorageError::read(&e))? {
+                Some(val) => val,
+                None => continue,
+            };
+            if is_current(&val) {
+                continue;
+            }
+            let val = match key {
+                b"vote" => {
+                    let vote: Vote<NodeId> = decode(&val).map_err(|e| StorageError::read_vote(&e))?;
+                    encode(&vote)
+                }
+                b"committed" => {
+                    let committed: Option<LogId<NodeId>> = decode(&val).map_err(|e| StorageError::read(&e))?;
+                    encode(&committed)
+                }
+                _ => {
+                    let last_purged: LogId<NodeId> = decode(&val).map_err(|e| StorageError::read(&e))?;
+                    encode(&last_purged)
+                }
+            };
+            batch.put_cf(self.store(), key, val.map_err(|e| StorageError::write(&e))?);
+        }
+
+        self.db.write(batch).map_err(|e| StorageError::write(&e))?;
+        self.flush(ErrorSubject::Store, ErrorVerb::Write)?;
+
+        self.db
+            .put_cf(self.store(), b"log_format", [LOG_FORMAT_VERSION])
+            .map_err(|e| StorageError::write(&e))?;
+        self.flush(ErrorSubject::Store, ErrorVerb::Write)
+    }
+
+    /// Log entries hold the written keys and values, they are encrypted when the storage is.
+    fn encode_entry(&self, entry: &Entry<TypeConfig>) -> StorageResult<Vec<u8>> {
+        encode(entry)
+            .and_then(|data| self.rocksdb_engine_handler.encrypt(data))
+            .map_err(|e| StorageError::write_logs(&e))
+    }
+
+    fn decode_entry(&self, val: &[u8]) -> StorageResult<Entry<TypeConfig>> {
+        self.rocksdb_engine_handler
+            .decrypt(val)
+            .and_then(|data| decode(&data))
+            .map_err(|e| StorageError::read_logs(&e))
+    }
 
     fn store(&self) -> &ColumnFamily {
         self.db.cf_handle("_raft_store").unwrap()
@@ -43,7 +141,7 @@ impl LogStore {
             .db
             .get_cf(self.store(),b"last_purged_log_id")
             .map_err(|e| StorageError::read(&e))?
-            .and_then(|v| serde_json::from_slice(&v).ok())
+            .and_then(|v| decode(&v).ok())
         )
     }
 
@@ -52,7 +150,7 @@ impl LogStore {
             .put_cf(
                 self.store(),
                 b"last_purged_log_id",
-                serde_json::to_vec(&log_id).unwrap().as_slice(),
+                encode(&log_id).map_err(|e| StorageError::write(&e))?,
             )
             .map_err(|e| StorageError::write(&e))?;
 
@@ -64,10 +162,10 @@ impl LogStore {
         &self,
         committed: &Option<LogId<NodeId>>,
     ) -> Result<(), StorageError<TypeConfig>> {
-        let json = serde_json::to_vec(committed).unwrap();
+        let data = encode(committed).map_err(|e| StorageError::write(&e))?;
 
         self.db
-           .put_cf(self.store(), b"committed", json)
+           .put_cf(self.store(), b"committed", data)
            .map_err(|e| StorageError::write(&e))?;
 
         self.flush(ErrorSubject::Store, ErrorVerb::Write)?;
@@ -80,13 +178,14 @@ impl LogStore {
                 .db
                 .get_cf(self.store(), b"committed")
                 .map_err(|e| StorageError::read(&e))?
-                .and_then(|v| serde_json::from_slice(&v).ok())
+                .and_then(|v| decode::<Option<LogId<NodeId>>>(&v).ok())
+                .flatten()
         )
     }
 
     fn set_vote_(&self, vote: &Vote<NodeId>) -> StorageResult<()> {
         self.db
-            .put_cf(self.store(), b"vote", serde_json::to_vec(vote).unwrap())
+            .put_cf(self.store(), b"vote", encode(vote).map_err(|e| StorageError::write_vote(&e))?)
             .map_err(|e| StorageError::write(&e))?;
 
         self.flush(ErrorSubject::Vote, ErrorVerb::Write)?;
@@ -99,9 +198,39 @@ impl LogStore {
                 .db
                 .get_cf(self.store(), b"vote")
                 .map_err(|e| StorageError::write_vote(&e))?
-                .and_then(|v| serde_json::from_slice(&v).ok())
+                .and_then(|v| decode(&v).ok())
         )
     }
+
+    /// Start the log of a node restored from a backup. Every entry up to `last_applied` is already
+    /// in the state machine, so the log begins after it, and the vote carries its term so the
+    /// first election of the node moves past the term of the backup.
+    pub fn seed_from_backup(&self, node_id: NodeId, last_applied: LogId<NodeId>) -> StorageResult<()> {
+        self.set_last_purged_(last_applied)?;
+        self.set_committed_(&Some(last_applied))?;
+        self.set_vote_(&Vote::new(last_applied.leader_id.term, node_id))
+    }
+
+    pub fn append_<I>(&self, entries: I) -> StorageResult<()>
+    where
+        I: IntoIterator<Item=Entry<TypeConfig>>,
+    {
+        let _guard = self.rocksdb_engine_handler.write_lock();
+        for entry in entries {
+            let id = id_to_bin(entry.log_id.index);
+            assert_eq!(bin_to_id(&id), entry.log_id.index);
+            self.db
+                .put_cf(self.logs(), id, self.encode_entry(&entry)?)
+                .map_err(|e| StorageError::write_logs(&e))?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite the log entries that are not encrypted with the active key, after a key rotation.
+    /// The other values of the log store are not encrypted.
+    pub fn reencrypt(&self) -> Result<u64, RobustMQError> {
+        self.rocksdb_engine_handler.reencrypt_cf(&self.db, self.logs())
+    }
 }
 
 impl RaftLogReader<TypeConfig> for LogStore {
@@ -121,8 +250,7 @@ impl RaftLogReader<TypeConfig> for LogStore {
             )
             .map(|res| {
                 let (id, val) = res.unwrap();
-                let entry: StorageResult<Entry<_>> = serde_json::from_slice(&val)
-                    .map_err(|e| StorageError::read_logs(&e));
+                let entry = self.decode_entry(&val);
                 let id = bin_to_id(&id);
                 assert_eq!(Ok(id), entry.as_ref().map(|e| e.log_id.index));
                 (id, entry)
@@ -147,11 +275,7 @@ impl RaftLogStorage<TypeConfig> for LogStore {
             .next()
             .and_then(|res| {
                 let (_, ent) = res.unwrap();
-                Some(
-                    serde_json::from_slice::<Entry<TypeConfig>>(&ent)
-                        .ok()?
-                        .log_id,
-                )
+                Some(self.decode_entry(&ent).ok()?.log_id)
             });
 
         let last_purged_log_id = self.get_last_purged_()?;
@@ -195,17 +319,7 @@ impl RaftLogStorage<TypeConfig> for LogStore {
         I: IntoIterator<Item=Entry<TypeConfig>> + Send,
         I::IntoIter: Send
     {
-        for entry in entries {
-            let id = id_to_bin(entry.log_id.index);
-            assert_eq!(bin_to_id(&id), entry.log_id.index);
-            self.db
-                .put_cf(
-                    self.logs(),
-                    id,
-                    serde_json::to_vec(&entry).map_err(|e| StorageError::write_logs(&e))?,
-                )
-                .map_err(|e| StorageError::write_logs(&e))?;
-        }
+        self.append_(entries)?;
 
         callback.io_completed(Ok(()));
 
@@ -218,6 +332,7 @@ impl RaftLogStorage<TypeConfig> for LogStore {
 
         let from = id_to_bin(log_id.index);
         let to = id_to_bin(0xff_ff_ff_ff_ff_ff_ff_ff);
+        let _guard = self.rocksdb_engine_handler.write_lock();
         self.db
             .delete_range_cf(self.logs(), &from, &to)
             .map_err(|e| StorageError::write_logs(&e))
@@ -230,8 +345,166 @@ impl RaftLogStorage<TypeConfig> for LogStore {
         self.set_last_purged_(log_id)?;
         let from = id_to_bin(0);
         let to = id_to_bin(log_id.index + 1);
+        let _guard = self.rocksdb_engine_handler.write_lock();
         self.db
             .delete_range_cf(self.logs(), &from, &to)
             .map_err(|e| StorageError::write_logs(&e))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use openraft::{CommittedLeaderId, Entry, EntryPayload, LogId, RaftLogReader, Vote};
+    use crate::openraft::route::AppRequestData;
+    use crate::openraft::sotre::codec::is_current;
+    use crate::openraft::sotre::open_raft_db;
+    use crate::openraft::typeconfig::TypeConfig;
+    use crate::storage::encryption::encryption_key_id;
+    use crate::storage::rocksdb::RocksDBEngine;
+    use crate::storage::test_tools::TempDir;
+    use super::{LogStore, MIGRATE_BATCH_SIZE};
+
+    #[tokio::test]
+    async fn migrate_json_values() {
+        let dir = TempDir::new();
+        let rocksdb_engine = dir.rocksdb_engine();
+        let db = Arc::new(open_raft_db(dir.join("openraft")));
+        let logs = db.cf_handle("_raft_logs").unwrap();
+        let store = db.cf_handle("_raft_store").unwrap();
+
+        // Values as written before the binary encoding, more than one migration batch of them
+        let leader_id = CommittedLeaderId::new(1, 1);
+        let count = MIGRATE_BATCH_SIZE as u64 + 3;
+        for index in 1..=count {
+            let entry = Entry::<TypeConfig> {
+                log_id: LogId::new(leader_id, index),
+                payload: EntryPayload::Normal(AppRequestData::Set {
+                    key: format!("k{}", index),
+                    value: format!("v{}", index),
+                    lease_id: 0,
+                }),
+            };
+            db.put_cf(logs, index.to_be_bytes(), serde_json::to_vec(&entry).unwrap()).unwrap();
+        }
+        let vote = Vote::new_committed(1, 1);
+        db.put_cf(store, b"vote", serde_json::to_vec(&vote).unwrap()).unwrap();
+        let committed = Some(LogId::new(leader_id, 2));
+        db.put_cf(store, b"committed", serde_json::to_vec(&committed).unwrap()).unwrap();
+
+        let mut log_store = LogStore::new(db.clone(), rocksdb_engine.clone()).unwrap();
+        for res in db.iterator_cf(logs, rocksdb::IteratorMode::Start) {
+            let (_, val) = res.unwrap();
+            assert!(is_current(&val));
+        }
+        assert!(is_current(&db.get_cf(store, b"vote").unwrap().unwrap()));
+        assert!(db.get_cf(store, b"log_format").unwrap().is_some());
+
+        let entries = log_store.try_get_log_entries(1..=count).await.unwrap();
+        assert_eq!(entries.len(), count as usize);
+        assert_eq!(entries[2].log_id.index, 3);
+        assert_eq!(log_store.read_vote().await.unwrap(), Some(vote));
+        assert_eq!(log_store.get_committed_().unwrap(), committed);
+
+        // A migrated store is left untouched when opened again
+        LogStore::new(db.clone(), rocksdb_engine.clone()).unwrap();
+        assert_eq!(log_store.try_get_log_entries(1..=count).await.unwrap().len(), count as usize);
+    }
+
+    #[tokio::test]
+    async fn encrypted_entries() {
+        let dir = TempDir::new();
+        let key_file = dir.join("encryption.keys");
+        std::fs::write(&key_file, format!("active_key_id = 1\n\n[keys]\n1 = \"{}\"\n", "11".repeat(32)))
+            .unwrap();
+        let mut config = dir.config();
+        config.encryption_key_file = key_file;
+        let rocksdb_engine = Arc::new(RocksDBEngine::new(&config));
+        let db = Arc::new(open_raft_db(dir.join("openraft")));
+        let logs = db.cf_handle("_raft_logs").unwrap();
+
+        // An entry written before encryption was enabled stays readable
+        let leader_id = CommittedLeaderId::new(1, 1);
+        let entry = |index: u64| Entry::<TypeConfig> {
+            log_id: LogId::new(leader_id, index),
+            payload: EntryPayload::Normal(AppRequestData::Set {
+                key: format!("/kv/default/secret-{}", index),
+                value: "password".to_string(),
+                lease_id: 0,
+            }),
+        };
+        let plain_dir = TempDir::new();
+        let plain = LogStore::new(db.clone(), plain_dir.rocksdb_engine()).unwrap();
+        plain.append_(vec![entry(1)]).unwrap();
+
+        let mut log_store = LogStore::new(db.clone(), rocksdb_engine.clone()).unwrap();
+        log_store.append_(vec![entry(2), entry(3)]).unwrap();
+        for res in db.iterator_cf(logs, rocksdb::IteratorMode::From(&2u64.to_be_bytes(), rocksdb::Direction::Forward)) {
+            let (_, val) = res.unwrap();
+            assert_eq!(encryption_key_id(&val), Some(1));
+            assert!(!val.windows(8).any(|window| window == b"password"));
+        }
+
+        let entries = log_store.try_get_log_entries(1..=3).await.unwrap();
+        assert_eq!(entries.len(), 3);
+        match &entries[2].payload {
+            EntryPayload::Normal(AppRequestData::Set { key, value, .. }) => {
+                assert_eq!(key, "/kv/default/secret-3");
+                assert_eq!(value, "password");
+            }
+            other => panic!("unexpected payload {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn reencrypt_entries_after_key_rotation() {
+        let dir = TempDir::new();
+        let key_file = dir.join("encryption.keys");
+        let key = |key_id: u32| format!("{} = \"{}\"\n", key_id, format!("{}{}", key_id, key_id).repeat(32));
+        let mut config = dir.config();
+        config.encryption_key_file = key_file.clone();
+        let db = Arc::new(open_raft_db(dir.join("openraft")));
+        let logs = db.cf_handle("_raft_logs").unwrap();
+        let entries = (1..=3)
+            .map(|index| Entry::<TypeConfig> {
+                log_id: LogId::new(CommittedLeaderId::new(1, 1), index),
+                payload: EntryPayload::Normal(AppRequestData::Set {
+                    key: format!("/kv/default/k{}", index),
+                    value: format!("v{}", index),
+                    lease_id: 0,
+                }),
+            })
+            .collect::<Vec<_>>();
+
+        std::fs::write(&key_file, format!("active_key_id = 1\n\n[keys]\n{}", key(1))).unwrap();
+        {
+            let log_store = LogStore::new(db.clone(), Arc::new(RocksDBEngine::new(&config))).unwrap();
+            log_store.append_(entries.clone()).unwrap();
+        }
+
+        // Rotate to key 2, the entries written with key 1 are rewritten
+        std::fs::write(&key_file, format!("active_key_id = 2\n\n[keys]\n{}{}", key(1), key(2))).unwrap();
+        {
+            let log_store = LogStore::new(db.clone(), Arc::new(RocksDBEngine::new(&config))).unwrap();
+            assert_eq!(log_store.reencrypt().unwrap(), 3);
+            assert_eq!(log_store.reencrypt().unwrap(), 0);
+            for res in db.iterator_cf(logs, rocksdb::IteratorMode::Start) {
+                let (_, val) = res.unwrap();
+                assert_eq!(encryption_key_id(&val), Some(2));
+            }
+        }
+
+        // Key 1 is no longer needed to read the log
+        std::fs::write(&key_file, format!("active_key_id = 2\n\n[keys]\n{}", key(2))).unwrap();
+        let mut log_store = LogStore::new(db.clone(), Arc::new(RocksDBEngine::new(&config))).unwrap();
+        let read = log_store.try_get_log_entries(1..=3).await.unwrap();
+        assert_eq!(read.len(), 3);
+        match &read[1].payload {
+            EntryPayload::Normal(AppRequestData::Set { key, value, .. }) => {
+                assert_eq!(key, "/kv/default/k2");
+                assert_eq!(value, "v2");
+            }
+            other => panic!("unexpected payload {:?}", other),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use openraft::{SnapshotMeta, StorageError};
@@ -6,20 +6,31 @@ use rocksdb::{ColumnFamilyDescriptor, Options, DB};
 use serde::{Deserialize, Serialize};
 use serde::de::Unexpected::Option;
 use crate::openraft::sotre::log_store::LogStore;
+use crate::openraft::sotre::snapshot::SnapshotFiles;
 use crate::openraft::sotre::state_machine_store::StateMachineStore;
 use crate::openraft::typeconfig::TypeConfig;
+use crate::storage::rocksdb::RocksDBEngine;
+use crate::watch::WatchHub;
 
+pub mod codec;
 pub mod log_store;
+pub mod snapshot;
 pub mod state_machine_store;
 
 type StorageResult<T> = Result<T, StorageError<TypeConfig>>;
 
 
+/// The current snapshot as recorded in the raft store, its data is only kept in its file.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StoredSnapshot {
     pub meta: SnapshotMeta<TypeConfig>,
 
-    /// The data of the state machine at the time of this snapshot.
+    /// The file holding the data of the state machine at the time of this snapshot.
+    #[serde(default)]
+    pub path: PathBuf,
+
+    /// The data of snapshots recorded before they were kept in files, empty since.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub data: Vec<u8>,
 }
 
@@ -33,7 +44,8 @@ fn bin_to_id(buf: &[u8]) -> u64 {
     (&buf[0..8]).read_u64::<BigEndian>().unwrap()
 }
 
-pub(crate) async fn new_storage<P: AsRef<Path>>(db_path: P) -> (LogStore, StateMachineStore) {
+/// Open the RocksDB holding the openraft log, vote and snapshot.
+pub fn open_raft_db<P: AsRef<Path>>(db_path: P) -> DB {
     let mut db_opts = Options::default();
     db_opts.create_missing_column_families(true);
     db_opts.create_if_missing(true);
@@ -41,11 +53,19 @@ pub(crate) async fn new_storage<P: AsRef<Path>>(db_path: P) -> (LogStore, StateM
     let store = ColumnFamilyDescriptor::new("_raft_store", Options::default());
     let logs = ColumnFamilyDescriptor::new("_raft_logs", Options::default());
 
-    let db = DB::open_cf_descriptors(&db_opts, db_path, vec![store, logs]).unwrap();
-    let db = Arc::new(db);
+    DB::open_cf_descriptors(&db_opts, db_path, vec![store, logs]).unwrap()
+}
+
+pub(crate) async fn new_storage<P: AsRef<Path>>(
+    db_path: P,
+    snapshot_files: SnapshotFiles,
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    watch_hub: Arc<WatchHub>,
+) -> (LogStore, StateMachineStore) {
+    let db = Arc::new(open_raft_db(db_path));
 
-    let log_store = LogStore { db: db.clone() };
-    let sm_store = StateMachineStore::new(db).await.unwrap();
+    let log_store = LogStore::new(db.clone(), rocksdb_engine_handler.clone()).unwrap();
+    let sm_store = StateMachineStore::new(db, rocksdb_engine_handler, watch_hub, snapshot_files).await.unwrap();
 
     (log_store, sm_store)
 }
\ No newline at end of file
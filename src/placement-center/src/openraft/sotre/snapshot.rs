@@ -0,0 +1,334 @@
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use bincode::serialize;
+use log::error;
+use openraft::{Snapshot, Vote};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use common_base::errors::RobustMQError;
+use protocol::openraft::SnapshotChunk;
+use crate::openraft::raft_node::NodeId;
+use crate::openraft::typeconfig::TypeConfig;
+
+/// Snapshots are kept as files under `{data_path}/_snapshot`.
+///
+/// A snapshot being received is written to `{snapshot_id}.part` and renamed to
+/// `{snapshot_id}.snap` once its last chunk has arrived. The part file outlives a broken
+/// stream, so the sender can resume from its length instead of starting over.
+#[derive(Debug, Clone)]
+pub struct SnapshotFiles {
+    dir: PathBuf,
+}
+
+impl SnapshotFiles {
+    pub fn new(data_path: &str) -> Self {
+        SnapshotFiles {
+            dir: PathBuf::from(format!("{}/_snapshot", data_path)),
+        }
+    }
+
+    // Snapshot ids of received snapshots come from the peer, they must name a file of the directory
+    pub fn snapshot_path(&self, snapshot_id: &str) -> Result<PathBuf, RobustMQError> {
+        check_snapshot_id(snapshot_id)?;
+        Ok(self.dir.join(format!("{}.snap", snapshot_id)))
+    }
+
+    fn part_path(&self, snapshot_id: &str) -> Result<PathBuf, RobustMQError> {
+        check_snapshot_id(snapshot_id)?;
+        Ok(self.dir.join(format!("{}.part", snapshot_id)))
+    }
+
+    /// Write a snapshot built by this node and return it opened for reading.
+    pub async fn create(&self, snapshot_id: &str, data: &[u8]) -> Result<File, RobustMQError> {
+        fs::create_dir_all(&self.dir).await?;
+        let path = self.snapshot_path(snapshot_id)?;
+        fs::write(&path, data).await?;
+        Ok(File::open(&path).await?)
+    }
+
+    /// The file a snapshot is built in before its id is known. It is written synchronously,
+    /// like the RocksDB snapshot it is read from.
+    pub fn building_path(&self) -> Result<PathBuf, RobustMQError> {
+        std::fs::create_dir_all(&self.dir)?;
+        self.part_path("_building")
+    }
+
+    /// The files of the built and received snapshots, read synchronously like `building_path`.
+    pub fn snapshot_paths(&self) -> Result<Vec<PathBuf>, RobustMQError> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().map_or(false, |extension| extension == "snap") {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Turn the built file into the snapshot `snapshot_id` and return its path.
+    pub async fn finish_building(&self, snapshot_id: &str) -> Result<PathBuf, RobustMQError> {
+        let path = self.snapshot_path(snapshot_id)?;
+        fs::rename(self.part_path("_building")?, &path).await?;
+        Ok(path)
+    }
+
+    pub async fn open(&self, snapshot_id: &str) -> Result<Option<File>, RobustMQError> {
+        let path = self.snapshot_path(snapshot_id)?;
+        if !fs::try_exists(&path).await? {
+            return Ok(None);
+        }
+        Ok(Some(File::open(&path).await?))
+    }
+
+    /// An empty, writable file for a snapshot whose id is not known yet.
+    pub async fn create_receiving(&self) -> Result<File, RobustMQError> {
+        fs::create_dir_all(&self.dir).await?;
+        Ok(OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(self.part_path("_receiving")?)
+            .await?)
+    }
+
+    /// Number of bytes of the snapshot received so far.
+    pub async fn received_offset(&self, snapshot_id: &str) -> Result<u64, RobustMQError> {
+        match fs::metadata(self.part_path(snapshot_id)?).await {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Append a chunk to the part file. A chunk at offset 0 starts the transfer over, any other
+    /// chunk has to start exactly where the received data ends.
+    pub async fn write_chunk(
+        &self,
+        snapshot_id: &str,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), RobustMQError> {
+        let path = self.part_path(snapshot_id)?;
+        fs::create_dir_all(&self.dir).await?;
+        let mut file = if offset == 0 {
+            File::create(&path).await?
+        } else {
+            let received = self.received_offset(snapshot_id).await?;
+            if received != offset {
+                return Err(RobustMQError::SnapshotOffsetMismatch(offset, received));
+            }
+            OpenOptions::new().append(true).open(&path).await?
+        };
+        file.write_all(data).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+
+    /// Turn the fully received part file into a snapshot and return it opened for reading.
+    pub async fn finish(&self, snapshot_id: &str) -> Result<File, RobustMQError> {
+        let path = self.snapshot_path(snapshot_id)?;
+        fs::rename(self.part_path(snapshot_id)?, &path).await?;
+        Ok(File::open(&path).await?)
+    }
+
+    /// Remove every snapshot and part file except the ones of `snapshot_id`.
+    pub async fn remove_others(&self, snapshot_id: &str) -> Result<(), RobustMQError> {
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let keep = [
+            self.snapshot_path(snapshot_id)?,
+            self.part_path(snapshot_id)?,
+            self.part_path("_building")?,
+        ];
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if !keep.contains(&path) {
+                fs::remove_file(path).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A snapshot id names a file of the snapshot directory, it must not reach outside of it.
+fn check_snapshot_id(snapshot_id: &str) -> Result<(), RobustMQError> {
+    if snapshot_id.is_empty()
+        || snapshot_id.contains(['/', '\\', '\0'])
+        || snapshot_id.contains("..")
+    {
+        return Err(RobustMQError::InvalidSnapshotId(snapshot_id.to_string()));
+    }
+    Ok(())
+}
+
+/// Read the snapshot from `offset` on and send it as chunks of at most `chunk_size` bytes.
+/// The first chunk carries the vote and the meta of the snapshot, the last one is marked `done`.
+/// Stops early if the stream is closed or the snapshot cannot be read, in which case the
+/// receiver sees the stream end without a `done` chunk.
+pub async fn send_snapshot_chunks(
+    vote: Vote<NodeId>,
+    snapshot: Snapshot<TypeConfig>,
+    offset: u64,
+    chunk_size: u64,
+    sx: mpsc::Sender<SnapshotChunk>,
+) {
+    let snapshot_id = snapshot.meta.snapshot_id.clone();
+    if let Err(e) = read_snapshot_chunks(vote, snapshot, offset, chunk_size, sx).await {
+        error!("Failed to send snapshot {}, error message: {}", snapshot_id, e);
+    }
+}
+
+async fn read_snapshot_chunks(
+    vote: Vote<NodeId>,
+    snapshot: Snapshot<TypeConfig>,
+    offset: u64,
+    chunk_size: u64,
+    sx: mpsc::Sender<SnapshotChunk>,
+) -> Result<(), RobustMQError> {
+    let mut vote = match serialize(&vote) {
+        Ok(data) => data,
+        Err(e) => return Err(RobustMQError::CommonError(e.to_string())),
+    };
+    let mut meta = match serialize(&snapshot.meta) {
+        Ok(data) => data,
+        Err(e) => return Err(RobustMQError::CommonError(e.to_string())),
+    };
+
+    let mut file = snapshot.snapshot;
+    let len = file.metadata().await?.len();
+    let mut offset = if offset > len { 0 } else { offset };
+    file.seek(SeekFrom::Start(offset)).await?;
+
+    loop {
+        let size = std::cmp::min(chunk_size, len - offset) as usize;
+        let mut data = vec![0; size];
+        file.read_exact(&mut data).await?;
+
+        let done = offset + size as u64 >= len;
+        let chunk = SnapshotChunk {
+            vote: std::mem::take(&mut vote),
+            meta: std::mem::take(&mut meta),
+            offset,
+            checksum: crc32(&data),
+            data,
+            done,
+        };
+        if sx.send(chunk).await.is_err() {
+            return Ok(());
+        }
+        if done {
+            return Ok(());
+        }
+        offset += size as u64;
+    }
+}
+
+/// CRC-32 (IEEE) of a snapshot chunk.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use openraft::{Snapshot, SnapshotMeta, Vote};
+    use tokio::io::AsyncReadExt;
+    use tokio::sync::mpsc;
+    use common_base::errors::RobustMQError;
+    use crate::storage::test_tools::TempDir;
+    use super::{crc32, send_snapshot_chunks, SnapshotFiles};
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[tokio::test]
+    async fn receive_chunks_and_resume() {
+        let dir = TempDir::new();
+        let files = SnapshotFiles::new(dir.path());
+        let snapshot_id = "1-1-10-1";
+
+        assert_eq!(files.received_offset(snapshot_id).await.unwrap(), 0);
+        files.write_chunk(snapshot_id, 0, b"hello ").await.unwrap();
+        assert_eq!(files.received_offset(snapshot_id).await.unwrap(), 6);
+
+        // A chunk that does not continue the received data is rejected
+        match files.write_chunk(snapshot_id, 3, b"lo world").await {
+            Err(RobustMQError::SnapshotOffsetMismatch(3, 6)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+
+        files.write_chunk(snapshot_id, 6, b"world").await.unwrap();
+        let mut file = files.finish(snapshot_id).await.unwrap();
+        let mut content = String::new();
+        file.read_to_string(&mut content).await.unwrap();
+        assert_eq!(content, "hello world");
+        assert_eq!(files.received_offset(snapshot_id).await.unwrap(), 0);
+
+        files.create("1-1-20-2", b"newer").await.unwrap();
+        files.remove_others("1-1-20-2").await.unwrap();
+        assert!(files.open(snapshot_id).await.unwrap().is_none());
+        assert!(files.open("1-1-20-2").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn send_chunks_of_chunk_size() {
+        let dir = TempDir::new();
+        let files = SnapshotFiles::new(dir.path());
+        let file = files.create("1-1-10-1", b"hello world").await.unwrap();
+        let snapshot = Snapshot {
+            meta: SnapshotMeta::default(),
+            snapshot: Box::new(file),
+        };
+
+        let (sx, mut rx) = mpsc::channel(10);
+        send_snapshot_chunks(Vote::default(), snapshot, 2, 4, sx).await;
+        let mut chunks = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            chunks.push((chunk.offset, chunk.data, chunk.done));
+        }
+        assert_eq!(
+            chunks,
+            vec![
+                (2, b"llo ".to_vec(), false),
+                (6, b"worl".to_vec(), false),
+                (10, b"d".to_vec(), true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reject_invalid_snapshot_ids() {
+        let dir = TempDir::new();
+        let files = SnapshotFiles::new(dir.path());
+
+        for snapshot_id in ["", "../escape", "a/b", "..", "a\\b"] {
+            match files.write_chunk(snapshot_id, 0, b"data").await {
+                Err(RobustMQError::InvalidSnapshotId(_)) => {}
+                other => panic!("unexpected result {:?}", other.map(|_| ())),
+            }
+            assert!(files.finish(snapshot_id).await.is_err());
+            assert!(files.received_offset(snapshot_id).await.is_err());
+        }
+        assert!(!tokio::fs::try_exists(dir.join("escape.part")).await.unwrap());
+    }
+}
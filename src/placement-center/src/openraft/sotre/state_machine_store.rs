@@ -1,16 +1,24 @@
 use crate::openraft::raft_node::{typ, NodeId};
 use crate::openraft::route::{AppRequestData, AppResponseData};
+use crate::openraft::sotre::snapshot::SnapshotFiles;
 use crate::openraft::sotre::{StorageResult, StoredSnapshot};
 use crate::openraft::typeconfig::{SnapshotData, TypeConfig};
+use crate::storage::engine::{engine_batch_across, EngineBatchOp};
+use crate::storage::keys::key_lease;
+use crate::storage::kv::{KvBatchOp, KvStorage};
+use crate::storage::lease::{is_lease_rejection, LeaseStorage};
+use crate::storage::namespace::{is_namespace_rejection, NamespaceStorage};
+use crate::storage::rocksdb::{RocksDBEngine, DB_COLUMN_FAMILY_KV, DB_COLUMN_FAMILY_RAFT};
+use crate::storage::state_machine::StateMachineStorage;
+use crate::watch::WatchHub;
 use openraft::storage::RaftStateMachine;
 use openraft::{AnyError, EntryPayload, ErrorSubject, ErrorVerb, LogId, OptionalSend, RaftSnapshotBuilder, Snapshot, SnapshotMeta, StorageError, StoredMembership};
 use rocksdb::{ColumnFamily, DB};
-use std::collections::BTreeMap;
-use std::io::Cursor;
+use std::io::{BufReader, Seek, SeekFrom};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::fs::File;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct StateMachineStore {
     pub data: StateMachineData,
 
@@ -22,27 +30,35 @@ pub struct StateMachineStore {
 
     /// State machine stores snapshot in db.
     db: Arc<DB>,
+
+    /// Applied entries are written to the storage shared with the gRPC read path.
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+
+    /// Receives a PUT or DELETE event for every key changed by an applied entry.
+    watch_hub: Arc<WatchHub>,
+
+    /// Files holding the data of the built and received snapshots.
+    snapshot_files: SnapshotFiles,
 }
 
+/// In-memory copy of the applied state, which is persisted together with every applied entry.
 #[derive(Debug, Clone)]
 pub struct StateMachineData {
     pub last_applied_log_id: Option<LogId<NodeId>>,
 
     pub last_membership: StoredMembership<TypeConfig>,
-
-    /// State built from applying the raft logs
-    pub kvs: Arc<RwLock<BTreeMap<String, String>>>,
 }
 
 impl RaftSnapshotBuilder<TypeConfig> for StateMachineStore {
     async fn build_snapshot(&mut self) -> Result<Snapshot<TypeConfig>, StorageError<TypeConfig>> {
-        let last_applied_log = self.data.last_applied_log_id;
-        let last_membership = self.data.last_membership.clone();
-
-        let kv_json = {
-            let kvs = self.data.kvs.read().await;
-            serde_json::to_vec(&*kvs).map_err(|e| StorageError::read_state_machine(&e))?
-        };
+        // Entries may be applied while the snapshot is built, so the applied state is read
+        // from the same RocksDB snapshot as the data instead of from memory. The data is
+        // written to the file frame by frame, compressed then encrypted like the storage.
+        let (last_applied_log, last_membership) = self
+            .snapshot_files
+            .building_path()
+            .and_then(|path| StateMachineStorage::new(self.rocksdb_engine_handler.clone()).write_snapshot_file(&path))
+            .map_err(|e| StorageError::read_state_machine(&e))?;
 
         let snapshot_id = if let Some(last) = last_applied_log {
             format!("{}-{}-{}", last.leader_id, last.index, self.snapshot_idx)
@@ -56,35 +72,62 @@ impl RaftSnapshotBuilder<TypeConfig> for StateMachineStore {
             snapshot_id,
         };
 
-        let snapshot = StoredSnapshot {
-            meta: meta.clone(),
-            data: kv_json.clone(),
-        };
+        let path = self
+            .snapshot_files
+            .finish_building(&meta.snapshot_id)
+            .await
+            .map_err(|e| StorageError::write_snapshot(Some(meta.signature()), &e))?;
+        let file = File::open(&path)
+            .await
+            .map_err(|e| StorageError::read_snapshot(Some(meta.signature()), &e))?;
 
-        self.set_current_snapshot_(snapshot)?;
+        self.set_current_snapshot_(StoredSnapshot {
+            meta: meta.clone(),
+            path,
+            data: Vec::new(),
+        })?;
+        self.remove_other_snapshots(&meta).await?;
 
         Ok(Snapshot {
             meta,
-            snapshot: Box::new(Cursor::new(kv_json)),
+            snapshot: Box::new(file),
         })
     }
 }
 
 impl StateMachineStore {
-    pub async fn new(db: Arc<DB>) -> Result<StateMachineStore, StorageError<TypeConfig>> {
+    pub async fn new(
+        db: Arc<DB>,
+        rocksdb_engine_handler: Arc<RocksDBEngine>,
+        watch_hub: Arc<WatchHub>,
+        snapshot_files: SnapshotFiles,
+    ) -> Result<StateMachineStore, StorageError<TypeConfig>> {
+        let storage = StateMachineStorage::new(rocksdb_engine_handler.clone());
+        let last_applied_log_id = storage
+            .last_applied()
+            .map_err(|e| StorageError::read_state_machine(&e))?;
+        let last_membership = storage
+            .last_membership()
+            .map_err(|e| StorageError::read_state_machine(&e))?;
+
         let mut sm = Self {
             data: StateMachineData {
-                last_applied_log_id: None,
-                last_membership: Default::default(),
-                kvs: Arc::new(Default::default()),
+                last_applied_log_id,
+                last_membership,
             },
             snapshot_idx: 0,
             db,
+            rocksdb_engine_handler,
+            watch_hub,
+            snapshot_files,
         };
 
-        let snapshot = sm.get_current_snapshot_()?;
-        if let Some(snap) = snapshot {
-            sm.update_state_machine_(snap).await?;
+        // Data applied before the state machine was persisted only survives in the last snapshot,
+        // and a node stopped while installing a snapshot has to install it again
+        if let Some((snapshot, file)) = sm.current_snapshot_file().await? {
+            if snapshot.meta.last_log_id > sm.data.last_applied_log_id {
+                sm.update_state_machine_(&snapshot.meta, file).await?;
+            }
         }
 
         Ok(sm)
@@ -92,19 +135,163 @@ impl StateMachineStore {
 
     async fn update_state_machine_(
         &mut self,
-        snapshot: StoredSnapshot,
+        meta: &SnapshotMeta<TypeConfig>,
+        file: File,
     ) -> Result<(), StorageError<TypeConfig>> {
-        let kvs: BTreeMap<String, String> = serde_json::from_slice(&snapshot.data)
-            .map_err(|e| StorageError::read_snapshot(Some(snapshot.meta.signature()), &e))?;
+        let mut file = file.into_std().await;
+        file.seek(SeekFrom::Start(0))
+            .map_err(|e| StorageError::read_snapshot(Some(meta.signature()), &e))?;
+
+        StateMachineStorage::new(self.rocksdb_engine_handler.clone())
+            .install_snapshot(BufReader::new(file), &meta.last_log_id, &meta.last_membership)
+            .map_err(|e| StorageError::write_snapshot(Some(meta.signature()), &e))?;
 
-        self.data.last_applied_log_id = snapshot.meta.last_log_id;
-        self.data.last_membership = snapshot.meta.last_membership.clone();
-        let mut x = self.data.kvs.write().await;
-        *x = kvs;
+        self.data.last_applied_log_id = meta.last_log_id;
+        self.data.last_membership = meta.last_membership.clone();
 
         Ok(())
     }
 
+    /// Apply one entry: its writes and the new applied state go to RocksDB in one batch.
+    fn apply_entry(
+        &mut self,
+        ent: typ::Entry,
+    ) -> Result<AppResponseData, StorageError<TypeConfig>> {
+        self.data.last_applied_log_id = Some(ent.log_id);
+        let revision = ent.log_id.index;
+        let kv_storage = KvStorage::new(self.rocksdb_engine_handler.clone());
+        let lease_storage = LeaseStorage::new(self.rocksdb_engine_handler.clone());
+
+        let mut resp_value = None;
+        let mut success = true;
+        let mut kv_ops = Vec::new();
+        let mut namespace_ops = Vec::new();
+        let mut lease_ops = Vec::new();
+        // A write rejected by its namespace or lease is answered with the error and only
+        // advances the applied state, every node rejects it the same way.
+        let mut error = None;
+
+        match ent.payload {
+            EntryPayload::Blank => {}
+            EntryPayload::Normal(req) => match req {
+                AppRequestData::Set {key, value, lease_id} => {
+                    resp_value = Some(value.clone());
+                    kv_ops.push(KvBatchOp::Set { key, value, lease_id });
+                },
+                AppRequestData::Delete {key} => {
+                    kv_ops.push(KvBatchOp::Delete { key });
+                },
+                AppRequestData::CompareAndSwap {key, expected_value, new_value} => {
+                    let current_value = kv_storage
+                        .get(key.clone())
+                        .map_err(|e| StorageError::read_state_machine(&e))?
+                        .unwrap_or_default();
+                    if current_value == expected_value {
+                        kv_ops.push(KvBatchOp::Set { key, value: new_value.clone(), lease_id: 0 });
+                        resp_value = Some(new_value);
+                    } else {
+                        resp_value = Some(current_value);
+                        success = false;
+                    }
+                }
+                AppRequestData::BatchSet {kvs} => {
+                    for (key, value) in kvs {
+                        kv_ops.push(KvBatchOp::Set { key, value, lease_id: 0 });
+                    }
+                },
+                AppRequestData::BatchDelete {keys} => {
+                    for key in keys {
+                        kv_ops.push(KvBatchOp::Delete { key });
+                    }
+                },
+                AppRequestData::NamespaceSet {namespace, max_keys, max_bytes, max_value_size} => {
+                    namespace_ops = NamespaceStorage::new(self.rocksdb_engine_handler.clone())
+                        .set_limits_ops(&namespace, max_keys, max_bytes, max_value_size)
+                        .map_err(|e| StorageError::write(&e))?;
+                },
+                AppRequestData::Import {namespaces, kvs} => {
+                    // Imported keys are written like any other write, with the revision of this entry
+                    let namespace_storage = NamespaceStorage::new(self.rocksdb_engine_handler.clone());
+                    for namespace in namespaces {
+                        namespace_ops.extend(
+                            namespace_storage
+                                .set_limits_ops(&namespace.name, namespace.max_keys, namespace.max_bytes, namespace.max_value_size)
+                                .map_err(|e| StorageError::write(&e))?,
+                        );
+                    }
+                    for (key, value) in kvs {
+                        kv_ops.push(KvBatchOp::Set { key, value, lease_id: 0 });
+                    }
+                },
+                AppRequestData::Txn {compares, success: success_ops, failure: failure_ops} => {
+                    success = kv_storage
+                        .compare_all(&compares)
+                        .map_err(|e| StorageError::read_state_machine(&e))?;
+                    kv_ops = if success { success_ops } else { failure_ops };
+                },
+                AppRequestData::LeaseGrant {lease_id, ttl} => {
+                    match lease_storage.grant_op(lease_id, ttl) {
+                        Ok((lease_id, ops)) => {
+                            resp_value = Some(lease_id.to_string());
+                            lease_ops = ops;
+                        }
+                        Err(e) if is_lease_rejection(&e) => error = Some(e.to_string()),
+                        Err(e) => return Err(StorageError::read_state_machine(&e)),
+                    }
+                },
+                AppRequestData::LeaseRevoke {lease_id} => {
+                    match lease_storage.attached_keys(lease_id) {
+                        Ok(keys) => {
+                            for key in keys {
+                                kv_ops.push(KvBatchOp::Delete { key });
+                            }
+                            // The deletes detach the keys from the lease, it is deleted after them
+                            lease_ops.push(EngineBatchOp::Delete(key_lease(lease_id)));
+                        }
+                        Err(e) if is_lease_rejection(&e) => error = Some(e.to_string()),
+                        Err(e) => return Err(StorageError::read_state_machine(&e)),
+                    }
+                },
+            },
+            EntryPayload::Membership(mem) => {
+                self.data.last_membership = StoredMembership::new(Some(ent.log_id), mem);
+            }
+        }
+
+        let (mut engine_ops, applied) = match kv_storage.batch_ops(kv_ops, revision) {
+            Ok(data) => data,
+            Err(e) if is_namespace_rejection(&e) || is_lease_rejection(&e) => {
+                error = Some(e.to_string());
+                (Vec::new(), Vec::new())
+            }
+            Err(e) => return Err(StorageError::write(&e)),
+        };
+        if error.is_some() {
+            success = false;
+            resp_value = None;
+        } else {
+            engine_ops.extend(namespace_ops);
+            engine_ops.extend(lease_ops);
+        }
+        let applied_ops = StateMachineStorage::new(self.rocksdb_engine_handler.clone())
+            .applied_state_ops(&self.data.last_applied_log_id, &self.data.last_membership)
+            .map_err(|e| StorageError::write(&e))?;
+        engine_batch_across(
+            self.rocksdb_engine_handler.clone(),
+            vec![(DB_COLUMN_FAMILY_KV, engine_ops), (DB_COLUMN_FAMILY_RAFT, applied_ops)],
+        )
+        .map_err(|e| StorageError::write(&e))?;
+
+        for op in applied {
+            match op {
+                KvBatchOp::Set { key, value, .. } => self.watch_hub.put(key, value, revision),
+                KvBatchOp::Delete { key } => self.watch_hub.delete(key, revision),
+            }
+        }
+
+        Ok(AppResponseData { value: resp_value, success, error })
+    }
+
     fn get_current_snapshot_(&self) -> StorageResult<Option<StoredSnapshot>> {
         Ok(self
             .db
@@ -114,6 +301,37 @@ impl StateMachineStore {
         )
     }
 
+    /// The current snapshot and its file. The data of a snapshot recorded before snapshots were
+    /// kept in files is moved to its file.
+    async fn current_snapshot_file(&self) -> StorageResult<Option<(StoredSnapshot, File)>> {
+        let mut snapshot = match self.get_current_snapshot_()? {
+            Some(snapshot) => snapshot,
+            None => return Ok(None),
+        };
+        let signature = snapshot.meta.signature();
+
+        if snapshot.path.as_os_str().is_empty() {
+            let path = self
+                .snapshot_files
+                .snapshot_path(&snapshot.meta.snapshot_id)
+                .map_err(|e| StorageError::read_snapshot(Some(signature.clone()), &e))?;
+            if !snapshot.data.is_empty() {
+                self.snapshot_files
+                    .create(&snapshot.meta.snapshot_id, &snapshot.data)
+                    .await
+                    .map_err(|e| StorageError::write_snapshot(Some(signature.clone()), &e))?;
+            }
+            snapshot.path = path;
+            snapshot.data = Vec::new();
+            self.set_current_snapshot_(snapshot.clone())?;
+        }
+
+        let file = File::open(&snapshot.path)
+            .await
+            .map_err(|e| StorageError::read_snapshot(Some(signature), &e))?;
+        Ok(Some((snapshot, file)))
+    }
+
     fn set_current_snapshot_(&self, snap: StoredSnapshot) -> StorageResult<()> {
         self.db
             .put_cf(
@@ -130,6 +348,13 @@ impl StateMachineStore {
         Ok(())
     }
 
+    async fn remove_other_snapshots(&self, meta: &SnapshotMeta<TypeConfig>) -> StorageResult<()> {
+        self.snapshot_files
+            .remove_others(&meta.snapshot_id)
+            .await
+            .map_err(|e| StorageError::write_snapshot(Some(meta.signature()), &e))
+    }
+
     fn flush(
         &self,
         subject: ErrorSubject<TypeConfig>,
@@ -171,30 +396,7 @@ impl RaftStateMachine<TypeConfig> for StateMachineStore {
         let mut replies = Vec::with_capacity(entries.size_hint().0);
 
         for ent in entries {
-            self.data.last_applied_log_id = Some(ent.log_id);
-
-            let mut resp_value = None;
-
-            match ent.payload {
-                EntryPayload::Blank => {}
-                EntryPayload::Normal(req) => match req {
-                    AppRequestData::Set {key, value} => {
-                        resp_value = Some(value.clone());
-
-                        let mut st = self.data.kvs.write().await;
-                        st.insert(key, value);
-                    },
-                    AppRequestData::Delete {key} => {
-                        let mut st = self.data.kvs.write().await;
-                        st.remove(&key);
-                    }
-                },
-                EntryPayload::Membership(mem) => {
-                    self.data.last_membership = StoredMembership::new(Some(ent.log_id), mem);
-                }
-            }
-
-            replies.push(AppResponseData { value: resp_value });
+            replies.push(self.apply_entry(ent)?);
         }
         Ok(replies)
     }
@@ -206,23 +408,34 @@ impl RaftStateMachine<TypeConfig> for StateMachineStore {
 
     async fn begin_receiving_snapshot(
         &mut self
-    ) -> Result<Box<Cursor<Vec<u8>>>, StorageError<TypeConfig>> {
-        Ok(Box::new(Cursor::new(Vec::new())))
+    ) -> Result<Box<SnapshotData>, StorageError<TypeConfig>> {
+        let file = self
+            .snapshot_files
+            .create_receiving()
+            .await
+            .map_err(|e| StorageError::write_snapshot(None, &e))?;
+        Ok(Box::new(file))
     }
 
+    /// Install a received snapshot from its file, one frame at a time. The snapshot is recorded
+    /// as the current one first, so a node stopped while installing it installs it again.
     async fn install_snapshot(
         &mut self,
         meta: &SnapshotMeta<TypeConfig>,
         snapshot: Box<SnapshotData>,
     ) -> Result<(), StorageError<TypeConfig>> {
-        let new_snapshot = StoredSnapshot {
+        let path = self
+            .snapshot_files
+            .snapshot_path(&meta.snapshot_id)
+            .map_err(|e| StorageError::read_snapshot(Some(meta.signature()), &e))?;
+        self.set_current_snapshot_(StoredSnapshot {
             meta: meta.clone(),
-            data: snapshot.into_inner(),
-        };
-
-        self.update_state_machine_(new_snapshot.clone()).await?;
+            path,
+            data: Vec::new(),
+        })?;
 
-        self.set_current_snapshot_(new_snapshot)?;
+        self.update_state_machine_(meta, *snapshot).await?;
+        self.remove_other_snapshots(meta).await?;
 
         Ok(())
     }
@@ -230,10 +443,207 @@ impl RaftStateMachine<TypeConfig> for StateMachineStore {
     async fn get_current_snapshot(
         &mut self
     ) -> Result<Option<Snapshot<TypeConfig>>, StorageError<TypeConfig>> {
-        let x = self.get_current_snapshot_()?;
-        Ok(x.map(|s| Snapshot {
-            meta: s.meta.clone(),
-            snapshot: Box::new(Cursor::new(s.data.clone())),
+        let (snapshot, file) = match self.current_snapshot_file().await? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+
+        Ok(Some(Snapshot {
+            meta: snapshot.meta,
+            snapshot: Box::new(file),
         }))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use openraft::storage::RaftStateMachine;
+    use openraft::{CommittedLeaderId, Entry, EntryPayload, LogId, RaftSnapshotBuilder};
+    use crate::openraft::route::AppRequestData;
+    use crate::openraft::sotre::open_raft_db;
+    use crate::openraft::sotre::snapshot::SnapshotFiles;
+    use crate::openraft::typeconfig::TypeConfig;
+    use crate::storage::keys::key_openraft_last_applied;
+    use crate::storage::kv::{KvBatchOp, KvCompare, KvCompareResult, KvCompareTarget, KvStorage};
+    use crate::storage::lease::LeaseStorage;
+    use crate::storage::namespace::{namespace_key, Namespace, NamespaceStorage};
+    use crate::storage::rocksdb::RocksDBEngine;
+    use crate::storage::test_tools::TempDir;
+    use crate::watch::WatchHub;
+    use super::StateMachineStore;
+
+    async fn state_machine(dir: &TempDir) -> (StateMachineStore, Arc<RocksDBEngine>) {
+        let rocksdb_engine = dir.rocksdb_engine();
+        let db = Arc::new(open_raft_db(dir.join("openraft")));
+        let sm = StateMachineStore::new(
+            db,
+            rocksdb_engine.clone(),
+            Arc::new(WatchHub::new(100)),
+            SnapshotFiles::new(dir.path()),
+        )
+        .await
+        .unwrap();
+        (sm, rocksdb_engine)
+    }
+
+    fn entry(index: u64, req: AppRequestData) -> Entry<TypeConfig> {
+        Entry {
+            log_id: LogId::new(CommittedLeaderId::new(1, 1), index),
+            payload: EntryPayload::Normal(req),
+        }
+    }
+
+    #[tokio::test]
+    async fn apply_leases() {
+        let dir = TempDir::new();
+        let (mut sm, rocksdb_engine) = state_machine(&dir).await;
+        let kv_storage = KvStorage::new(rocksdb_engine.clone());
+        let lease_storage = LeaseStorage::new(rocksdb_engine);
+        let key = "/kv/default/leased".to_string();
+
+        let resp = sm.apply_entry(entry(1, AppRequestData::LeaseGrant { lease_id: 0, ttl: 10 })).unwrap();
+        assert!(resp.success);
+        assert_eq!(resp.value, Some("1".to_string()));
+        let resp = sm.apply_entry(entry(2, AppRequestData::LeaseGrant { lease_id: 1, ttl: 10 })).unwrap();
+        assert!(!resp.success);
+        assert!(resp.error.is_some());
+
+        let set = |value: &str, lease_id| AppRequestData::Set {
+            key: key.clone(),
+            value: value.to_string(),
+            lease_id,
+        };
+        let resp = sm.apply_entry(entry(3, set("v1", 1))).unwrap();
+        assert!(resp.success);
+        assert_eq!(lease_storage.get(1).unwrap().unwrap().keys, vec![key.clone()]);
+
+        // A write naming a missing lease is rejected without changing the key
+        let resp = sm.apply_entry(entry(4, set("v2", 9))).unwrap();
+        assert!(!resp.success);
+        assert_eq!(kv_storage.get(key.clone()).unwrap().unwrap(), "v1");
+
+        let resp = sm.apply_entry(entry(5, AppRequestData::LeaseRevoke { lease_id: 1 })).unwrap();
+        assert!(resp.success);
+        assert!(kv_storage.get(key.clone()).unwrap().is_none());
+        assert!(lease_storage.get(1).unwrap().is_none());
+        assert_eq!(sm.data.last_applied_log_id.unwrap().index, 5);
+
+        let resp = sm.apply_entry(entry(6, AppRequestData::LeaseRevoke { lease_id: 1 })).unwrap();
+        assert!(!resp.success);
+
+        // Allocated ids are not reused after a revoke and skip the ids that were chosen by clients
+        let resp = sm.apply_entry(entry(7, AppRequestData::LeaseGrant { lease_id: 2, ttl: 10 })).unwrap();
+        assert_eq!(resp.value, Some("2".to_string()));
+        let resp = sm.apply_entry(entry(8, AppRequestData::LeaseGrant { lease_id: 0, ttl: 10 })).unwrap();
+        assert_eq!(resp.value, Some("3".to_string()));
+    }
+
+    #[tokio::test]
+    async fn apply_txn() {
+        let dir = TempDir::new();
+        let (mut sm, rocksdb_engine) = state_machine(&dir).await;
+        let kv_storage = KvStorage::new(rocksdb_engine);
+        let key = |name: &str| format!("/kv/default/{}", name);
+        let txn = |expected: &str| AppRequestData::Txn {
+            compares: vec![KvCompare {
+                key: key("leader"),
+                target: KvCompareTarget::Value(expected.to_string()),
+                result: KvCompareResult::Equal,
+            }],
+            success: vec![
+                KvBatchOp::Set { key: key("leader"), value: "broker-1".to_string(), lease_id: 0 },
+                KvBatchOp::Set { key: key("term"), value: "1".to_string(), lease_id: 0 },
+            ],
+            failure: vec![KvBatchOp::Set { key: key("lost"), value: "broker-1".to_string(), lease_id: 0 }],
+        };
+
+        let resp = sm.apply_entry(entry(1, txn(""))).unwrap();
+        assert!(resp.success);
+        assert_eq!(kv_storage.get(key("leader")).unwrap().unwrap(), "broker-1");
+        assert_eq!(kv_storage.get_data(key("term")).unwrap().unwrap().mod_revision, 1);
+
+        // The compare no longer holds, only the failure ops are applied
+        let resp = sm.apply_entry(entry(2, txn(""))).unwrap();
+        assert!(!resp.success);
+        assert!(resp.error.is_none());
+        assert_eq!(kv_storage.get(key("lost")).unwrap().unwrap(), "broker-1");
+        assert_eq!(kv_storage.get_data(key("leader")).unwrap().unwrap().mod_revision, 1);
+    }
+
+    #[tokio::test]
+    async fn apply_import_within_namespace_quotas() {
+        let dir = TempDir::new();
+        let (mut sm, rocksdb_engine) = state_machine(&dir).await;
+        let namespace_storage = NamespaceStorage::new(rocksdb_engine.clone());
+        let kv_storage = KvStorage::new(rocksdb_engine);
+        let import = |namespaces: Vec<Namespace>, kvs: Vec<(String, String)>| AppRequestData::Import { namespaces, kvs };
+        let kv = |name: &str| (namespace_key("tenant", name), "v".to_string());
+
+        // The usage recorded by the export is not replayed, it is counted again from the keys
+        let mut tenant = Namespace::new("tenant");
+        tenant.max_keys = 2;
+        tenant.key_count = 5;
+        let resp = sm.apply_entry(entry(1, import(vec![tenant], Vec::new()))).unwrap();
+        assert!(resp.error.is_none());
+        let resp = sm.apply_entry(entry(2, import(Vec::new(), vec![kv("a"), kv("b")]))).unwrap();
+        assert!(resp.error.is_none());
+        let namespace = namespace_storage.get("tenant").unwrap().unwrap();
+        assert_eq!((namespace.max_keys, namespace.key_count), (2, 2));
+        assert_eq!(kv_storage.get_data(namespace_key("tenant", "a")).unwrap().unwrap().mod_revision, 2);
+
+        // A key over the quota rejects the whole batch
+        let resp = sm.apply_entry(entry(3, import(Vec::new(), vec![kv("c")]))).unwrap();
+        assert!(!resp.success);
+        assert!(resp.error.is_some());
+        assert!(kv_storage.get(namespace_key("tenant", "c")).unwrap().is_none());
+        assert_eq!(namespace_storage.get("tenant").unwrap().unwrap().key_count, 2);
+        assert_eq!(sm.data.last_applied_log_id.unwrap().index, 3);
+    }
+
+    #[tokio::test]
+    async fn build_and_install_snapshot() {
+        let source_dir = TempDir::new();
+        let (mut source, _) = state_machine(&source_dir).await;
+        let key = |i: usize| format!("/kv/default/k{}", i);
+        // More keys than a frame of the snapshot file holds
+        let kvs = (0..2500).map(|i| (key(i), format!("v{}", i))).collect();
+        source.apply_entry(entry(1, AppRequestData::BatchSet { kvs })).unwrap();
+
+        let snapshot = source.build_snapshot().await.unwrap();
+        assert_eq!(snapshot.meta.last_log_id.unwrap().index, 1);
+        // The raft store only records the meta and the file of the snapshot
+        let record = source.db.get_cf(source.store(), b"snapshot").unwrap().unwrap();
+        let record: serde_json::Value = serde_json::from_slice(&record).unwrap();
+        assert!(record.get("data").is_none());
+        let path = source.get_current_snapshot_().unwrap().unwrap().path;
+        assert!(path.exists());
+
+        let target_dir = TempDir::new();
+        {
+            let (mut target, rocksdb_engine) = state_machine(&target_dir).await;
+            target
+                .apply_entry(entry(1, AppRequestData::Set { key: key(9999), value: "stale".to_string(), lease_id: 0 }))
+                .unwrap();
+            // A received snapshot is installed from the file of the snapshot directory
+            let received = target.snapshot_files.snapshot_path(&snapshot.meta.snapshot_id).unwrap();
+            tokio::fs::create_dir_all(received.parent().unwrap()).await.unwrap();
+            tokio::fs::copy(&path, &received).await.unwrap();
+            let file = tokio::fs::File::open(&received).await.unwrap();
+            target.install_snapshot(&snapshot.meta, Box::new(file)).await.unwrap();
+
+            let kv_storage = KvStorage::new(rocksdb_engine.clone());
+            assert_eq!(kv_storage.get(key(0)).unwrap().unwrap(), "v0");
+            assert_eq!(kv_storage.get(key(2499)).unwrap().unwrap(), "v2499");
+            assert!(kv_storage.get(key(9999)).unwrap().is_none());
+            assert_eq!(target.data.last_applied_log_id, snapshot.meta.last_log_id);
+
+            // As if the node stopped before the install wrote the applied state
+            rocksdb_engine.delete(rocksdb_engine.cf_raft(), &key_openraft_last_applied()).unwrap();
+        }
+
+        let (target, rocksdb_engine) = state_machine(&target_dir).await;
+        assert_eq!(target.data.last_applied_log_id, snapshot.meta.last_log_id);
+        assert_eq!(KvStorage::new(rocksdb_engine).get(key(1234)).unwrap().unwrap(), "v1234");
+    }
+}
@@ -1,12 +1,14 @@
-use std::io::Cursor;
+use tokio::fs::File;
 use crate::openraft::raft_node::Node;
 use crate::openraft::route::{AppRequestData, AppResponseData};
 
-pub type SnapshotData = Cursor<Vec<u8>>;
+/// Snapshots are streamed to and from files, see `SnapshotFiles`.
+pub type SnapshotData = File;
 
 openraft::declare_raft_types!(
     pub TypeConfig:
         D = AppRequestData,
         R = AppResponseData,
         Node = Node,
+        SnapshotData = SnapshotData,
 );
\ No newline at end of file
@@ -14,6 +14,10 @@ use std::time::Duration;
 
 pub enum RaftResponseMessage {
     Success,
+    // The entry was applied, carrying the result returned by the state machine
+    Reply(Vec<u8>),
+    // The state machine rejected the entry
+    Error(String),
     Fail,
 }
 
@@ -38,6 +42,11 @@ pub enum RaftMessage {
         data: Vec<u8>,
         chan:  Sender<RaftResponseMessage>,
     },
+
+    // Wait until the local state machine has applied everything committed when the request arrived
+    ReadIndex {
+        chan: Sender<RaftResponseMessage>,
+    },
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -45,6 +54,20 @@ pub enum StorageDataType {
     // kv
     KvSet,
     KvDelete,
+    KvCompareAndSwap,
+    KvTxn,
+    KvBatchSet,
+    KvBatchDelete,
+
+    // lease
+    LeaseGrant,
+    LeaseRevoke,
+
+    // namespace
+    NamespaceSet,
+
+    // import, the value is a JSON `ImportBatch`
+    Import,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -103,7 +126,7 @@ impl RaftMachineApply {
         Ok(self
             .apply_raft_status_machine_message(
                 RaftMessage::Propose {
-                    data: serialize(&data).unwrap(),
+                    data: serialize_storage_data(&data)?,
                     chan: sx,
                 },
                 action,
@@ -112,6 +135,41 @@ impl RaftMachineApply {
         )
     }
 
+    /// Propose the data and wait until it is applied, returning the result produced by the state machine.
+    pub async fn apply_propose_message_with_reply(
+        &self,
+        data: StorageData,
+        action: String,
+    ) -> Result<Vec<u8>, RobustMQError> {
+        let (sx, rx) = oneshot::channel::<RaftResponseMessage>();
+        let message = RaftMessage::Propose {
+            data: serialize_storage_data(&data)?,
+            chan: sx,
+        };
+        self.send_raft_status_machine_message(message, &action).await?;
+
+        match timeout(Duration::from_secs(30), rx).await {
+            Ok(Ok(RaftResponseMessage::Reply(reply))) => Ok(reply),
+            Ok(Ok(RaftResponseMessage::Success)) => Ok(Vec::new()),
+            Ok(Ok(RaftResponseMessage::Error(e))) => Err(RobustMQError::CommonError(e)),
+            _ => Err(RobustMQError::RaftLogCommitTimeout(action)),
+        }
+    }
+
+    /// Confirm the leadership through raft ReadIndex and wait until this node has applied the
+    /// read index, after which a read from the local storage is linearizable.
+    pub async fn read_index(&self, action: String) -> Result<(), RobustMQError> {
+        let (sx, rx) = oneshot::channel::<RaftResponseMessage>();
+        self.send_raft_status_machine_message(RaftMessage::ReadIndex { chan: sx }, &action)
+            .await?;
+
+        match timeout(Duration::from_secs(30), rx).await {
+            Ok(Ok(RaftResponseMessage::Success)) => Ok(()),
+            Ok(Ok(RaftResponseMessage::Error(e))) => Err(RobustMQError::CommonError(e)),
+            _ => Err(RobustMQError::RaftLogCommitTimeout(action)),
+        }
+    }
+
     pub async fn  apply_raft_message(
         &self,
         message: raftPreludeMessage,
@@ -148,13 +206,21 @@ impl RaftMachineApply {
         action: String,
         rx: Receiver<RaftResponseMessage>,
     ) ->  Result<(), RobustMQError> {
-        let _ = self.raft_status_machine_sender.send(message).await;
+        self.send_raft_status_machine_message(message, &action).await?;
         if !self.wait_recv_chan_resp(rx).await {
             return Err(RobustMQError::RaftLogCommitTimeout(action))
         }
         Ok(())
     }
 
+    // The receiver is only dropped when the raft state machine stopped
+    async fn send_raft_status_machine_message(&self, message: RaftMessage, action: &str) -> Result<(), RobustMQError> {
+        match self.raft_status_machine_sender.send(message).await {
+            Ok(()) => Ok(()),
+            Err(_) => Err(RobustMQError::RaftStateMachineStopped(action.to_string())),
+        }
+    }
+
     pub async fn wait_recv_chan_resp(&self, rx: Receiver<RaftResponseMessage>) -> bool {
         let res = timeout(Duration::from_secs(30), async {
             match rx.await {
@@ -171,4 +237,11 @@ impl RaftMachineApply {
             Err(_) => false,
         }
     }
-}
\ No newline at end of file
+}
+
+fn serialize_storage_data(data: &StorageData) -> Result<Vec<u8>, RobustMQError> {
+    match serialize(data) {
+        Ok(data) => Ok(data),
+        Err(e) => Err(RobustMQError::CommonError(e.to_string())),
+    }
+}
@@ -1,31 +1,178 @@
 use std::sync::Arc;
 use prost::Message;
 use common_base::errors::RobustMQError;
-use protocol::kv::{DeleteRequest, SetRequest};
-use crate::storage::kv::KvStorage;
+use protocol::kv::txn_op::Op;
+use protocol::kv::{BatchDeleteRequest, BatchSetRequest, Compare, CompareAndSwapReply, CompareAndSwapRequest, CompareResult, CompareTarget, DeleteRequest, SetRequest, TxnOp, TxnReply, TxnRequest};
+use crate::storage::export::ImportBatch;
+use crate::storage::kv::{KvBatchOp, KvCompare, KvCompareResult, KvCompareTarget, KvStorage};
+use crate::storage::namespace::NamespaceStorage;
 use crate::storage::rocksdb::RocksDBEngine;
+use crate::watch::WatchHub;
 
 pub struct DataRouteKv {
     pub rocksdb_engine_handler: Arc<RocksDBEngine>,
     kv_storage: KvStorage,
+    watch_hub: Arc<WatchHub>,
 }
 
 impl DataRouteKv {
-    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>) -> Self {
+    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>, watch_hub: Arc<WatchHub>) -> Self {
         let kv_storage = KvStorage::new(rocksdb_engine_handler.clone());
         DataRouteKv {
             rocksdb_engine_handler,
             kv_storage,
+            watch_hub,
         }
     }
 
-    pub fn set(&self, value: Vec<u8>) -> Result<(), RobustMQError> {
+    /// Save the key, its lease key lists are updated in the same write batch.
+    pub fn set(&self, revision: u64, value: Vec<u8>) -> Result<(), RobustMQError> {
         let req: SetRequest = SetRequest::decode(value.as_ref()).unwrap();
-        self.kv_storage.set(req.key, req.value)
+        self.kv_storage
+            .set(req.key.clone(), req.value.clone(), req.lease_id, revision)?;
+        self.watch_hub.put(req.key, req.value, revision);
+        Ok(())
     }
 
-    pub fn delete(&self, value: Vec<u8>) -> Result<(), RobustMQError> {
+    pub fn delete(&self, revision: u64, value: Vec<u8>) -> Result<(), RobustMQError> {
         let req: DeleteRequest = DeleteRequest::decode(value.as_ref()).unwrap();
-        self.kv_storage.delete(req.key)
+        if self.kv_storage.get_data(req.key.clone())?.is_none() {
+            return Ok(());
+        }
+        self.kv_storage.delete(req.key.clone())?;
+        self.watch_hub.delete(req.key, revision);
+        Ok(())
+    }
+
+    pub fn compare_and_swap(&self, revision: u64, value: Vec<u8>) -> Result<Vec<u8>, RobustMQError> {
+        let req: CompareAndSwapRequest = CompareAndSwapRequest::decode(value.as_ref()).unwrap();
+        let (success, current_value) = self
+            .kv_storage
+            .compare_and_swap(req.key.clone(), req.expected_value, req.new_value, revision)?;
+        if success {
+            self.watch_hub.put(req.key, current_value.clone(), revision);
+        }
+        let reply = CompareAndSwapReply {
+            success,
+            current_value,
+        };
+        Ok(CompareAndSwapReply::encode_to_vec(&reply))
+    }
+
+    /// Evaluate the compares of the transaction and apply its success or failure ops
+    /// in a single write batch.
+    pub fn txn(&self, revision: u64, value: Vec<u8>) -> Result<Vec<u8>, RobustMQError> {
+        let req: TxnRequest = TxnRequest::decode(value.as_ref()).unwrap();
+        let compares: Vec<KvCompare> = req.compare.iter().map(kv_compare).collect();
+        let succeeded = self.kv_storage.compare_all(&compares)?;
+
+        let ops = if succeeded { req.success } else { req.failure };
+        let applied = self.kv_storage.batch(txn_batch_ops(ops), revision)?;
+        self.publish(applied, revision);
+
+        let reply = TxnReply { succeeded };
+        Ok(TxnReply::encode_to_vec(&reply))
+    }
+
+    /// Write every key of the batch in a single write batch.
+    pub fn batch_set(&self, revision: u64, value: Vec<u8>) -> Result<(), RobustMQError> {
+        let req: BatchSetRequest = BatchSetRequest::decode(value.as_ref()).unwrap();
+        let ops = req
+            .kvs
+            .into_iter()
+            .map(|kv| KvBatchOp::Set {
+                key: kv.key,
+                value: kv.value,
+                lease_id: 0,
+            })
+            .collect();
+        let applied = self.kv_storage.batch(ops, revision)?;
+        self.publish(applied, revision);
+        Ok(())
+    }
+
+    /// Delete every key of the batch in a single write batch, keys that do not exist are skipped.
+    pub fn batch_delete(&self, revision: u64, value: Vec<u8>) -> Result<(), RobustMQError> {
+        let req: BatchDeleteRequest = BatchDeleteRequest::decode(value.as_ref()).unwrap();
+        let ops = req
+            .keys
+            .into_iter()
+            .map(|key| KvBatchOp::Delete { key })
+            .collect();
+        let applied = self.kv_storage.batch(ops, revision)?;
+        self.publish(applied, revision);
+        Ok(())
+    }
+
+    /// Replay a batch of an import, its keys are written like a batch set.
+    pub fn import(&self, revision: u64, value: Vec<u8>) -> Result<(), RobustMQError> {
+        let batch: ImportBatch = serde_json::from_slice(&value)?;
+        let namespace_storage = NamespaceStorage::new(self.rocksdb_engine_handler.clone());
+        for namespace in batch.namespaces {
+            namespace_storage.set_limits(
+                &namespace.name,
+                namespace.max_keys,
+                namespace.max_bytes,
+                namespace.max_value_size,
+            )?;
+        }
+        let ops = batch
+            .kvs
+            .into_iter()
+            .map(|(key, value)| KvBatchOp::Set {
+                key,
+                value,
+                lease_id: 0,
+            })
+            .collect();
+        let applied = self.kv_storage.batch(ops, revision)?;
+        self.publish(applied, revision);
+        Ok(())
     }
-}
\ No newline at end of file
+
+    fn publish(&self, applied: Vec<KvBatchOp>, revision: u64) {
+        for op in applied {
+            match op {
+                KvBatchOp::Set { key, value, .. } => self.watch_hub.put(key, value, revision),
+                KvBatchOp::Delete { key } => self.watch_hub.delete(key, revision),
+            }
+        }
+    }
+}
+
+/// The storage compare of a compare of a `TxnRequest`.
+pub fn kv_compare(compare: &Compare) -> KvCompare {
+    let target = match compare.target() {
+        CompareTarget::Value => KvCompareTarget::Value(compare.value.clone()),
+        CompareTarget::Version => KvCompareTarget::Version(compare.version),
+        CompareTarget::Exists => KvCompareTarget::Exists(compare.exists),
+    };
+    let result = match compare.result() {
+        CompareResult::Equal => KvCompareResult::Equal,
+        CompareResult::NotEqual => KvCompareResult::NotEqual,
+        CompareResult::Greater => KvCompareResult::Greater,
+        CompareResult::Less => KvCompareResult::Less,
+    };
+    KvCompare {
+        key: compare.key.clone(),
+        target,
+        result,
+    }
+}
+
+/// The storage writes of the ops of a `TxnRequest`.
+pub fn txn_batch_ops(ops: Vec<TxnOp>) -> Vec<KvBatchOp> {
+    let mut results = Vec::with_capacity(ops.len());
+    for op in ops {
+        match op.op {
+            Some(Op::Set(req)) => results.push(KvBatchOp::Set {
+                key: req.key,
+                value: req.value,
+                lease_id: req.lease_id,
+            }),
+            Some(Op::Delete(req)) => results.push(KvBatchOp::Delete { key: req.key }),
+            None => {}
+        }
+    }
+    results
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use log::info;
+use prost::Message;
+use common_base::errors::RobustMQError;
+use protocol::kv::{LeaseGrantReply, LeaseGrantRequest, LeaseRevokeRequest};
+use crate::storage::engine::{engine_batch_by_kv, EngineBatchOp};
+use crate::storage::keys::key_lease;
+use crate::storage::kv::{KvBatchOp, KvStorage};
+use crate::storage::lease::LeaseStorage;
+use crate::storage::rocksdb::RocksDBEngine;
+use crate::watch::WatchHub;
+
+pub struct DataRouteLease {
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    kv_storage: KvStorage,
+    lease_storage: LeaseStorage,
+    watch_hub: Arc<WatchHub>,
+}
+
+impl DataRouteLease {
+    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>, watch_hub: Arc<WatchHub>) -> Self {
+        let kv_storage = KvStorage::new(rocksdb_engine_handler.clone());
+        let lease_storage = LeaseStorage::new(rocksdb_engine_handler.clone());
+        DataRouteLease {
+            rocksdb_engine_handler,
+            kv_storage,
+            lease_storage,
+            watch_hub,
+        }
+    }
+
+    pub fn grant(&self, value: Vec<u8>) -> Result<Vec<u8>, RobustMQError> {
+        let req: LeaseGrantRequest = LeaseGrantRequest::decode(value.as_ref()).unwrap();
+        let (lease_id, ops) = self.lease_storage.grant_op(req.lease_id, req.ttl)?;
+        engine_batch_by_kv(self.rocksdb_engine_handler.clone(), ops)?;
+
+        let reply = LeaseGrantReply {
+            lease_id,
+            ttl: req.ttl,
+        };
+        Ok(LeaseGrantReply::encode_to_vec(&reply))
+    }
+
+    /// Delete the lease together with every key that is still attached to it, in one write batch.
+    /// Keys that were rewritten without the lease after being attached are kept.
+    pub fn revoke(&self, revision: u64, value: Vec<u8>) -> Result<(), RobustMQError> {
+        let req: LeaseRevokeRequest = LeaseRevokeRequest::decode(value.as_ref()).unwrap();
+        let ops = self
+            .lease_storage
+            .attached_keys(req.lease_id)?
+            .into_iter()
+            .map(|key| KvBatchOp::Delete { key })
+            .collect();
+
+        // The deletes detach the keys from the lease, which is then deleted by the same batch
+        let (mut engine_ops, applied) = self.kv_storage.batch_ops(ops, revision)?;
+        engine_ops.push(EngineBatchOp::Delete(key_lease(req.lease_id)));
+        engine_batch_by_kv(self.rocksdb_engine_handler.clone(), engine_ops)?;
+
+        for op in applied {
+            if let KvBatchOp::Delete { key } = op {
+                self.watch_hub.delete(key, revision);
+            }
+        }
+
+        info!("Lease {} was revoked", req.lease_id);
+        Ok(())
+    }
+}
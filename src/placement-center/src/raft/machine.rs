@@ -11,7 +11,7 @@ use bincode::{deserialize, serialize};
 use log::{error, info};
 use prost::Message;
 use raft::eraftpb::{ConfChange, ConfChangeType, Entry, EntryType, Message as raftPreludeMessage, MessageType, Snapshot};
-use raft::{Config, RawNode};
+use raft::{Config, RawNode, ReadState};
 
 use slog::o;
 use slog::Drain;
@@ -30,6 +30,9 @@ pub struct RaftMachine {
     receiver: Receiver<RaftMessage>,
     seqnum: AtomicUsize,
     resp_channel: HashMap<usize, oneshot::Sender<RaftResponseMessage>>,
+    read_channel: HashMap<usize, oneshot::Sender<RaftResponseMessage>>,
+    // Reads confirmed by the leader, waiting for the local apply index to reach their read index
+    pending_reads: Vec<(u64, oneshot::Sender<RaftResponseMessage>)>,
     data_route: Arc<RwLock<DataRoute>>,
     entry_num: AtomicUsize,
     peer_message_send: Sender<PeerMessage>,
@@ -54,6 +57,8 @@ impl RaftMachine {
             receiver,
             seqnum,
             resp_channel,
+            read_channel: HashMap::new(),
+            pending_reads: Vec::new(),
             data_route,
             entry_num,
             peer_message_send,
@@ -136,6 +141,14 @@ impl RaftMachine {
                         }
                     }
                 }
+
+                Ok(Some(RaftMessage::ReadIndex { chan })) => {
+                    let seq = self
+                        .seqnum
+                        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    raft_node.read_index(serialize(&seq).unwrap());
+                    self.read_channel.insert(seq, chan);
+                }
                 Ok(None) => continue,
                 Err(_) => {}
             }
@@ -156,7 +169,18 @@ impl RaftMachine {
                 self.placement_cluster
                     .write()
                     .unwrap()
-                    .set_role(raft_node.raft.state)
+                    .set_role(raft_node.raft.state);
+                // ReadIndex requests in flight are not answered after a role change, fail them now
+                self.read_channel.clear();
+            }
+
+            // Followers forward writes to the leader they know
+            if self.placement_cluster.read().unwrap().leader_id != raft_node.raft.leader_id {
+                info!("Raft leader changes to node {}", raft_node.raft.leader_id);
+                self.placement_cluster
+                    .write()
+                    .unwrap()
+                    .set_leader(raft_node.raft.leader_id);
             }
             // info!(&format!("{:?}",raft_node.raft.state));
             self.on_ready(&mut raft_node).await;
@@ -198,6 +222,11 @@ impl RaftMachine {
         // The committed raft log can be applied to the State Machine.
         self.handle_committed_entries(raft_node, ready.take_committed_entries());
 
+        // ReadIndex requests confirmed by the leader
+        if !ready.read_states().is_empty() {
+            self.handle_read_states(ready.take_read_states());
+        }
+
         // If there is a change in HardState, such as a re-vote,
         // term is increased, the hs will not be empty.Persist non-empty hs.
         if let Some(hs) = ready.hs() {
@@ -223,6 +252,36 @@ impl RaftMachine {
         self.handle_committed_entries(raft_node, light_rd.take_committed_entries());
 
         raft_node.advance_apply();
+
+        self.release_pending_reads(raft_node.raft.raft_log.applied);
+    }
+
+    fn handle_read_states(&mut self, read_states: Vec<ReadState>) {
+        for read_state in read_states {
+            match deserialize::<usize>(&read_state.request_ctx) {
+                Ok(seq) => {
+                    if let Some(chan) = self.read_channel.remove(&seq) {
+                        self.pending_reads.push((read_state.index, chan));
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to parse the context of read state with error message {:?}", e);
+                }
+            }
+        }
+    }
+
+    fn release_pending_reads(&mut self, applied: u64) {
+        let pending_reads = std::mem::take(&mut self.pending_reads);
+        for (index, chan) in pending_reads {
+            if index > applied {
+                self.pending_reads.push((index, chan));
+                continue;
+            }
+            if chan.send(RaftResponseMessage::Success).is_err() {
+                error!("{}", "read index Fails to return data to chan. chan may have been closed");
+            }
+        }
     }
 
     fn handle_committed_entries(
@@ -232,15 +291,19 @@ impl RaftMachine {
     ) {
         let data_route = self.data_route.write().unwrap();
         for entry in entries {
+            let mut reply = RaftResponseMessage::Reply(Vec::new());
             if !entry.data.is_empty() {
                 info!("ready entrys entry type:{:?}", entry.get_entry_type());
                 match entry.get_entry_type() {
                     EntryType::EntryNormal => {
                         // Saves the service data sent by the client
-                        match data_route.route(entry.get_data().to_vec()) {
-                            Ok(_) => {}
+                        match data_route.route(entry.get_index(), entry.get_data().to_vec()) {
+                            Ok(data) => {
+                                reply = RaftResponseMessage::Reply(data);
+                            }
                             Err(err) => {
                                 error!("{}", err);
+                                reply = RaftResponseMessage::Error(err.to_string());
                             }
                         }
                     }
@@ -262,15 +325,31 @@ impl RaftMachine {
                                     }
                                 }
                             }
+                            ConfChangeType::AddLearnerNode => {
+                                match deserialize::<BrokerNode>(change.get_context()) {
+                                    Ok(node) => {
+                                        let mut cls = self.placement_cluster.write().unwrap();
+                                        cls.add_learner(id, node);
+                                    }
+                                    Err(e) => {
+                                        error!("Failed to parse Node data from context with error message {:?}", e);
+                                    }
+                                }
+                            }
                             ConfChangeType::RemoveNode => {
                                 let mut cls = self.placement_cluster.write().unwrap();
                                 cls.remove_peer(id);
                             }
-                            _ => unimplemented!(),
                         }
 
                         if let Ok(cs) = raft_node.apply_conf_change(&change) {
                             let _ = raft_node.mut_store().set_conf_state(cs);
+                            let mut cls = self.placement_cluster.write().unwrap();
+                            cls.membership_log_index = entry.get_index();
+                            let peers = cls.peers.values().cloned().collect();
+                            if let Err(e) = raft_node.mut_store().write_lock().save_peers(peers) {
+                                error!("Failed to save the raft peers with error message {:?}", e);
+                            }
                         }
                     }
                     EntryType::EntryConfChangeV2 => {}
@@ -282,7 +361,7 @@ impl RaftMachine {
 
             match deserialize(entry.get_context()) {
                 Ok(seq) => match self.resp_channel.remove(&seq) {
-                    Some(chan) => match chan.send(RaftResponseMessage::Success) {
+                    Some(chan) => match chan.send(reply) {
                         Ok(_) => {}
                         Err(_) => {
                             error!("commit entry Fails to return data to chan. chan may have been closed");
@@ -311,17 +390,27 @@ impl RaftMachine {
     }
 
     pub async fn new_node(&self) -> RawNode<RaftRocksDBStorage> {
-        let cluster = self.placement_cluster.read().unwrap();
+        let mut cluster = self.placement_cluster.write().unwrap();
         let storage = RaftRocksDBStorage::new(self.raft_storage.clone());
 
         // build config
         let hs = storage.read_lock().hard_state();
         let conf = self.build_config(hs.commit);
 
-        // init voters && learns
+        // The voters and learners come from the configuration on the first boot only, then from
+        // the conf changes, whose ConfState and peers are persisted
         let mut cs = storage.read_lock().conf_state();
-        cs.voters = cluster.node_ids();
-        let _ = storage.write_lock().save_conf_state(cs);
+        if cs.voters.is_empty() && cs.learners.is_empty() {
+            cs.voters = cluster.node_ids();
+            cs.learners = cluster.learner_ids();
+            let _ = storage.write_lock().save_conf_state(cs);
+        } else {
+            let saved_peers = storage.read_lock().peers().unwrap_or_else(|e| {
+                error!("Failed to read the raft peers with error message {:?}", e);
+                Vec::new()
+            });
+            cluster.restore_membership(&cs.voters, &cs.learners, saved_peers);
+        }
 
         let logger = self.build_slog();
         let node = RawNode::new(&conf, storage, &logger).unwrap();
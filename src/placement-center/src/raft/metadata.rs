@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use log::__private_api::loc;
 use log::info;
 use raft::StateRole;
@@ -21,9 +21,15 @@ pub enum NodeState {
 pub struct RaftGroupMetadata {
     pub local: BrokerNode,
     pub leader: Option<BrokerNode>,
+    /// Id of the leader elected by raft, 0 when there is none
+    pub leader_id: u64,
     pub state: NodeState,
     pub raft_role: StateRole,
     pub peers: HashMap<u64, BrokerNode>,
+    /// Peers added as learners and not promoted to voters yet
+    pub learners: HashSet<u64>,
+    /// Index of the last applied conf change, 0 when none was applied since the start
+    pub membership_log_index: u64,
 }
 
 impl RaftGroupMetadata {
@@ -44,7 +50,7 @@ impl RaftGroupMetadata {
 
             node.cluster_type = ClusterType::PlacementCenter.as_str_name().to_string();
             node.cluster_name = config.cluster_name.clone();
-            node.node_inner_addr = addr.to_string();
+            node.node_inner_addr = addr.as_str().unwrap().to_string();
             node.node_ip = ip.to_string();
             node.node_id = id;
             peers.insert(id, node);
@@ -53,9 +59,12 @@ impl RaftGroupMetadata {
         RaftGroupMetadata {
             local,
             leader: None,
+            leader_id: 0,
             raft_role: StateRole::Follower,
             state: NodeState::Starting,
             peers,
+            learners: HashSet::new(),
+            membership_log_index: 0,
         }
     }
 
@@ -63,16 +72,29 @@ impl RaftGroupMetadata {
         self.peers.get(&id)
     }
 
+    /// Add a voter, or promote the peer when it is a learner.
     pub fn add_peer(&mut self, id: u64, node: BrokerNode) {
         info!("add peer node:{:?}", node);
+        self.learners.remove(&id);
+        self.peers.insert(id, node);
+    }
+
+    pub fn add_learner(&mut self, id: u64, node: BrokerNode) {
+        info!("add learner node:{:?}", node);
+        self.learners.insert(id);
         self.peers.insert(id, node);
     }
 
     pub fn remove_peer(&mut self, id: u64) {
         info!("remove peer node id:{:?}", id);
+        self.learners.remove(&id);
         self.peers.remove(&id);
     }
 
+    pub fn is_learner(&self, id: u64) -> bool {
+        self.learners.contains(&id)
+    }
+
     pub fn is_leader(&self) -> bool {
         self.raft_role == StateRole::Leader
     }
@@ -81,14 +103,42 @@ impl RaftGroupMetadata {
         self.raft_role = role;
     }
 
+    /// Record the leader elected by raft, 0 when there is none.
+    pub fn set_leader(&mut self, leader_id: u64) {
+        self.leader_id = leader_id;
+        self.leader = if leader_id == self.local.node_id {
+            Some(self.local.clone())
+        } else {
+            self.peers.get(&leader_id).cloned()
+        };
+    }
+
+    /// Ids of the voters, learners are left out.
     pub fn node_ids(&self) -> Vec<u64> {
         let mut voters = Vec::new();
         for (id, _) in self.peers.iter() {
-            voters.push(*id);
+            if !self.learners.contains(id) {
+                voters.push(*id);
+            }
         }
         voters
     }
 
+    pub fn learner_ids(&self) -> Vec<u64> {
+        self.learners.iter().copied().collect()
+    }
+
+    /// Replace the peers of the configuration with the membership persisted by raft. A peer
+    /// that is not in the configuration is known by the address saved with the membership.
+    pub fn restore_membership(&mut self, voters: &[u64], learners: &[u64], saved_peers: Vec<BrokerNode>) {
+        for node in saved_peers {
+            self.peers.entry(node.node_id).or_insert(node);
+        }
+        self.peers
+            .retain(|id, _| voters.contains(id) || learners.contains(id));
+        self.learners = learners.iter().copied().collect();
+    }
+
     pub fn leader_addr(&self) -> String {
         if let Some(leader) = self.leader.clone() {
             return leader.node_inner_addr;
@@ -5,4 +5,6 @@ pub mod node;
 pub mod route;
 pub mod storage;
 pub mod kv;
+pub mod lease;
+pub mod namespace;
 pub mod peer;
\ No newline at end of file
@@ -0,0 +1,23 @@
+use std::sync::Arc;
+use prost::Message;
+use common_base::errors::RobustMQError;
+use protocol::kv::NamespaceSetRequest;
+use crate::storage::namespace::NamespaceStorage;
+use crate::storage::rocksdb::RocksDBEngine;
+
+pub struct DataRouteNamespace {
+    namespace_storage: NamespaceStorage,
+}
+
+impl DataRouteNamespace {
+    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>) -> Self {
+        let namespace_storage = NamespaceStorage::new(rocksdb_engine_handler);
+        DataRouteNamespace { namespace_storage }
+    }
+
+    pub fn set(&self, value: Vec<u8>) -> Result<(), RobustMQError> {
+        let req: NamespaceSetRequest = NamespaceSetRequest::decode(value.as_ref()).unwrap();
+        self.namespace_storage
+            .set_limits(&req.namespace, req.max_keys, req.max_bytes, req.max_value_size)
+    }
+}
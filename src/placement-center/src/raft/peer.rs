@@ -34,9 +34,14 @@ impl PeersManager {
                 let request: SendRaftMessageRequest = SendRaftMessageRequest {
                     message: data.data,
                 };
-                let mut client = PlacementCenterServiceClient::connect(format!("http://{}", addr))
-                    .await
-                    .unwrap();
+                // An unreachable peer must not stop the messages to the other peers
+                let mut client = match PlacementCenterServiceClient::connect(format!("http://{}", addr)).await {
+                    Ok(client) => client,
+                    Err(e) => {
+                        error!("Failed to connect to node {}, error message: {}", addr, e);
+                        continue;
+                    }
+                };
 
                 match client.send_raft_message(request).await {
                     Ok(_) => debug!("Send Raft message to node {} Successful.", addr),
@@ -3,25 +3,65 @@ use bincode::deserialize;
 use common_base::errors::RobustMQError;
 use crate::raft::apply::{StorageData, StorageDataType};
 use crate::raft::kv::DataRouteKv;
+use crate::raft::lease::DataRouteLease;
+use crate::raft::namespace::DataRouteNamespace;
 use crate::storage::rocksdb::RocksDBEngine;
+use crate::watch::WatchHub;
 
 pub struct DataRoute {
     route_kv: DataRouteKv,
+    route_lease: DataRouteLease,
+    route_namespace: DataRouteNamespace,
 }
 
 impl DataRoute {
-    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>) -> DataRoute {
-        let route_kv = DataRouteKv::new(rocksdb_engine_handler.clone());
+    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>, watch_hub: Arc<WatchHub>) -> DataRoute {
+        let route_kv = DataRouteKv::new(rocksdb_engine_handler.clone(), watch_hub.clone());
+        let route_lease = DataRouteLease::new(rocksdb_engine_handler.clone(), watch_hub);
+        let route_namespace = DataRouteNamespace::new(rocksdb_engine_handler);
         DataRoute {
-            route_kv
+            route_kv,
+            route_lease,
+            route_namespace,
         }
     }
 
-    pub fn route(&self, data: Vec<u8>) -> Result<(), RobustMQError> {
+    /// Apply a committed entry to the storage, `index` is the raft log index of the entry
+    /// and is used as the revision of the keys it writes and of the watch events it emits.
+    pub fn route(&self, index: u64, data: Vec<u8>) -> Result<Vec<u8>, RobustMQError> {
         let storage_data: StorageData = deserialize(data.as_ref()).unwrap();
         match storage_data.data_type {
-            StorageDataType::KvSet => self.route_kv.set(storage_data.value),
-            StorageDataType::KvDelete => self.route_kv.delete(storage_data.value),
+            StorageDataType::KvSet => {
+                self.route_kv.set(index, storage_data.value)?;
+                Ok(Vec::new())
+            }
+            StorageDataType::KvDelete => {
+                self.route_kv.delete(index, storage_data.value)?;
+                Ok(Vec::new())
+            }
+            StorageDataType::KvCompareAndSwap => self.route_kv.compare_and_swap(index, storage_data.value),
+            StorageDataType::KvTxn => self.route_kv.txn(index, storage_data.value),
+            StorageDataType::KvBatchSet => {
+                self.route_kv.batch_set(index, storage_data.value)?;
+                Ok(Vec::new())
+            }
+            StorageDataType::KvBatchDelete => {
+                self.route_kv.batch_delete(index, storage_data.value)?;
+                Ok(Vec::new())
+            }
+            StorageDataType::LeaseGrant => self.route_lease.grant(storage_data.value),
+            StorageDataType::LeaseRevoke => {
+                self.route_lease.revoke(index, storage_data.value)?;
+                Ok(Vec::new())
+            }
+            StorageDataType::NamespaceSet => {
+                self.route_namespace.set(storage_data.value)?;
+                Ok(Vec::new())
+            }
+            StorageDataType::Import => {
+                self.route_kv.import(index, storage_data.value)?;
+                Ok(Vec::new())
+            }
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+use prost::Message;
+use tonic::{Response, Status};
+use clients::placement::{forward_call, PlacementCenterInterface};
+use clients::poll::ClientPool;
+use common_base::errors::{not_leader_status, RobustMQError};
+
+/// Serve a mutating request received by a node that is not the leader by proxying it to the
+/// leader. A request that was already forwarded once, or that arrives while the leader is
+/// unknown, fails with a NotLeader status the clients follow instead.
+pub(crate) async fn forward_to_leader<Req, Reply>(
+    client_poll: Arc<ClientPool>,
+    leader_addr: String,
+    interface: PlacementCenterInterface,
+    forwarded: bool,
+    request: &Req,
+) -> Result<Response<Reply>, Status>
+where
+    Req: Message,
+    Reply: Message + Default,
+{
+    if forwarded || leader_addr.is_empty() {
+        return Err(not_leader_status(&leader_addr));
+    }
+    match forward_call(interface, client_poll, leader_addr, request.encode_to_vec()).await {
+        Ok(data) => match Reply::decode(data.as_ref()) {
+            Ok(reply) => Ok(Response::new(reply)),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        },
+        // The status of the leader is passed on, a NotLeader status keeps naming the leader
+        Err(RobustMQError::GrpcServerStatus(status)) => Err(status),
+        Err(e) => Err(Status::unavailable(e.to_string())),
+    }
+}
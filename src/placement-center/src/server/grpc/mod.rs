@@ -1,5 +1,5 @@
 pub mod server;
+mod forward;
 mod services_kv;
-mod services_kv_new;
 mod services_openraft;
 mod services_raft;
\ No newline at end of file
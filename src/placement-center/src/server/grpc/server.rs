@@ -1,4 +1,4 @@
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 use log::info;
 use openraft::Raft;
 use tokio::select;
@@ -9,30 +9,39 @@ use common_base::config::placement_center::placement_center_conf;
 use protocol::kv::kv_service_server::KvServiceServer;
 use protocol::openraft::open_raft_service_server::OpenRaftServiceServer;
 use protocol::placement::placement_center_service_server::PlacementCenterServiceServer;
+use crate::consensus::ConsensusEngine;
+use crate::lease::LeaseManager;
+use crate::openraft::sotre::snapshot::SnapshotFiles;
 use crate::openraft::typeconfig::TypeConfig;
 use crate::raft::apply::RaftMachineApply;
-use crate::raft::metadata::RaftGroupMetadata;
 use crate::server::grpc::services_kv::GrpcKvServices;
 use crate::server::grpc::services_openraft::GrpcOpenRaftServices;
 use crate::server::grpc::services_raft::GrpcRaftServices;
 use crate::storage::rocksdb::RocksDBEngine;
+use crate::watch::WatchHub;
 
+/// `raft_node` is set with the openraft engine and `placement_center_storage` with the raft-rs
+/// one, each serves the transport of its engine.
 pub async fn start_grpc_server(
     client_poll: Arc<ClientPool>,
-    raft_node: Raft<TypeConfig>,
-    placement_center_storage: Arc<RaftMachineApply>,
+    raft_node: Option<Raft<TypeConfig>>,
+    placement_center_storage: Option<Arc<RaftMachineApply>>,
+    engine: Arc<dyn ConsensusEngine>,
     rocksdb_engine_handler: Arc<RocksDBEngine>,
-    placement_cluster: Arc<RwLock<RaftGroupMetadata>>,
+    lease_manager: Arc<LeaseManager>,
+    watch_hub: Arc<WatchHub>,
     stop_sx: broadcast::Sender<bool>,
 ) {
     let config = placement_center_conf();
-    let server = GrpcServer::new(config.grpc_port);
+    let server = GrpcServer::new(config.grpc_port, SnapshotFiles::new(&config.data_path));
     server
         .start(
             client_poll,
             placement_center_storage,
+            engine,
             rocksdb_engine_handler,
-            placement_cluster,
+            lease_manager,
+            watch_hub,
             stop_sx,
             raft_node,
         )
@@ -41,36 +50,44 @@ pub async fn start_grpc_server(
 
 pub struct GrpcServer {
     port: usize,
+    snapshot_files: SnapshotFiles,
 }
 
 impl GrpcServer {
-    pub fn new(port: usize) -> Self {
+    pub fn new(port: usize, snapshot_files: SnapshotFiles) -> Self {
         Self {
             port,
+            snapshot_files,
         }
     }
 
     pub async fn start(
         &self,
         client_poll: Arc<ClientPool>,
-        placement_center_storage: Arc<RaftMachineApply>,
+        placement_center_storage: Option<Arc<RaftMachineApply>>,
+        engine: Arc<dyn ConsensusEngine>,
         rocksdb_engine_handler: Arc<RocksDBEngine>,
-        placement_cluster: Arc<RwLock<RaftGroupMetadata>>,
+        lease_manager: Arc<LeaseManager>,
+        watch_hub: Arc<WatchHub>,
         stop_sx: broadcast::Sender<bool>,
-        raft_node: Raft<TypeConfig>,
+        raft_node: Option<Raft<TypeConfig>>,
     ) {
         let addr = format!("0.0.0.0:{}", self.port).parse().unwrap();
         info!("Broker Grpc Server start. port:{}", self.port);
 
         let kv_service_handler = GrpcKvServices::new(
             client_poll.clone(),
-            placement_center_storage.clone(),
+            engine.clone(),
             rocksdb_engine_handler,
-            placement_cluster,
+            lease_manager,
+            watch_hub,
         );
-        let raft_service_handler = GrpcRaftServices::new(placement_center_storage);
+        let raft_service_handler = placement_center_storage
+            .map(|storage| PlacementCenterServiceServer::new(GrpcRaftServices::new(storage)));
 
-        let openraft_service_handler = GrpcOpenRaftServices::new(raft_node);
+        let openraft_service_handler = raft_node.map(|raft_node| {
+            OpenRaftServiceServer::new(GrpcOpenRaftServices::new(raft_node, self.snapshot_files.clone(), engine))
+        });
 
         let mut stop_rx = stop_sx.subscribe();
         select! {
@@ -86,8 +103,8 @@ impl GrpcServer {
                 }
             },
             val =  Server::builder().add_service(KvServiceServer::new(kv_service_handler))
-                                    .add_service(PlacementCenterServiceServer::new(raft_service_handler))
-                                    .add_service(OpenRaftServiceServer::new(openraft_service_handler))
+                                    .add_optional_service(raft_service_handler)
+                                    .add_optional_service(openraft_service_handler)
                                     .serve(addr)=>{
                 match val{
                     Ok(()) => {
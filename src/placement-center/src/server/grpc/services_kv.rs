@@ -1,119 +1,190 @@
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
+use log::error;
 use prost::Message;
-use tonic::{Request, Response, Status};
-use clients::placement::kv::call::{placement_delete, placement_set};
+use tokio::sync::mpsc;
+use tonic::codegen::tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+use clients::placement::{forward_lease_keep_alive, is_forwarded, PlacementCenterInterface};
 use clients::poll::ClientPool;
-use common_base::errors::RobustMQError;
+use common_base::errors::{not_leader_status, RobustMQError};
 use protocol::kv::kv_service_server::KvService;
-use protocol::kv::{CommonReply, DeleteRequest, ExistsReply, ExistsRequest, GetReply, GetRequest, SetRequest};
-use crate::raft::apply::{RaftMachineApply, StorageData, StorageDataType};
-use crate::raft::metadata::RaftGroupMetadata;
-use crate::storage::kv::KvStorage;
+use protocol::kv::{BatchDeleteRequest, BatchSetRequest, CommonReply, CompareAndSwapReply, CompareAndSwapRequest, DeleteRequest, ExistsReply, ExistsRequest, GetReply, GetRequest, KeyValue, LeaseGrantReply, LeaseGrantRequest, LeaseKeepAliveReply, LeaseKeepAliveRequest, LeaseRevokeRequest, ListReply, ListRequest, NamespaceGetReply, NamespaceGetRequest, NamespaceSetRequest, ReadConsistency, SetRequest, TxnOp, TxnReply, TxnRequest, WatchReply, WatchRequest};
+use protocol::kv::txn_op::Op;
+use crate::consensus::ConsensusEngine;
+use crate::lease::LeaseManager;
+use crate::raft::apply::{StorageData, StorageDataType};
+use crate::server::grpc::forward::forward_to_leader;
+use crate::storage::kv::{KvStorage, DEFAULT_LIST_LIMIT, MAX_LIST_LIMIT};
+use crate::storage::namespace::{namespace_key, namespace_name, namespace_user_key, NamespaceStorage};
 use crate::storage::rocksdb::RocksDBEngine;
+use crate::watch::{watch_stream, WatchHub};
 
 pub struct GrpcKvServices {
     client_poll: Arc<ClientPool>,
-    placement_center_storage: Arc<RaftMachineApply>,
+    engine: Arc<dyn ConsensusEngine>,
     rocksdb_engine_handler: Arc<RocksDBEngine>,
-    placement_cluster: Arc<RwLock<RaftGroupMetadata>>,
+    lease_manager: Arc<LeaseManager>,
+    watch_hub: Arc<WatchHub>,
 }
 
 impl GrpcKvServices {
     pub fn new(
         client_poll: Arc<ClientPool>,
-        placement_center_storage: Arc<RaftMachineApply>,
+        engine: Arc<dyn ConsensusEngine>,
         rocksdb_engine_handler: Arc<RocksDBEngine>,
-        placement_cluster: Arc<RwLock<RaftGroupMetadata>>,
+        lease_manager: Arc<LeaseManager>,
+        watch_hub: Arc<WatchHub>,
     ) -> Self {
         GrpcKvServices {
             client_poll,
-            placement_center_storage,
+            engine,
             rocksdb_engine_handler,
-            placement_cluster,
+            lease_manager,
+            watch_hub,
         }
     }
 
     pub fn is_leader(&self) -> bool {
-        self.placement_cluster.read().unwrap().is_leader()
+        self.engine.is_leader()
+    }
+
+    /// Relay the keep alive requests of a client to the leader and its replies back to the
+    /// client, until either side closes its stream.
+    async fn forward_lease_keep_alive(
+        &self,
+        leader_addr: String,
+        mut stream: Streaming<LeaseKeepAliveRequest>,
+    ) -> Result<Response<ReceiverStream<Result<LeaseKeepAliveReply, Status>>>, Status> {
+        let (request_sx, request_rx) = mpsc::channel(100);
+        let mut replies = match forward_lease_keep_alive(
+            self.client_poll.clone(),
+            leader_addr,
+            ReceiverStream::new(request_rx),
+        )
+        .await
+        {
+            Ok(replies) => replies,
+            // The status of the leader is passed on, a NotLeader status keeps naming the leader
+            Err(RobustMQError::GrpcServerStatus(status)) => return Err(status),
+            Err(e) => return Err(Status::unavailable(e.to_string())),
+        };
+
+        tokio::spawn(async move {
+            loop {
+                match stream.message().await {
+                    Ok(Some(req)) => {
+                        if request_sx.send(req).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("Lease keep alive stream was interrupted with error message: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        let (sx, rx) = mpsc::channel(100);
+        tokio::spawn(async move {
+            loop {
+                let reply = match replies.message().await {
+                    Ok(Some(reply)) => Ok(reply),
+                    Ok(None) => break,
+                    Err(status) => Err(status),
+                };
+                let failed = reply.is_err();
+                if sx.send(reply).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
     }
 
     pub fn leader_addr(&self) -> String {
-        self.placement_cluster.read().unwrap().leader_addr()
+        self.engine.leader_addr()
+    }
+
+    /// Make sure a read of the local storage observes every write committed before it,
+    /// unless the client asked for a serializable read.
+    async fn read_barrier(&self, consistency: ReadConsistency, action: &str) -> Result<(), Status> {
+        if consistency == ReadConsistency::Serializable {
+            return Ok(());
+        }
+        match self.engine.read_index(action).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(engine_status(e)),
+        }
     }
 }
 
 #[tonic::async_trait]
 impl KvService for GrpcKvServices {
     async fn set(&self, request: Request<SetRequest>) -> Result<Response<CommonReply>, Status> {
-        let req = request.into_inner();
+        let forwarded = is_forwarded(&request);
+        let mut req = request.into_inner();
 
         if req.key.is_empty() || req.value.is_empty() {
             return Err(Status::cancelled(
                 RobustMQError::ParameterCannotBeNull("key or value".to_string()).to_string(),
             ));
         }
+        let namespace = request_namespace(&req.namespace)?;
 
         if !self.is_leader() {
-            let leader_addr = self.leader_addr();
-            match placement_set(self.client_poll.clone(), vec![leader_addr], req).await {
-                Ok(reply) => {
-                    return Ok(Response::new(reply));
-                }
-                Err(e) => {
-                    return Err(Status::cancelled(e.to_string()));
-                }
-            }
+            return forward_to_leader(
+                self.client_poll.clone(),
+                self.leader_addr(),
+                PlacementCenterInterface::Set,
+                forwarded,
+                &req,
+            )
+            .await;
         }
 
         // Raft state machine is used to store Node data
+        req.key = namespace_key(&namespace, &req.key);
         let data = StorageData::new(StorageDataType::KvSet, SetRequest::encode_to_vec(&req));
-        match self
-            .placement_center_storage
-            .apply_propose_message(data, "set".to_string())
-            .await
-        {
+        match self.engine.propose(data, "set").await {
             Ok(_) => Ok(Response::new(CommonReply::default())),
-            Err(e) => {
-                Err(Status::cancelled(e.to_string()))
-            }
+            Err(e) => Err(engine_status(e)),
         }
     }
 
     async fn delete(&self, request: Request<DeleteRequest>) -> Result<Response<CommonReply>, Status> {
-        let req = request.into_inner();
+        let forwarded = is_forwarded(&request);
+        let mut req = request.into_inner();
 
         if req.key.is_empty() {
             return Err(Status::cancelled(
                 RobustMQError::ParameterCannotBeNull("key".to_string()).to_string(),
             ));
         }
+        let namespace = request_namespace(&req.namespace)?;
 
         if !self.is_leader() {
-            let leader_addr = self.leader_addr();
-            match placement_delete(self.client_poll.clone(), vec![leader_addr], req).await {
-                Ok(reply) => {
-                    return Ok(Response::new(reply));
-                }
-                Err(e) => {
-                    return Err(Status::cancelled(e.to_string()));
-                }
-            }
+            return forward_to_leader(
+                self.client_poll.clone(),
+                self.leader_addr(),
+                PlacementCenterInterface::Delete,
+                forwarded,
+                &req,
+            )
+            .await;
         }
 
         // Raft state machine is used to store Node data
+        req.key = namespace_key(&namespace, &req.key);
         let data = StorageData::new(
             StorageDataType::KvDelete,
             DeleteRequest::encode_to_vec(&req),
         );
-        match self
-            .placement_center_storage
-            .apply_propose_message(data, "delete".to_string())
-            .await
-        {
+        match self.engine.propose(data, "delete").await {
             Ok(_) => Ok(Response::new(CommonReply::default())),
-            Err(e) => {
-                Err(Status::cancelled(e.to_string()))
-            }
+            Err(e) => Err(engine_status(e)),
         }
     }
 
@@ -126,11 +197,20 @@ impl KvService for GrpcKvServices {
             ));
         }
 
+        let namespace = request_namespace(&req.namespace)?;
+        self.read_barrier(req.consistency(), "get").await?;
+
         let kv_storage = KvStorage::new(self.rocksdb_engine_handler.clone());
         let mut reply = GetReply::default();
-        match kv_storage.get(req.key) {
+        match kv_storage.get_data(namespace_key(&namespace, &req.key)) {
             Ok(Some(data)) => {
-                reply.value = data;
+                reply.value = match serde_json::from_slice::<String>(&data.data) {
+                    Ok(value) => value,
+                    Err(e) => return Err(Status::cancelled(e.to_string())),
+                };
+                reply.create_revision = data.create_revision;
+                reply.mod_revision = data.mod_revision;
+                reply.version = data.version;
                 return Ok(Response::new(reply));
             }
             Ok(None) => {}
@@ -149,8 +229,11 @@ impl KvService for GrpcKvServices {
             ));
         }
 
+        let namespace = request_namespace(&req.namespace)?;
+        self.read_barrier(req.consistency(), "exists").await?;
+
         let kv_storage = KvStorage::new(self.rocksdb_engine_handler.clone());
-        match kv_storage.exists(req.key) {
+        match kv_storage.exists(namespace_key(&namespace, &req.key)) {
             Ok(flag) => {
                 Ok(Response::new(ExistsReply { flag }))
             }
@@ -159,4 +242,470 @@ impl KvService for GrpcKvServices {
             }
         }
     }
-}
\ No newline at end of file
+
+    async fn compare_and_swap(&self, request: Request<CompareAndSwapRequest>) -> Result<Response<CompareAndSwapReply>, Status> {
+        let forwarded = is_forwarded(&request);
+        let mut req = request.into_inner();
+
+        if req.key.is_empty() || req.new_value.is_empty() {
+            return Err(Status::cancelled(
+                RobustMQError::ParameterCannotBeNull("key or new_value".to_string()).to_string(),
+            ));
+        }
+        let namespace = request_namespace(&req.namespace)?;
+
+        if !self.is_leader() {
+            return forward_to_leader(
+                self.client_poll.clone(),
+                self.leader_addr(),
+                PlacementCenterInterface::CompareAndSwap,
+                forwarded,
+                &req,
+            )
+            .await;
+        }
+
+        // The comparison is decided by the state machine when the entry is applied,
+        // so concurrent swaps on the same key are serialized by the raft log.
+        req.key = namespace_key(&namespace, &req.key);
+        let data = StorageData::new(
+            StorageDataType::KvCompareAndSwap,
+            CompareAndSwapRequest::encode_to_vec(&req),
+        );
+        match self.engine.propose(data, "compare_and_swap").await {
+            Ok(reply) => match CompareAndSwapReply::decode(reply.as_ref()) {
+                Ok(reply) => Ok(Response::new(reply)),
+                Err(e) => Err(Status::cancelled(e.to_string())),
+            },
+            Err(e) => Err(engine_status(e)),
+        }
+    }
+
+    async fn lease_grant(&self, request: Request<LeaseGrantRequest>) -> Result<Response<LeaseGrantReply>, Status> {
+        let forwarded = is_forwarded(&request);
+        let req = request.into_inner();
+
+        if req.ttl == 0 {
+            return Err(Status::cancelled(
+                RobustMQError::ParameterCannotBeNull("ttl".to_string()).to_string(),
+            ));
+        }
+
+        if !self.is_leader() {
+            return forward_to_leader(
+                self.client_poll.clone(),
+                self.leader_addr(),
+                PlacementCenterInterface::LeaseGrant,
+                forwarded,
+                &req,
+            )
+            .await;
+        }
+
+        // A lease id of 0 is allocated by the state machine when the grant is applied
+        let data = StorageData::new(
+            StorageDataType::LeaseGrant,
+            LeaseGrantRequest::encode_to_vec(&req),
+        );
+        match self.engine.propose(data, "lease_grant").await {
+            Ok(reply) => match LeaseGrantReply::decode(reply.as_ref()) {
+                Ok(reply) => {
+                    if let Err(e) = self.lease_manager.keep_alive(reply.lease_id) {
+                        return Err(Status::cancelled(e.to_string()));
+                    }
+                    Ok(Response::new(reply))
+                }
+                Err(e) => Err(Status::cancelled(e.to_string())),
+            },
+            Err(e) => Err(engine_status(e)),
+        }
+    }
+
+    type lease_keep_aliveStream = ReceiverStream<Result<LeaseKeepAliveReply, Status>>;
+
+    async fn lease_keep_alive(&self, request: Request<Streaming<LeaseKeepAliveRequest>>) -> Result<Response<Self::lease_keep_aliveStream>, Status> {
+        // Lease deadlines are only tracked by the leader, a follower proxies the stream to it
+        if !self.is_leader() {
+            let leader_addr = self.leader_addr();
+            if is_forwarded(&request) || leader_addr.is_empty() {
+                return Err(not_leader_status(&leader_addr));
+            }
+            return self.forward_lease_keep_alive(leader_addr, request.into_inner()).await;
+        }
+
+        let mut stream = request.into_inner();
+        let lease_manager = self.lease_manager.clone();
+        let (sx, rx) = mpsc::channel(100);
+        tokio::spawn(async move {
+            loop {
+                let req = match stream.message().await {
+                    Ok(Some(req)) => req,
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("Lease keep alive stream was interrupted with error message: {}", e);
+                        break;
+                    }
+                };
+
+                let reply = match lease_manager.keep_alive(req.lease_id) {
+                    Ok(Some(ttl)) => Ok(LeaseKeepAliveReply {
+                        lease_id: req.lease_id,
+                        ttl,
+                    }),
+                    // A TTL of 0 tells the client that the lease has expired or was revoked
+                    Ok(None) => Ok(LeaseKeepAliveReply {
+                        lease_id: req.lease_id,
+                        ttl: 0,
+                    }),
+                    Err(e) => Err(Status::failed_precondition(e.to_string())),
+                };
+
+                if sx.send(reply).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn lease_revoke(&self, request: Request<LeaseRevokeRequest>) -> Result<Response<CommonReply>, Status> {
+        let forwarded = is_forwarded(&request);
+        let req = request.into_inner();
+
+        if req.lease_id == 0 {
+            return Err(Status::cancelled(
+                RobustMQError::ParameterCannotBeNull("lease_id".to_string()).to_string(),
+            ));
+        }
+
+        if !self.is_leader() {
+            return forward_to_leader(
+                self.client_poll.clone(),
+                self.leader_addr(),
+                PlacementCenterInterface::LeaseRevoke,
+                forwarded,
+                &req,
+            )
+            .await;
+        }
+
+        let lease_id = req.lease_id;
+        let data = StorageData::new(
+            StorageDataType::LeaseRevoke,
+            LeaseRevokeRequest::encode_to_vec(&req),
+        );
+        match self.engine.propose(data, "lease_revoke").await {
+            Ok(_) => {
+                self.lease_manager.remove(lease_id);
+                Ok(Response::new(CommonReply::default()))
+            }
+            Err(e) => Err(engine_status(e)),
+        }
+    }
+
+    type watchStream = ReceiverStream<Result<WatchReply, Status>>;
+
+    async fn watch(&self, request: Request<WatchRequest>) -> Result<Response<Self::watchStream>, Status> {
+        let req = request.into_inner();
+
+        if req.key.is_empty() && !req.prefix {
+            return Err(Status::cancelled(
+                RobustMQError::ParameterCannotBeNull("key".to_string()).to_string(),
+            ));
+        }
+        request_namespace(&req.namespace)?;
+
+        match watch_stream(&self.watch_hub, req) {
+            Ok(stream) => Ok(Response::new(stream)),
+            Err(e) => Err(Status::out_of_range(e.to_string())),
+        }
+    }
+
+    async fn list(&self, request: Request<ListRequest>) -> Result<Response<ListReply>, Status> {
+        let req = request.into_inner();
+
+        let limit = if req.limit == 0 {
+            DEFAULT_LIST_LIMIT
+        } else {
+            req.limit.min(MAX_LIST_LIMIT)
+        };
+
+        let namespace = request_namespace(&req.namespace)?;
+        let start_after = if req.start_after.is_empty() {
+            req.start_after
+        } else {
+            namespace_key(&namespace, &req.start_after)
+        };
+
+        let kv_storage = KvStorage::new(self.rocksdb_engine_handler.clone());
+        let (results, next_token) = match kv_storage.list(
+            namespace_key(&namespace, &req.prefix),
+            start_after,
+            limit as usize,
+        ) {
+            Ok(data) => data,
+            Err(e) => return Err(Status::cancelled(e.to_string())),
+        };
+        let next_token = if next_token.is_empty() {
+            next_token
+        } else {
+            namespace_user_key(&namespace, &next_token)
+        };
+
+        let mut kvs = Vec::with_capacity(results.len());
+        for (key, data) in results {
+            let value = if req.keys_only {
+                "".to_string()
+            } else {
+                match serde_json::from_slice::<String>(&data.data) {
+                    Ok(value) => value,
+                    // Values written by the placement center itself are not plain strings
+                    Err(_) => String::from_utf8_lossy(&data.data).to_string(),
+                }
+            };
+            kvs.push(KeyValue {
+                key: namespace_user_key(&namespace, &key),
+                value,
+                create_revision: data.create_revision,
+                mod_revision: data.mod_revision,
+                version: data.version,
+            });
+        }
+
+        Ok(Response::new(ListReply { kvs, next_token }))
+    }
+
+    async fn txn(&self, request: Request<TxnRequest>) -> Result<Response<TxnReply>, Status> {
+        let forwarded = is_forwarded(&request);
+        let mut req = request.into_inner();
+
+        if let Err(e) = validate_txn(&req) {
+            return Err(Status::cancelled(e.to_string()));
+        }
+        let namespace = request_namespace(&req.namespace)?;
+
+        if !self.is_leader() {
+            return forward_to_leader(
+                self.client_poll.clone(),
+                self.leader_addr(),
+                PlacementCenterInterface::Txn,
+                forwarded,
+                &req,
+            )
+            .await;
+        }
+
+        // The compares are evaluated when the entry is applied, so the whole transaction
+        // is atomic with respect to every other write in the raft log.
+        for compare in req.compare.iter_mut() {
+            compare.key = namespace_key(&namespace, &compare.key);
+        }
+        for op in req.success.iter_mut().chain(req.failure.iter_mut()) {
+            match &mut op.op {
+                Some(Op::Set(set)) => set.key = namespace_key(&namespace, &set.key),
+                Some(Op::Delete(delete)) => delete.key = namespace_key(&namespace, &delete.key),
+                None => {}
+            }
+        }
+        let data = StorageData::new(StorageDataType::KvTxn, TxnRequest::encode_to_vec(&req));
+        match self.engine.propose(data, "txn").await {
+            Ok(reply) => match TxnReply::decode(reply.as_ref()) {
+                Ok(reply) => Ok(Response::new(reply)),
+                Err(e) => Err(Status::cancelled(e.to_string())),
+            },
+            Err(e) => Err(engine_status(e)),
+        }
+    }
+
+    async fn batch_set(&self, request: Request<BatchSetRequest>) -> Result<Response<CommonReply>, Status> {
+        let forwarded = is_forwarded(&request);
+        let mut req = request.into_inner();
+
+        if let Err(e) = validate_batch_set(&req) {
+            return Err(Status::cancelled(e.to_string()));
+        }
+        let namespace = request_namespace(&req.namespace)?;
+
+        if !self.is_leader() {
+            return forward_to_leader(
+                self.client_poll.clone(),
+                self.leader_addr(),
+                PlacementCenterInterface::BatchSet,
+                forwarded,
+                &req,
+            )
+            .await;
+        }
+
+        for kv in req.kvs.iter_mut() {
+            kv.key = namespace_key(&namespace, &kv.key);
+        }
+        let data = StorageData::new(
+            StorageDataType::KvBatchSet,
+            BatchSetRequest::encode_to_vec(&req),
+        );
+        match self.engine.propose(data, "batch_set").await {
+            Ok(_) => Ok(Response::new(CommonReply::default())),
+            Err(e) => Err(engine_status(e)),
+        }
+    }
+
+    async fn batch_delete(&self, request: Request<BatchDeleteRequest>) -> Result<Response<CommonReply>, Status> {
+        let forwarded = is_forwarded(&request);
+        let mut req = request.into_inner();
+
+        if req.keys.is_empty() || req.keys.iter().any(|key| key.is_empty()) {
+            return Err(Status::cancelled(
+                RobustMQError::ParameterCannotBeNull("keys".to_string()).to_string(),
+            ));
+        }
+        let namespace = request_namespace(&req.namespace)?;
+
+        if !self.is_leader() {
+            return forward_to_leader(
+                self.client_poll.clone(),
+                self.leader_addr(),
+                PlacementCenterInterface::BatchDelete,
+                forwarded,
+                &req,
+            )
+            .await;
+        }
+
+        for key in req.keys.iter_mut() {
+            *key = namespace_key(&namespace, key);
+        }
+        let data = StorageData::new(
+            StorageDataType::KvBatchDelete,
+            BatchDeleteRequest::encode_to_vec(&req),
+        );
+        match self.engine.propose(data, "batch_delete").await {
+            Ok(_) => Ok(Response::new(CommonReply::default())),
+            Err(e) => Err(engine_status(e)),
+        }
+    }
+
+    async fn namespace_set(&self, request: Request<NamespaceSetRequest>) -> Result<Response<CommonReply>, Status> {
+        let forwarded = is_forwarded(&request);
+        let mut req = request.into_inner();
+        req.namespace = request_namespace(&req.namespace)?;
+
+        if !self.is_leader() {
+            return forward_to_leader(
+                self.client_poll.clone(),
+                self.leader_addr(),
+                PlacementCenterInterface::NamespaceSet,
+                forwarded,
+                &req,
+            )
+            .await;
+        }
+
+        let data = StorageData::new(
+            StorageDataType::NamespaceSet,
+            NamespaceSetRequest::encode_to_vec(&req),
+        );
+        match self.engine.propose(data, "namespace_set").await {
+            Ok(_) => Ok(Response::new(CommonReply::default())),
+            Err(e) => Err(engine_status(e)),
+        }
+    }
+
+    async fn namespace_get(&self, request: Request<NamespaceGetRequest>) -> Result<Response<NamespaceGetReply>, Status> {
+        let req = request.into_inner();
+        let namespace = request_namespace(&req.namespace)?;
+        namespace_get_reply(self.rocksdb_engine_handler.clone(), &namespace)
+    }
+}
+
+fn validate_txn(req: &TxnRequest) -> Result<(), RobustMQError> {
+    for compare in req.compare.iter() {
+        if compare.key.is_empty() {
+            return Err(RobustMQError::ParameterCannotBeNull("compare key".to_string()));
+        }
+    }
+    for op in req.success.iter().chain(req.failure.iter()) {
+        validate_txn_op(op)?;
+    }
+    Ok(())
+}
+
+/// Status of a request the consensus engine failed, the leadership may have moved after the
+/// request was checked.
+fn engine_status(e: RobustMQError) -> Status {
+    match e {
+        RobustMQError::NotLeader(leader_addr) => not_leader_status(&leader_addr),
+        RobustMQError::UnsupportedByConsensusEngine(_, _) => Status::unimplemented(e.to_string()),
+        e => Status::cancelled(e.to_string()),
+    }
+}
+
+/// The namespace of a request, an empty namespace is the default one.
+pub(crate) fn request_namespace(namespace: &str) -> Result<String, Status> {
+    match namespace_name(namespace) {
+        Ok(namespace) => Ok(namespace),
+        Err(e) => Err(Status::cancelled(e.to_string())),
+    }
+}
+
+pub(crate) fn namespace_get_reply(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    namespace: &str,
+) -> Result<Response<NamespaceGetReply>, Status> {
+    let namespace_storage = NamespaceStorage::new(rocksdb_engine_handler);
+    match namespace_storage.get(namespace) {
+        Ok(Some(data)) => Ok(Response::new(NamespaceGetReply {
+            namespace: data.name,
+            max_keys: data.max_keys,
+            max_bytes: data.max_bytes,
+            max_value_size: data.max_value_size,
+            key_count: data.key_count,
+            total_bytes: data.total_bytes,
+        })),
+        Ok(None) => Err(Status::cancelled(
+            RobustMQError::NamespaceNotFound(namespace.to_string()).to_string(),
+        )),
+        Err(e) => Err(Status::cancelled(e.to_string())),
+    }
+}
+
+pub(crate) fn validate_batch_set(req: &BatchSetRequest) -> Result<(), RobustMQError> {
+    if req.kvs.is_empty() {
+        return Err(RobustMQError::ParameterCannotBeNull("kvs".to_string()));
+    }
+    for kv in req.kvs.iter() {
+        if kv.key.is_empty() || kv.value.is_empty() {
+            return Err(RobustMQError::ParameterCannotBeNull("key or value".to_string()));
+        }
+        if kv.lease_id > 0 {
+            return Err(RobustMQError::CommonError(
+                "Keys cannot be attached to a lease inside a batch".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_txn_op(op: &TxnOp) -> Result<(), RobustMQError> {
+    match &op.op {
+        Some(Op::Set(set)) => {
+            if set.key.is_empty() || set.value.is_empty() {
+                return Err(RobustMQError::ParameterCannotBeNull("key or value".to_string()));
+            }
+            if set.lease_id > 0 {
+                return Err(RobustMQError::CommonError(
+                    "Keys cannot be attached to a lease inside a transaction".to_string(),
+                ));
+            }
+            Ok(())
+        }
+        Some(Op::Delete(delete)) => {
+            if delete.key.is_empty() {
+                return Err(RobustMQError::ParameterCannotBeNull("key".to_string()));
+            }
+            Ok(())
+        }
+        None => Err(RobustMQError::ParameterCannotBeNull("op".to_string())),
+    }
+}
@@ -1,17 +1,30 @@
+use std::sync::Arc;
 use bincode::{deserialize, serialize};
-use openraft::Raft;
-use tonic::{Request, Response, Status};
+use openraft::error::{CheckIsLeaderError, RaftError};
+use openraft::{Raft, Snapshot, SnapshotMeta, Vote};
+use tonic::{Request, Response, Status, Streaming};
+use common_base::errors::{not_leader_status, RobustMQError};
 use protocol::openraft::open_raft_service_server::OpenRaftService;
-use protocol::openraft::{AppendReply, AppendRequest, SnapshotReply, SnapshotRequest, VoteReply, VoteRequest};
+use protocol::openraft::{AddLearnerRequest, AppendReply, AppendRequest, MembershipNode, MembershipReply, MembershipRequest, PromoteVoterRequest, ReadIndexReply, ReadIndexRequest, RemoveNodeRequest, SnapshotChunk, SnapshotOffsetReply, SnapshotOffsetRequest, SnapshotReply, VoteReply, VoteRequest};
+use crate::consensus::{ConsensusEngine, MembershipConfig};
+use crate::openraft::raft_node::NodeId;
+use crate::openraft::sotre::snapshot::{crc32, SnapshotFiles};
 use crate::openraft::typeconfig::TypeConfig;
 
+/// Transport of the openraft node, and membership management of the consensus engine.
 pub struct GrpcOpenRaftServices {
     raft_node: Raft<TypeConfig>,
+    snapshot_files: SnapshotFiles,
+    engine: Arc<dyn ConsensusEngine>,
 }
 
 impl GrpcOpenRaftServices {
-    pub fn new(raft_node: Raft<TypeConfig>) -> Self {
-        GrpcOpenRaftServices { raft_node }
+    pub fn new(raft_node: Raft<TypeConfig>, snapshot_files: SnapshotFiles, engine: Arc<dyn ConsensusEngine>) -> Self {
+        GrpcOpenRaftServices {
+            raft_node,
+            snapshot_files,
+            engine,
+        }
     }
 }
 
@@ -57,23 +70,142 @@ impl OpenRaftService for GrpcOpenRaftServices {
         Ok(Response::new(reply))
     }
 
-    async fn snapshot(&self, request: Request<SnapshotRequest>) -> Result<Response<SnapshotReply>, Status> {
-        let req = request.into_inner();
-        let vote_data = deserialize(&req.value).unwrap();
-        let res = match self.raft_node.install_snapshot(vote_data).await {
-            Ok(data) => data,
-            Err(e) => {
-                return Err(Status::cancelled(e.to_string()));
+    async fn snapshot_stream(
+        &self,
+        request: Request<Streaming<SnapshotChunk>>,
+    ) -> Result<Response<SnapshotReply>, Status> {
+        let mut stream = request.into_inner();
+        let mut header: Option<(Vote<NodeId>, SnapshotMeta<TypeConfig>)> = None;
+
+        loop {
+            let chunk = match stream.message().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => {
+                    return Err(Status::cancelled(
+                        "Snapshot stream ended before the last chunk".to_string(),
+                    ));
+                }
+                Err(e) => return Err(e),
+            };
+
+            // Only the first chunk of a stream carries the vote and the meta
+            if header.is_none() {
+                let vote = match deserialize(&chunk.vote) {
+                    Ok(data) => data,
+                    Err(e) => return Err(Status::cancelled(e.to_string())),
+                };
+                let meta = match deserialize(&chunk.meta) {
+                    Ok(data) => data,
+                    Err(e) => return Err(Status::cancelled(e.to_string())),
+                };
+                header = Some((vote, meta));
             }
-        };
+            let snapshot_id = header.as_ref().unwrap().1.snapshot_id.clone();
 
-        let mut reply = SnapshotReply::default();
-        reply.value = match serialize(&res) {
-            Ok(data) => data,
-            Err(e) => {
+            if crc32(&chunk.data) != chunk.checksum {
+                return Err(Status::cancelled(
+                    RobustMQError::SnapshotChecksumMismatch(chunk.offset).to_string(),
+                ));
+            }
+
+            if let Err(e) = self
+                .snapshot_files
+                .write_chunk(&snapshot_id, chunk.offset, &chunk.data)
+                .await
+            {
                 return Err(Status::cancelled(e.to_string()));
             }
-        };
-        Ok(Response::new(reply))
+
+            if !chunk.done {
+                continue;
+            }
+
+            let file = match self.snapshot_files.finish(&snapshot_id).await {
+                Ok(file) => file,
+                Err(e) => return Err(Status::cancelled(e.to_string())),
+            };
+            let (vote, meta) = header.unwrap();
+            let snapshot = Snapshot {
+                meta,
+                snapshot: Box::new(file),
+            };
+            let res = match self.raft_node.install_full_snapshot(vote, snapshot).await {
+                Ok(data) => data,
+                Err(e) => {
+                    return Err(Status::cancelled(e.to_string()));
+                }
+            };
+
+            let mut reply = SnapshotReply::default();
+            reply.value = match serialize(&res) {
+                Ok(data) => data,
+                Err(e) => {
+                    return Err(Status::cancelled(e.to_string()));
+                }
+            };
+            return Ok(Response::new(reply));
+        }
     }
-}
\ No newline at end of file
+
+    async fn snapshot_offset(
+        &self,
+        request: Request<SnapshotOffsetRequest>,
+    ) -> Result<Response<SnapshotOffsetReply>, Status> {
+        let req = request.into_inner();
+        match self.snapshot_files.received_offset(&req.snapshot_id).await {
+            Ok(offset) => Ok(Response::new(SnapshotOffsetReply { offset })),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn add_learner(&self, request: Request<AddLearnerRequest>) -> Result<Response<MembershipReply>, Status> {
+        let req = request.into_inner();
+        membership_reply(self.engine.add_learner(req.node_id, req.rpc_addr).await)
+    }
+
+    async fn promote_voter(&self, request: Request<PromoteVoterRequest>) -> Result<Response<MembershipReply>, Status> {
+        let req = request.into_inner();
+        membership_reply(self.engine.promote_voter(req.node_id).await)
+    }
+
+    async fn remove_node(&self, request: Request<RemoveNodeRequest>) -> Result<Response<MembershipReply>, Status> {
+        let req = request.into_inner();
+        membership_reply(self.engine.remove_node(req.node_id).await)
+    }
+
+    async fn membership(&self, _: Request<MembershipRequest>) -> Result<Response<MembershipReply>, Status> {
+        membership_reply(Ok(self.engine.membership()))
+    }
+
+    /// Confirm the leadership and return the index a follower waits for before serving a read.
+    async fn read_index(&self, _: Request<ReadIndexRequest>) -> Result<Response<ReadIndexReply>, Status> {
+        match self.raft_node.ensure_linearizable().await {
+            Ok(log_id) => Ok(Response::new(ReadIndexReply {
+                log_index: log_id.map(|log_id| log_id.index).unwrap_or(0),
+            })),
+            Err(RaftError::APIError(CheckIsLeaderError::ForwardToLeader(forward))) => Err(not_leader_status(
+                &forward.leader_node.map(|node| node.rpc_addr).unwrap_or_default(),
+            )),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+}
+
+fn membership_reply(result: Result<MembershipConfig, RobustMQError>) -> Result<Response<MembershipReply>, Status> {
+    match result {
+        Ok(config) => Ok(Response::new(MembershipReply {
+            log_index: config.log_index,
+            nodes: config
+                .nodes
+                .into_iter()
+                .map(|node| MembershipNode {
+                    node_id: node.node_id,
+                    rpc_addr: node.rpc_addr,
+                    voter: node.voter,
+                })
+                .collect(),
+        })),
+        Err(RobustMQError::NotLeader(leader_addr)) => Err(not_leader_status(&leader_addr)),
+        Err(e) => Err(Status::cancelled(e.to_string())),
+    }
+}
@@ -0,0 +1,30 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use common_base::config::placement_center::placement_center_conf;
+use common_base::http_response::{error_response, success_response};
+use crate::server::http::server::HttpServerState;
+use crate::storage::backup::create_backup;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupRequest {
+    /// Directory on the host of the placement center, it must not exist yet
+    pub target_dir: String,
+}
+
+/// Back up the running placement center. The checkpoint is taken while the node keeps serving.
+pub async fn backup_create(
+    State(state): State<HttpServerState>,
+    Json(req): Json<BackupRequest>,
+) -> String {
+    let rocksdb_engine_handler = state.rocksdb_engine_handler.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        create_backup(&rocksdb_engine_handler, placement_center_conf(), &req.target_dir)
+    })
+    .await;
+    match result {
+        Ok(Ok(metadata)) => success_response(metadata),
+        Ok(Err(e)) => error_response(e.to_string()),
+        Err(e) => error_response(e.to_string()),
+    }
+}
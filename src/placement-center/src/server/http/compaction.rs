@@ -0,0 +1,41 @@
+use axum::extract::{Query, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use common_base::http_response::{error_response, success_response};
+use crate::server::http::server::HttpServerState;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompactionRequest {
+    pub family: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompactionQuery {
+    /// Every column family when it is not set
+    pub family: Option<String>,
+}
+
+/// Start a manual compaction of a column family, its progress is reported by `compaction_list`.
+pub async fn compaction_create(
+    State(state): State<HttpServerState>,
+    Json(req): Json<CompactionRequest>,
+) -> String {
+    match state.compaction_manager.trigger(&req.family) {
+        Ok(task) => success_response(task),
+        Err(e) => error_response(e.to_string()),
+    }
+}
+
+pub async fn compaction_list(
+    State(state): State<HttpServerState>,
+    Query(query): Query<CompactionQuery>,
+) -> String {
+    let result = match query.family {
+        Some(family) => state.compaction_manager.status(&family).map(|status| vec![status]),
+        None => state.compaction_manager.status_list(),
+    };
+    match result {
+        Ok(data) => success_response(data),
+        Err(e) => error_response(e.to_string()),
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::Arc;
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use common_base::config::placement_center::placement_center_conf;
+use common_base::errors::RobustMQError;
+use common_base::http_response::{error_response, success_response};
+use crate::openraft::sotre::log_store::LogStore;
+use crate::openraft::sotre::snapshot::SnapshotFiles;
+use crate::server::http::server::HttpServerState;
+use crate::storage::rocksdb::RocksDBEngine;
+use crate::storage::state_machine::StateMachineStorage;
+
+/// What a re-encryption rewrote.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ReencryptSummary {
+    pub values: u64,
+    pub log_entries: u64,
+    pub snapshot_files: u64,
+}
+
+/// Re-encrypt the data of this node that is not encrypted with the active key, after a key
+/// was rotated in the key file: the storage and, with the openraft engine, its log and snapshot
+/// files. Every node stores its own copy, so it is run on each node. The old key can be removed
+/// from the key file once it succeeded on every node.
+pub async fn encryption_reencrypt(State(state): State<HttpServerState>) -> String {
+    let rocksdb_engine_handler = state.rocksdb_engine_handler.clone();
+    let log_store = state.log_store.clone();
+    let snapshot_files = SnapshotFiles::new(&placement_center_conf().data_path);
+    match tokio::task::spawn_blocking(move || reencrypt(rocksdb_engine_handler, log_store, snapshot_files)).await {
+        Ok(Ok(summary)) => success_response(summary),
+        Ok(Err(e)) => error_response(e.to_string()),
+        Err(e) => error_response(e.to_string()),
+    }
+}
+
+fn reencrypt(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    log_store: Option<LogStore>,
+    snapshot_files: SnapshotFiles,
+) -> Result<ReencryptSummary, RobustMQError> {
+    let mut summary = ReencryptSummary {
+        values: rocksdb_engine_handler.reencrypt()?,
+        ..Default::default()
+    };
+    if let Some(log_store) = log_store {
+        summary.log_entries = log_store.reencrypt()?;
+        let state_machine_storage = StateMachineStorage::new(rocksdb_engine_handler);
+        for path in snapshot_files.snapshot_paths()? {
+            if state_machine_storage.reencrypt_snapshot_file(&path)? {
+                summary.snapshot_files += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
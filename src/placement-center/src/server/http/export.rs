@@ -0,0 +1,114 @@
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use log::error;
+use tokio::sync::mpsc;
+use tonic::codegen::tokio_stream::wrappers::ReceiverStream;
+use tonic::codegen::tokio_stream::StreamExt;
+use common_base::errors::RobustMQError;
+use common_base::http_response::{error_response, success_response};
+use crate::raft::apply::{StorageData, StorageDataType};
+use crate::server::http::server::HttpServerState;
+use crate::storage::export::{export_entries, import_batch, parse_export_line, ExportEntry, ImportBatcher, ImportSummary, IMPORT_BATCH_SIZE};
+
+/// Number of export lines buffered ahead of the client.
+const EXPORT_CHANNEL_SIZE: usize = 1024;
+
+/// Stream every KV and metadata entry as JSON Lines. The entries are read from one RocksDB
+/// snapshot while they are sent, so the export is consistent without being held in memory.
+pub async fn export_list(State(state): State<HttpServerState>) -> impl IntoResponse {
+    let (tx, rx) = mpsc::channel::<Result<String, std::io::Error>>(EXPORT_CHANNEL_SIZE);
+    let rocksdb_engine_handler = state.rocksdb_engine_handler.clone();
+    tokio::task::spawn_blocking(move || {
+        let result = export_entries(&rocksdb_engine_handler, |entry| {
+            let mut line = serde_json::to_string(&entry)?;
+            line.push('\n');
+            match tx.blocking_send(Ok(line)) {
+                Ok(()) => Ok(()),
+                Err(_) => Err(RobustMQError::CommonError(
+                    "The export client went away".to_string(),
+                )),
+            }
+        });
+        if let Err(e) = result {
+            error!("Export failed: {}", e);
+            let _ = tx.blocking_send(Err(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())));
+        }
+    });
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+}
+
+/// Replay a JSON Lines export through raft, `IMPORT_BATCH_SIZE` entries per proposal.
+/// The entries of the batches proposed before an error stay imported.
+pub async fn import_create(State(state): State<HttpServerState>, body: Body) -> String {
+    let mut summary = ImportSummary::default();
+    match import(&state, body, &mut summary).await {
+        Ok(()) => success_response(summary),
+        Err(e) => error_response(format!(
+            "{}, {} entries were imported before the error",
+            e, summary.imported
+        )),
+    }
+}
+
+async fn import(state: &HttpServerState, body: Body, summary: &mut ImportSummary) -> Result<(), RobustMQError> {
+    let mut stream = body.into_data_stream();
+    let mut batcher = ImportBatcher::new(IMPORT_BATCH_SIZE);
+    let mut buf: Vec<u8> = Vec::new();
+    let mut line_no = 0;
+    loop {
+        let chunk = match stream.next().await {
+            Some(Ok(chunk)) => Some(chunk),
+            Some(Err(e)) => return Err(RobustMQError::CommonError(e.to_string())),
+            None => None,
+        };
+        let finished = chunk.is_none();
+        if let Some(chunk) = chunk {
+            buf.extend_from_slice(&chunk);
+        } else if !buf.is_empty() {
+            // The last line may not end with a newline
+            buf.push(b'\n');
+        }
+
+        while let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buf.drain(..=pos).collect();
+            line_no += 1;
+            let line = String::from_utf8_lossy(&line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = parse_export_line(line_no, line.trim())?;
+            if !entry.is_importable() {
+                summary.skipped += 1;
+                continue;
+            }
+            if let Some(batch) = batcher.push(entry) {
+                propose(state, batch, summary).await?;
+            }
+        }
+
+        if finished {
+            break;
+        }
+    }
+    if let Some(batch) = batcher.finish() {
+        propose(state, batch, summary).await?;
+    }
+    Ok(())
+}
+
+async fn propose(state: &HttpServerState, batch: Vec<ExportEntry>, summary: &mut ImportSummary) -> Result<(), RobustMQError> {
+    let count = batch.len() as u64;
+    let value = serde_json::to_vec(&import_batch(batch))?;
+    state
+        .engine
+        .propose(StorageData::new(StorageDataType::Import, value), "import")
+        .await?;
+    summary.imported += count;
+    summary.batches += 1;
+    Ok(())
+}
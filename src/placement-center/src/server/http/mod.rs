@@ -1,6 +1,11 @@
 pub mod server;
 pub mod index;
 pub mod openraft;
+pub mod backup;
+pub mod export;
+pub mod encryption;
+pub mod compaction;
+pub mod raft;
 
 pub(crate) fn v1_path(path: &str) -> String {
     format!("/v1{}", path)
@@ -1,74 +1,108 @@
-use std::collections::{BTreeMap, BTreeSet};
 use axum::extract::State;
+use axum::Json;
 use openraft::error::Infallible;
 use openraft::RaftMetrics;
+use prost::Message;
+use serde::{Deserialize, Serialize};
 use common_base::http_response::{error_response, success_response};
-use crate::openraft::raft_node::Node;
-use crate::openraft::route::AppRequestData;
+use protocol::kv::SetRequest;
+use crate::openraft::leadership::transfer_leader;
+use crate::openraft::raft_node::NodeId;
 use crate::openraft::typeconfig::TypeConfig;
+use crate::raft::apply::{StorageData, StorageDataType};
 use crate::server::http::server::HttpServerState;
+use crate::storage::kv::KvStorage;
 
-pub async fn add_leadrner(State(state): State<HttpServerState>) -> String {
-    let node_id = 3;
-    let node = Node {
-        rpc_addr: "127.0.0.0:7654".to_string(),
-        node_id: 2,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AddLearnerRequest {
+    pub node_id: NodeId,
+    pub rpc_addr: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MembershipNodeRequest {
+    pub node_id: NodeId,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TransferLeaderRequest {
+    /// The voter with the most up to date log when it is not set
+    pub target: Option<NodeId>,
+}
+
+/// Move the leadership off this node, it has to be the leader. The response is sent once the
+/// new leader is elected.
+pub async fn leader_update(
+    State(state): State<HttpServerState>,
+    Json(req): Json<TransferLeaderRequest>,
+) -> String {
+    let raft_node = match state.openraft_node("leader_update") {
+        Ok(raft_node) => raft_node,
+        Err(e) => return error_response(e.to_string()),
     };
-    match state.raft_node.add_learner(node_id, node, true).await {
-        Ok(data) => {
-            success_response(data)
-        }
-        Err(e) => {
-            error_response(e.to_string())
-        }
+    match transfer_leader(raft_node, req.target).await {
+        Ok(data) => success_response(data),
+        Err(e) => error_response(e.to_string()),
     }
 }
 
-pub async fn change_membership(State(state): State<HttpServerState>) -> String {
-    let mut body = BTreeSet::new();
-    body.insert(3);
-    match state.raft_node.change_membership(body, true).await {
-        Ok(data) => {
-            success_response(data)
-        }
-        Err(e) => {
-            error_response(e.to_string())
-        }
-    }
+/// Engine, role and leader of this node.
+pub async fn consensus_list(State(state): State<HttpServerState>) -> String {
+    success_response(state.engine.status())
 }
 
-pub async fn init(State(state): State<HttpServerState>) -> String {
-    let node_id = 3;
-    let node = Node {
-        rpc_addr: "127.0.0.0:7654".to_string(),
-        node_id: 2,
-    };
+pub async fn membership_list(State(state): State<HttpServerState>) -> String {
+    success_response(state.engine.membership())
+}
 
-    let mut nodes = BTreeMap::new();
-    nodes.insert(node_id, node);
+/// Add a learner, the response is sent once it has caught up with the log.
+pub async fn learner_create(
+    State(state): State<HttpServerState>,
+    Json(req): Json<AddLearnerRequest>,
+) -> String {
+    match state.engine.add_learner(req.node_id, req.rpc_addr).await {
+        Ok(data) => success_response(data),
+        Err(e) => error_response(e.to_string()),
+    }
+}
 
-    match state.raft_node.initialize(nodes).await {
-        Ok(data) => {
-            success_response(data)
-        }
-        Err(e) => {
-            error_response(e.to_string())
-        }
+pub async fn voter_create(
+    State(state): State<HttpServerState>,
+    Json(req): Json<MembershipNodeRequest>,
+) -> String {
+    match state.engine.promote_voter(req.node_id).await {
+        Ok(data) => success_response(data),
+        Err(e) => error_response(e.to_string()),
+    }
+}
+
+pub async fn membership_delete(
+    State(state): State<HttpServerState>,
+    Json(req): Json<MembershipNodeRequest>,
+) -> String {
+    match state.engine.remove_node(req.node_id).await {
+        Ok(data) => success_response(data),
+        Err(e) => error_response(e.to_string()),
     }
 }
 
 pub async fn metrics(State(state): State<HttpServerState>) -> String {
-    let metrics = state.raft_node.metrics().borrow().clone();
+    let metrics = match state.openraft_node("metrics") {
+        Ok(raft_node) => raft_node.metrics().borrow().clone(),
+        Err(e) => return error_response(e.to_string()),
+    };
     let res: Result<RaftMetrics<TypeConfig>, Infallible> = Ok(metrics);
     success_response(res)
 }
 
 pub async fn set(State(state): State<HttpServerState>) -> String {
-    let data = AppRequestData::Set {
+    let req = SetRequest {
         key: "k1".to_string(),
         value: "v1".to_string(),
+        ..Default::default()
     };
-    match state.raft_node.client_write(data).await {
+    let data = StorageData::new(StorageDataType::KvSet, SetRequest::encode_to_vec(&req));
+    match state.engine.propose(data, "set").await {
         Ok(data) => {
             success_response(data)
         }
@@ -79,9 +113,10 @@ pub async fn set(State(state): State<HttpServerState>) -> String {
 }
 
 pub async fn kv_get(State(state): State<HttpServerState>) -> String {
-    let kvs = state.kvs.read().await;
+    let kv_storage = KvStorage::new(state.rocksdb_engine_handler.clone());
     let key = "k1".to_string();
-    let value = kvs.get(&key);
-
-    success_response(value)
+    match kv_storage.get(key) {
+        Ok(value) => success_response(value),
+        Err(e) => error_response(e.to_string()),
+    }
 }
\ No newline at end of file
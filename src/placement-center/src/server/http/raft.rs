@@ -0,0 +1,69 @@
+use axum::extract::State;
+use axum::Json;
+use openraft::Raft;
+use serde::{Deserialize, Serialize};
+use common_base::errors::RobustMQError;
+use common_base::http_response::{error_response, success_response};
+use crate::openraft::typeconfig::TypeConfig;
+use crate::server::http::server::HttpServerState;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PurgeRequest {
+    /// Last log index to purge, the index of the last snapshot when it is not set
+    pub upto: Option<u64>,
+}
+
+/// Log indexes of the node, to follow a snapshot or a purge started by the endpoints below.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RaftLogStatus {
+    pub last_log_index: Option<u64>,
+    pub last_applied: Option<u64>,
+    pub snapshot: Option<u64>,
+    pub purged: Option<u64>,
+}
+
+fn log_status(raft_node: &Raft<TypeConfig>) -> RaftLogStatus {
+    let metrics = raft_node.metrics().borrow().clone();
+    RaftLogStatus {
+        last_log_index: metrics.last_log_index,
+        last_applied: metrics.last_applied.map(|log_id| log_id.index),
+        snapshot: metrics.snapshot.map(|log_id| log_id.index),
+        purged: metrics.purged.map(|log_id| log_id.index),
+    }
+}
+
+pub async fn snapshot_list(State(state): State<HttpServerState>) -> String {
+    match state.openraft_node("snapshot_list") {
+        Ok(raft_node) => success_response(log_status(raft_node)),
+        Err(e) => error_response(e.to_string()),
+    }
+}
+
+/// Start building a snapshot of this node, it is done once `snapshot` reaches `last_applied`.
+pub async fn snapshot_create(State(state): State<HttpServerState>) -> String {
+    let raft_node = match state.openraft_node("snapshot_create") {
+        Ok(raft_node) => raft_node,
+        Err(e) => return error_response(e.to_string()),
+    };
+    match raft_node.trigger().snapshot().await {
+        Ok(()) => success_response(log_status(raft_node)),
+        Err(e) => error_response(e.to_string()),
+    }
+}
+
+/// Start purging the logs of this node. Logs that are not in a snapshot yet are never purged,
+/// `max_in_snapshot_log_to_keep` does not apply to a purge started here.
+pub async fn purge_create(State(state): State<HttpServerState>, Json(req): Json<PurgeRequest>) -> String {
+    let raft_node = match state.openraft_node("purge_create") {
+        Ok(raft_node) => raft_node,
+        Err(e) => return error_response(e.to_string()),
+    };
+    let upto = match req.upto.or(log_status(raft_node).snapshot) {
+        Some(upto) => upto,
+        None => return error_response(RobustMQError::RaftSnapshotNotFound.to_string()),
+    };
+    match raft_node.trigger().purge_log(upto).await {
+        Ok(()) => success_response(log_status(raft_node)),
+        Err(e) => error_response(e.to_string()),
+    }
+}
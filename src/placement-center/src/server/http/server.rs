@@ -1,4 +1,3 @@
-use std::collections::BTreeMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::{select, sync::broadcast};
@@ -6,9 +5,19 @@ use axum::Router;
 use axum::routing::{delete, get, post, put};
 use log::info;
 use openraft::Raft;
-use tokio::sync::RwLock;
 use common_base::config::placement_center::placement_center_conf;
+use common_base::errors::RobustMQError;
+use crate::consensus::ConsensusEngine;
+use crate::openraft::sotre::log_store::LogStore;
 use crate::openraft::typeconfig::TypeConfig;
+use crate::storage::compaction::CompactionManager;
+use crate::storage::rocksdb::RocksDBEngine;
+use crate::server::http::backup::backup_create;
+use crate::server::http::compaction::{compaction_create, compaction_list};
+use crate::server::http::encryption::encryption_reencrypt;
+use crate::server::http::export::{export_list, import_create};
+use crate::server::http::openraft::{consensus_list, leader_update, learner_create, membership_delete, membership_list, voter_create};
+use crate::server::http::raft::{purge_create, snapshot_create, snapshot_list};
 use crate::server::http::{index::index, path_create, path_delete, path_list, path_update, v1_path};
 
 pub const ROUTE_ROOT: &str = "/index";
@@ -18,18 +27,55 @@ pub const ROUTE_INIT: &str = "/init";
 pub const ROUTE_METRICS: &str = "/metrics";
 pub const ROUTE_SET: &str = "/set";
 pub const ROUTE_GET: &str = "/get";
+pub const ROUTE_BACKUP: &str = "/backup";
+pub const ROUTE_EXPORT: &str = "/export";
+pub const ROUTE_IMPORT: &str = "/import";
+pub const ROUTE_ENCRYPTION: &str = "/encryption";
+pub const ROUTE_COMPACTION: &str = "/compaction";
+pub const ROUTE_SNAPSHOT: &str = "/snapshot";
+pub const ROUTE_PURGE: &str = "/purge";
+pub const ROUTE_MEMBERSHIP: &str = "/membership";
+pub const ROUTE_LEARNER: &str = "/learner";
+pub const ROUTE_VOTER: &str = "/voter";
+pub const ROUTE_LEADER: &str = "/leader";
+pub const ROUTE_CONSENSUS: &str = "/consensus";
 
 #[derive(Clone)]
 pub struct HttpServerState {
-    pub raft_node: Raft<TypeConfig>,
-    pub kvs: Arc<RwLock<BTreeMap<String, String>>>,
+    /// Only set with the openraft engine
+    pub raft_node: Option<Raft<TypeConfig>>,
+    /// Only set with the openraft engine
+    pub log_store: Option<LogStore>,
+    pub engine: Arc<dyn ConsensusEngine>,
+    pub rocksdb_engine_handler: Arc<RocksDBEngine>,
+    pub compaction_manager: Arc<CompactionManager>,
 }
 
 impl HttpServerState {
-    pub fn new(raft_node: Raft<TypeConfig>, kvs: Arc<RwLock<BTreeMap<String, String>>>) -> Self {
+    pub fn new(
+        raft_node: Option<Raft<TypeConfig>>,
+        log_store: Option<LogStore>,
+        engine: Arc<dyn ConsensusEngine>,
+        rocksdb_engine_handler: Arc<RocksDBEngine>,
+        compaction_manager: Arc<CompactionManager>,
+    ) -> Self {
         Self {
             raft_node,
-            kvs,
+            log_store,
+            engine,
+            rocksdb_engine_handler,
+            compaction_manager,
+        }
+    }
+
+    /// The openraft node, for the endpoints that only exist with the openraft engine.
+    pub fn openraft_node(&self, action: &str) -> Result<&Raft<TypeConfig>, RobustMQError> {
+        match &self.raft_node {
+            Some(raft_node) => Ok(raft_node),
+            None => Err(RobustMQError::UnsupportedByConsensusEngine(
+                self.engine.engine_type().name().to_string(),
+                action.to_string(),
+            )),
         }
     }
 }
@@ -81,6 +127,23 @@ fn routes(state: HttpServerState) -> Router {
         .route(&v1_path(&path_update(ROUTE_ROOT)), put(index))
         .route(&v1_path(&path_delete(ROUTE_ROOT)), delete(index));
 
-    let app = Router::new().merge(common);
+    let admin = Router::new()
+        .route(&v1_path(&path_create(ROUTE_BACKUP)), post(backup_create))
+        .route(&v1_path(&path_list(ROUTE_EXPORT)), get(export_list))
+        .route(&v1_path(&path_create(ROUTE_IMPORT)), post(import_create))
+        .route(&v1_path(&path_update(ROUTE_ENCRYPTION)), put(encryption_reencrypt))
+        .route(&v1_path(&path_create(ROUTE_COMPACTION)), post(compaction_create))
+        .route(&v1_path(&path_list(ROUTE_COMPACTION)), get(compaction_list))
+        .route(&v1_path(&path_list(ROUTE_SNAPSHOT)), get(snapshot_list))
+        .route(&v1_path(&path_create(ROUTE_SNAPSHOT)), post(snapshot_create))
+        .route(&v1_path(&path_create(ROUTE_PURGE)), post(purge_create))
+        .route(&v1_path(&path_list(ROUTE_CONSENSUS)), get(consensus_list))
+        .route(&v1_path(&path_list(ROUTE_MEMBERSHIP)), get(membership_list))
+        .route(&v1_path(&path_delete(ROUTE_MEMBERSHIP)), delete(membership_delete))
+        .route(&v1_path(&path_create(ROUTE_LEARNER)), post(learner_create))
+        .route(&v1_path(&path_create(ROUTE_VOTER)), post(voter_create))
+        .route(&v1_path(&path_update(ROUTE_LEADER)), put(leader_update));
+
+    let app = Router::new().merge(common).merge(admin);
     app.with_state(state)
 }
\ No newline at end of file
@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use log::info;
+use openraft::{LogId, Membership, StoredMembership};
+use rocksdb::checkpoint::Checkpoint;
+use rocksdb::{Options, DB};
+use serde::{Deserialize, Serialize};
+use common_base::config::placement_center::PlacementCenterConfig;
+use common_base::errors::RobustMQError;
+use common_base::tools::now_second;
+use crate::openraft::raft_node::{openraft_db_path, Node, NodeId};
+use crate::openraft::sotre::log_store::LogStore;
+use crate::openraft::sotre::open_raft_db;
+use crate::openraft::typeconfig::TypeConfig;
+use crate::storage::engine::{engine_batch, EngineBatchOp};
+use crate::storage::keys::{key_name_raft_prefix, key_openraft_last_applied, key_openraft_last_membership};
+use crate::storage::rocksdb::{column_family_list, storage_db_path, RocksDBEngine, DB_COLUMN_FAMILY_RAFT};
+use crate::storage::schema::{schema_version, SCHEMA_VERSION};
+use crate::storage::state_machine::StateMachineStorage;
+use crate::storage::StorageDataWrap;
+
+/// Name of the file describing a backup, next to the checkpoint.
+const BACKUP_METADATA_FILE: &str = "backup.json";
+
+/// What a backup contains. The checkpoint is stored under the backup directory the same way
+/// the storage is stored under a data path, so a backup can be opened like a data path.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupMetadata {
+    pub cluster_name: String,
+    pub node_id: u64,
+    pub schema_version: u64,
+    /// Raft state of the openraft state machine at the time of the checkpoint
+    pub last_applied_log_id: Option<LogId<NodeId>>,
+    pub last_membership: StoredMembership<TypeConfig>,
+    pub create_time: u64,
+}
+
+/// Create a consistent checkpoint of the storage of a running placement center under
+/// `target_dir`, which must not exist yet. The raft metadata is read from the checkpoint
+/// itself, so it matches the data of the backup exactly.
+pub fn create_backup(
+    rocksdb_engine_handler: &RocksDBEngine,
+    config: &PlacementCenterConfig,
+    target_dir: &str,
+) -> Result<BackupMetadata, RobustMQError> {
+    if Path::new(target_dir).exists() {
+        return Err(RobustMQError::BackupTargetExists(target_dir.to_string()));
+    }
+    fs::create_dir_all(target_dir)?;
+
+    let checkpoint_path = storage_db_path(target_dir);
+    Checkpoint::new(&rocksdb_engine_handler.db)?.create_checkpoint(&checkpoint_path)?;
+
+    let (last_applied_log_id, last_membership) = read_checkpoint_raft_state(rocksdb_engine_handler, &checkpoint_path)?;
+    let metadata = BackupMetadata {
+        cluster_name: config.cluster_name.clone(),
+        node_id: config.node_id,
+        schema_version: schema_version(rocksdb_engine_handler)?.unwrap_or(SCHEMA_VERSION),
+        last_applied_log_id,
+        last_membership,
+        create_time: now_second(),
+    };
+    fs::write(
+        Path::new(target_dir).join(BACKUP_METADATA_FILE),
+        serde_json::to_vec_pretty(&metadata)?,
+    )?;
+    info!("Backup of the placement center was created in {}", target_dir);
+    Ok(metadata)
+}
+
+pub fn read_backup_metadata(backup_dir: &str) -> Result<BackupMetadata, RobustMQError> {
+    let path = Path::new(backup_dir).join(BACKUP_METADATA_FILE);
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(e) => return Err(RobustMQError::InvalidBackup(backup_dir.to_string(), e.to_string())),
+    };
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// Seed the data path of a new node from a backup. The node starts as the only member of a new
+/// cluster: the raft-rs state of the old cluster is dropped, the openraft membership is replaced
+/// by this node, and its openraft log starts after the last entry applied in the backup.
+pub fn restore_backup(
+    config: &PlacementCenterConfig,
+    backup_dir: &str,
+) -> Result<BackupMetadata, RobustMQError> {
+    let metadata = read_backup_metadata(backup_dir)?;
+    if metadata.schema_version > SCHEMA_VERSION {
+        return Err(RobustMQError::SchemaVersionTooNew(metadata.schema_version, SCHEMA_VERSION));
+    }
+
+    let db_path = storage_db_path(&config.data_path);
+    let raft_db_path = openraft_db_path(&config.data_path);
+    if Path::new(&db_path).exists() || Path::new(&raft_db_path).exists() {
+        return Err(RobustMQError::RestoreTargetNotEmpty(config.data_path.clone()));
+    }
+
+    let checkpoint_path = storage_db_path(backup_dir);
+    if !Path::new(&checkpoint_path).is_dir() {
+        return Err(RobustMQError::InvalidBackup(
+            backup_dir.to_string(),
+            format!("{} is missing", checkpoint_path),
+        ));
+    }
+    copy_dir(Path::new(&checkpoint_path), Path::new(&db_path))?;
+
+    // Opening the storage runs the migrations of a backup taken by an older binary
+    let rocksdb_engine_handler = Arc::new(RocksDBEngine::new(config));
+    let state_machine_storage = StateMachineStorage::new(rocksdb_engine_handler.clone());
+    let last_applied_log_id = state_machine_storage.last_applied()?;
+    let last_membership = state_machine_storage.last_membership()?;
+
+    let mut ops = Vec::new();
+    for (key, _) in rocksdb_engine_handler.read_all_consistent(rocksdb_engine_handler.cf_raft()) {
+        if key.starts_with(&key_name_raft_prefix()) {
+            ops.push(EngineBatchOp::Delete(key));
+        }
+    }
+    let membership = StoredMembership::new(
+        *last_membership.log_id(),
+        single_node_membership(config),
+    );
+    ops.extend(state_machine_storage.applied_state_ops(&last_applied_log_id, &membership)?);
+    engine_batch(rocksdb_engine_handler.clone(), DB_COLUMN_FAMILY_RAFT, ops)?;
+
+    if let Some(last_applied) = last_applied_log_id {
+        let log_store = match LogStore::new(Arc::new(open_raft_db(&raft_db_path)), rocksdb_engine_handler.clone()) {
+            Ok(log_store) => log_store,
+            Err(e) => return Err(RobustMQError::CommonError(e.to_string())),
+        };
+        if let Err(e) = log_store.seed_from_backup(config.node_id, last_applied) {
+            return Err(RobustMQError::CommonError(e.to_string()));
+        }
+    }
+
+    info!(
+        "Data path {} was restored from the backup in {}, last applied log id {:?}",
+        config.data_path, backup_dir, last_applied_log_id
+    );
+    Ok(metadata)
+}
+
+fn single_node_membership(config: &PlacementCenterConfig) -> Membership<TypeConfig> {
+    let rpc_addr = match config.nodes.get(&config.node_id.to_string()) {
+        Some(addr) => addr.to_string().replace('"', ""),
+        None => format!("{}:{}", config.addr, config.grpc_port),
+    };
+    let mut nodes = BTreeMap::new();
+    nodes.insert(
+        config.node_id,
+        Node {
+            node_id: config.node_id,
+            rpc_addr,
+        },
+    );
+    Membership::new_with_defaults(vec![BTreeSet::from([config.node_id])], nodes)
+}
+
+// The checkpoint is opened read only, the raft metadata is stored in the raft column family.
+// Its values are encrypted like those of the storage it was taken from.
+fn read_checkpoint_raft_state(
+    rocksdb_engine_handler: &RocksDBEngine,
+    checkpoint_path: &str,
+) -> Result<(Option<LogId<NodeId>>, StoredMembership<TypeConfig>), RobustMQError> {
+    let db = DB::open_cf_for_read_only(&Options::default(), checkpoint_path, column_family_list(), false)?;
+    let cf = match db.cf_handle(DB_COLUMN_FAMILY_RAFT) {
+        Some(cf) => cf,
+        None => return Err(RobustMQError::ColumnFamilyNotFound(DB_COLUMN_FAMILY_RAFT.to_string())),
+    };
+
+    let last_applied_log_id = match db.get_cf(cf, key_openraft_last_applied())? {
+        Some(value) => {
+            let value = rocksdb_engine_handler.decrypt(&value)?;
+            serde_json::from_slice(&serde_json::from_slice::<StorageDataWrap>(&value)?.data)?
+        }
+        None => None,
+    };
+    let last_membership = match db.get_cf(cf, key_openraft_last_membership())? {
+        Some(value) => {
+            let value = rocksdb_engine_handler.decrypt(&value)?;
+            serde_json::from_slice(&serde_json::from_slice::<StorageDataWrap>(&value)?.data)?
+        }
+        None => StoredMembership::default(),
+    };
+    Ok((last_applied_log_id, last_membership))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), RobustMQError> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use openraft::{CommittedLeaderId, LogId, StoredMembership};
+    use common_base::config::placement_center::PlacementCenterConfig;
+    use common_base::errors::RobustMQError;
+    use crate::storage::engine::engine_batch_across;
+    use crate::storage::kv::KvStorage;
+    use crate::storage::namespace::{namespace_key, DEFAULT_NAMESPACE};
+    use crate::storage::rocksdb::{RocksDBEngine, DB_COLUMN_FAMILY_RAFT};
+    use crate::storage::state_machine::StateMachineStorage;
+    use crate::storage::test_tools::TempDir;
+    use super::{create_backup, read_backup_metadata, restore_backup};
+
+    fn config(data_path: &str) -> PlacementCenterConfig {
+        let mut config = PlacementCenterConfig::default();
+        config.data_path = data_path.to_string();
+        config.node_id = 1;
+        config.addr = "127.0.0.1".to_string();
+        config.grpc_port = 8871;
+        config
+    }
+
+    #[test]
+    fn backup_and_restore() {
+        let dir = TempDir::new();
+        let source = config(&dir.join("source"));
+        let backup_dir = &dir.join("backup");
+
+        let rs = Arc::new(RocksDBEngine::new(&source));
+        let key = namespace_key(DEFAULT_NAMESPACE, "/broker/1");
+        KvStorage::new(rs.clone()).set(key.clone(), "v1".to_string(), 0, 5).unwrap();
+        let log_id = LogId::new(CommittedLeaderId::new(3, 1), 5);
+        let ops = StateMachineStorage::new(rs.clone())
+            .applied_state_ops(&Some(log_id), &StoredMembership::default())
+            .unwrap();
+        engine_batch_across(rs.clone(), vec![(DB_COLUMN_FAMILY_RAFT, ops)]).unwrap();
+
+        let metadata = create_backup(&rs, &source, backup_dir).unwrap();
+        assert_eq!(metadata.last_applied_log_id, Some(log_id));
+        match create_backup(&rs, &source, backup_dir) {
+            Err(RobustMQError::BackupTargetExists(_)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        assert_eq!(read_backup_metadata(backup_dir).unwrap().node_id, 1);
+
+        let target = config(&dir.join("restore"));
+        restore_backup(&target, backup_dir).unwrap();
+        {
+            let restored = Arc::new(RocksDBEngine::new(&target));
+            assert_eq!(KvStorage::new(restored.clone()).get(key).unwrap().unwrap(), "v1");
+            let storage = StateMachineStorage::new(restored);
+            assert_eq!(storage.last_applied().unwrap(), Some(log_id));
+            let voters: Vec<u64> = storage.last_membership().unwrap().membership().voter_ids().collect();
+            assert_eq!(voters, vec![1]);
+        }
+
+        // A data path that is already in use is never overwritten
+        match restore_backup(&target, backup_dir) {
+            Err(RobustMQError::RestoreTargetNotEmpty(_)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::Arc;
+use std::time::Duration;
+use dashmap::DashMap;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+use common_base::errors::RobustMQError;
+use common_base::tools::now_second;
+use crate::storage::rocksdb::{column_family_list, RocksDBEngine};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum CompactionState {
+    Running,
+    Finished,
+}
+
+/// The last compaction of a column family, manual or scheduled.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompactionTask {
+    pub family: String,
+    pub state: CompactionState,
+    pub start_time: u64,
+    /// 0 while the compaction is running
+    pub end_time: u64,
+}
+
+/// Progress of the compactions of a column family. RocksDB does not report the progress of a
+/// manual compaction itself, the pending bytes shrink as it goes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompactionStatus {
+    pub family: String,
+    pub task: Option<CompactionTask>,
+    pub pending_compaction_bytes: u64,
+    pub running_compactions: u64,
+    pub sst_files_size: u64,
+}
+
+/// Runs the manual and scheduled compactions of the column families, at most one per family.
+pub struct CompactionManager {
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    tasks: DashMap<String, CompactionTask>,
+}
+
+impl CompactionManager {
+    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>) -> Self {
+        CompactionManager {
+            rocksdb_engine_handler,
+            tasks: DashMap::with_capacity(2),
+        }
+    }
+
+    /// Compact the column family in the background. Only one compaction of a family runs at a time.
+    pub fn trigger(self: &Arc<Self>, family: &str) -> Result<CompactionTask, RobustMQError> {
+        self.rocksdb_engine_handler.cf_handle(family)?;
+        let task = CompactionTask {
+            family: family.to_string(),
+            state: CompactionState::Running,
+            start_time: now_second(),
+            end_time: 0,
+        };
+        match self.tasks.entry(family.to_string()) {
+            dashmap::mapref::entry::Entry::Occupied(mut entry) => {
+                if entry.get().state == CompactionState::Running {
+                    return Err(RobustMQError::CompactionRunning(family.to_string()));
+                }
+                entry.insert(task.clone());
+            }
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(task.clone());
+            }
+        }
+
+        let manager = self.clone();
+        let family = family.to_string();
+        tokio::task::spawn_blocking(move || {
+            info!("Compaction of column family {} started", family);
+            if let Err(e) = manager.rocksdb_engine_handler.compact_column_family(&family) {
+                error!("Compaction of column family {} failed, error message: {}", family, e);
+            }
+            if let Some(mut task) = manager.tasks.get_mut(&family) {
+                task.state = CompactionState::Finished;
+                task.end_time = now_second();
+                info!(
+                    "Compaction of column family {} finished in {}s",
+                    family,
+                    task.end_time - task.start_time
+                );
+            }
+        });
+        Ok(task)
+    }
+
+    pub fn status(&self, family: &str) -> Result<CompactionStatus, RobustMQError> {
+        let engine = &self.rocksdb_engine_handler;
+        Ok(CompactionStatus {
+            family: family.to_string(),
+            task: self.tasks.get(family).map(|task| task.clone()),
+            pending_compaction_bytes: engine.property_int_value(family, "rocksdb.estimate-pending-compaction-bytes")?,
+            running_compactions: engine.property_int_value(family, "rocksdb.num-running-compactions")?,
+            sst_files_size: engine.property_int_value(family, "rocksdb.total-sst-files-size")?,
+        })
+    }
+
+    pub fn status_list(&self) -> Result<Vec<CompactionStatus>, RobustMQError> {
+        let mut results = Vec::new();
+        for family in column_family_list() {
+            results.push(self.status(&family)?);
+        }
+        Ok(results)
+    }
+
+    /// Compact every column family each `interval_sec` seconds, auto compaction is disabled by default.
+    pub async fn start(self: &Arc<Self>, stop_sx: broadcast::Sender<bool>, interval_sec: u64) {
+        if interval_sec == 0 {
+            return;
+        }
+        info!("{}", "Scheduled compaction thread started successfully");
+        let mut stop_rx = stop_sx.subscribe();
+        loop {
+            select! {
+                val = stop_rx.recv() => {
+                    if let Ok(flag) = val {
+                        if flag {
+                            info!("{}", "Scheduled compaction thread stopped successfully");
+                            break;
+                        }
+                    }
+                }
+                _ = sleep(Duration::from_secs(interval_sec)) => {
+                    for family in column_family_list() {
+                        if let Err(e) = self.trigger(&family) {
+                            error!("Scheduled compaction of column family {} was skipped: {}", family, e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::time::sleep;
+    use common_base::errors::RobustMQError;
+    use crate::storage::rocksdb::DB_COLUMN_FAMILY_KV;
+    use crate::storage::test_tools::TempDir;
+    use super::{CompactionManager, CompactionState};
+
+    #[tokio::test]
+    async fn trigger_and_status() {
+        let dir = TempDir::new();
+        let rs = dir.rocksdb_engine();
+        for i in 0..100 {
+            rs.write_str(rs.cf_kv(), &format!("/kv/default/k{}", i), i.to_string()).unwrap();
+        }
+
+        let manager = Arc::new(CompactionManager::new(rs));
+        match manager.trigger("unknown") {
+            Err(RobustMQError::ColumnFamilyNotFound(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        manager.trigger(DB_COLUMN_FAMILY_KV).unwrap();
+
+        let mut finished = false;
+        for _ in 0..100 {
+            let status = manager.status(DB_COLUMN_FAMILY_KV).unwrap();
+            if status.task.unwrap().state == CompactionState::Finished {
+                finished = true;
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        assert!(finished);
+        assert_eq!(manager.status_list().unwrap().len(), 5);
+    }
+}
@@ -0,0 +1,99 @@
+use common_base::errors::RobustMQError;
+
+/// First byte of a compressed snapshot payload. It can start neither a JSON document nor the
+/// bincode encoding of the column family map, which tells compressed payloads apart from the
+/// uncompressed ones written before the header existed.
+pub const SNAPSHOT_MAGIC: u8 = 0xFC;
+
+/// zstd level of the snapshots, the metadata keys share long prefixes and compress well
+/// without a higher level.
+const ZSTD_LEVEL: i32 = 3;
+
+/// Compression of a snapshot payload, recorded in the byte after the magic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotCodec {
+    None,
+    Zstd,
+}
+
+impl SnapshotCodec {
+    fn id(&self) -> u8 {
+        match self {
+            SnapshotCodec::None => 0,
+            SnapshotCodec::Zstd => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, RobustMQError> {
+        match id {
+            0 => Ok(SnapshotCodec::None),
+            1 => Ok(SnapshotCodec::Zstd),
+            _ => Err(RobustMQError::UnsupportedSnapshotCodec(id)),
+        }
+    }
+}
+
+/// Compress a snapshot payload with zstd behind a two byte header naming the codec.
+pub fn compress_snapshot(data: &[u8]) -> Result<Vec<u8>, RobustMQError> {
+    encode_snapshot(data, SnapshotCodec::Zstd)
+}
+
+pub fn encode_snapshot(data: &[u8], codec: SnapshotCodec) -> Result<Vec<u8>, RobustMQError> {
+    let mut buf = vec![SNAPSHOT_MAGIC, codec.id()];
+    match codec {
+        SnapshotCodec::None => buf.extend_from_slice(data),
+        SnapshotCodec::Zstd => buf.extend(zstd::bulk::compress(data, ZSTD_LEVEL)?),
+    }
+    Ok(buf)
+}
+
+/// Decompress a payload written by `encode_snapshot`. A payload without the header is a
+/// snapshot written before compression existed and is returned as it is.
+pub fn decompress_snapshot(data: &[u8]) -> Result<Vec<u8>, RobustMQError> {
+    if data.first() != Some(&SNAPSHOT_MAGIC) {
+        return Ok(data.to_vec());
+    }
+    let codec = match data.get(1) {
+        Some(id) => SnapshotCodec::from_id(*id)?,
+        None => {
+            return Err(RobustMQError::CommonError(
+                "Snapshot is missing its codec".to_string(),
+            ))
+        }
+    };
+    match codec {
+        SnapshotCodec::None => Ok(data[2..].to_vec()),
+        SnapshotCodec::Zstd => Ok(zstd::stream::decode_all(&data[2..])?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use common_base::errors::RobustMQError;
+    use super::{compress_snapshot, decompress_snapshot, encode_snapshot, SnapshotCodec};
+
+    #[test]
+    fn compress_decompress() {
+        let mut data = BTreeMap::new();
+        for i in 0..1000 {
+            data.insert(format!("/mqtt/session/cluster-1/client-{}", i), "{\"clean_session\":true}");
+        }
+        let data = serde_json::to_vec(&data).unwrap();
+
+        let compressed = compress_snapshot(&data).unwrap();
+        assert!(compressed.len() * 5 < data.len());
+        assert_eq!(decompress_snapshot(&compressed).unwrap(), data);
+        assert_eq!(decompress_snapshot(&encode_snapshot(&data, SnapshotCodec::None).unwrap()).unwrap(), data);
+
+        // Snapshots written before compression existed
+        assert_eq!(decompress_snapshot(&data).unwrap(), data);
+
+        let mut unknown = compressed.clone();
+        unknown[1] = 9;
+        match decompress_snapshot(&unknown) {
+            Err(RobustMQError::UnsupportedSnapshotCodec(9)) => {}
+            other => panic!("unexpected result {:?}", other.map(|data| data.len())),
+        }
+    }
+}
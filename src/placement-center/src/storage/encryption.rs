@@ -0,0 +1,238 @@
+use std::collections::{BTreeMap, HashMap};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use serde::Deserialize;
+use common_base::errors::RobustMQError;
+use common_base::tools::read_file;
+use crate::openraft::sotre::codec::FORMAT_MAGIC;
+use crate::storage::compression::SNAPSHOT_MAGIC;
+
+/// First byte of every encrypted value. A value without it is only read as written before
+/// encryption was enabled when it starts like one of the plaintext formats, see `is_plaintext`.
+const ENCRYPTED_MAGIC: u8 = 0xFD;
+const KEY_ID_LEN: usize = 4;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = 1 + KEY_ID_LEN + NONCE_LEN;
+
+/// The key file names the key new values are encrypted with, and every key that may still
+/// be needed to decrypt older values. Keys are 32 bytes written as hex:
+///
+/// ```toml
+/// active_key_id = 2
+///
+/// [keys]
+/// 1 = "<64 hex characters>"
+/// 2 = "<64 hex characters>"
+/// ```
+///
+/// A key is rotated by adding a new key, making it active, re-encrypting the stored values,
+/// and only then removing the old key from the file.
+#[derive(Deserialize)]
+struct KeyFile {
+    active_key_id: u32,
+    keys: BTreeMap<String, String>,
+}
+
+/// AES-256-GCM encryption of stored values. An encrypted value is the magic byte, the id of
+/// its key as a big endian u32, the nonce and the ciphertext.
+pub struct ValueCipher {
+    active_key_id: u32,
+    keys: HashMap<u32, Aes256Gcm>,
+}
+
+impl ValueCipher {
+    pub fn from_key_file(path: &str) -> Result<Self, RobustMQError> {
+        let invalid = |e: String| RobustMQError::InvalidEncryptionKeyFile(path.to_string(), e);
+        let content = match read_file(&path.to_string()) {
+            Ok(content) => content,
+            Err(e) => return Err(invalid(e.to_string())),
+        };
+        let key_file: KeyFile = match toml::from_str(&content) {
+            Ok(key_file) => key_file,
+            Err(e) => return Err(invalid(e.to_string())),
+        };
+
+        let mut keys = Vec::with_capacity(key_file.keys.len());
+        for (key_id, key) in key_file.keys {
+            let key_id = match key_id.parse::<u32>() {
+                Ok(key_id) => key_id,
+                Err(e) => return Err(invalid(format!("key id {}: {}", key_id, e))),
+            };
+            match decode_hex(&key) {
+                Some(key) => keys.push((key_id, key)),
+                None => return Err(invalid(format!("key {} is not valid hex", key_id))),
+            }
+        }
+        Self::from_keys(key_file.active_key_id, keys)
+    }
+
+    pub fn from_keys(active_key_id: u32, keys: Vec<(u32, Vec<u8>)>) -> Result<Self, RobustMQError> {
+        let mut ciphers = HashMap::with_capacity(keys.len());
+        for (key_id, key) in keys {
+            match Aes256Gcm::new_from_slice(&key) {
+                Ok(cipher) => ciphers.insert(key_id, cipher),
+                Err(_) => {
+                    return Err(RobustMQError::InvalidEncryptionKeyFile(
+                        key_id.to_string(),
+                        "keys must be 32 bytes long".to_string(),
+                    ))
+                }
+            };
+        }
+        if !ciphers.contains_key(&active_key_id) {
+            return Err(RobustMQError::EncryptionKeyNotFound(active_key_id));
+        }
+        Ok(ValueCipher {
+            active_key_id,
+            keys: ciphers,
+        })
+    }
+
+    /// Encrypt the value with the active key.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, RobustMQError> {
+        let cipher = &self.keys[&self.active_key_id];
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = match cipher.encrypt(&nonce, plaintext) {
+            Ok(data) => data,
+            Err(e) => return Err(RobustMQError::CommonError(e.to_string())),
+        };
+
+        let mut data = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        data.push(ENCRYPTED_MAGIC);
+        data.extend_from_slice(&self.active_key_id.to_be_bytes());
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        Ok(data)
+    }
+
+    /// Decrypt a value written by `encrypt`. Plaintext values are returned as they are, a value
+    /// starting with any other byte is rejected.
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, RobustMQError> {
+        let key_id = match encryption_key_id(data) {
+            Some(key_id) => key_id,
+            None if is_plaintext(data) => return Ok(data.to_vec()),
+            None => {
+                return Err(RobustMQError::DecryptionFailed(format!(
+                    "value starts with the unknown byte 0x{:02X}",
+                    data[0]
+                )))
+            }
+        };
+        if data.len() < HEADER_LEN {
+            return Err(RobustMQError::DecryptionFailed(
+                "value is shorter than its header".to_string(),
+            ));
+        }
+        let cipher = match self.keys.get(&key_id) {
+            Some(cipher) => cipher,
+            None => return Err(RobustMQError::EncryptionKeyNotFound(key_id)),
+        };
+        let nonce = Nonce::from_slice(&data[1 + KEY_ID_LEN..HEADER_LEN]);
+        match cipher.decrypt(nonce, &data[HEADER_LEN..]) {
+            Ok(plaintext) => Ok(plaintext),
+            Err(e) => Err(RobustMQError::DecryptionFailed(e.to_string())),
+        }
+    }
+
+    /// Whether the value is encrypted with the active key, the others need re-encryption.
+    pub fn is_current(&self, data: &[u8]) -> bool {
+        encryption_key_id(data) == Some(self.active_key_id)
+    }
+}
+
+/// The id of the key the value is encrypted with, None for a value that is not encrypted.
+pub fn encryption_key_id(data: &[u8]) -> Option<u32> {
+    if data.first() != Some(&ENCRYPTED_MAGIC) || data.len() < 1 + KEY_ID_LEN {
+        return None;
+    }
+    let mut key_id = [0u8; KEY_ID_LEN];
+    key_id.copy_from_slice(&data[1..1 + KEY_ID_LEN]);
+    Some(u32::from_be_bytes(key_id))
+}
+
+/// Whether the value starts like one written without encryption: a JSON document, a value of
+/// the raft log codec or a compressed snapshot.
+fn is_plaintext(data: &[u8]) -> bool {
+    match data.first() {
+        None => true,
+        Some(&byte) if byte == FORMAT_MAGIC || byte == SNAPSHOT_MAGIC => true,
+        Some(byte) => matches!(
+            byte,
+            b'{' | b'[' | b'"' | b'-' | b'0'..=b'9' | b't' | b'f' | b'n' | b' ' | b'\t' | b'\n' | b'\r'
+        ),
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use common_base::errors::RobustMQError;
+    use crate::storage::test_tools::TempDir;
+    use super::{encryption_key_id, ValueCipher};
+
+    #[test]
+    fn encrypt_decrypt_and_rotate() {
+        let old = ValueCipher::from_keys(1, vec![(1, vec![7; 32])]).unwrap();
+        let encrypted = old.encrypt(b"\"secret\"").unwrap();
+        assert_eq!(encryption_key_id(&encrypted), Some(1));
+        assert!(!encrypted.windows(6).any(|w| w == b"secret"));
+        assert_eq!(old.decrypt(&encrypted).unwrap(), b"\"secret\"");
+        // Values written before encryption was enabled are read as they are
+        assert_eq!(old.decrypt(b"\"plain\"").unwrap(), b"\"plain\"");
+        assert_eq!(old.decrypt(&[0xFE, 1, 0]).unwrap(), vec![0xFE, 1, 0]);
+        // Anything else is neither encrypted nor plaintext
+        for data in [&[0xAB, 1, 2][..], &[0xFD, 0, 0]] {
+            match old.decrypt(data) {
+                Err(RobustMQError::DecryptionFailed(_)) => {}
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+
+        let rotated = ValueCipher::from_keys(2, vec![(1, vec![7; 32]), (2, vec![9; 32])]).unwrap();
+        assert!(!rotated.is_current(&encrypted));
+        assert_eq!(rotated.decrypt(&encrypted).unwrap(), b"\"secret\"");
+        assert!(rotated.is_current(&rotated.encrypt(b"1").unwrap()));
+
+        let without_old = ValueCipher::from_keys(2, vec![(2, vec![9; 32])]).unwrap();
+        match without_old.decrypt(&encrypted) {
+            Err(RobustMQError::EncryptionKeyNotFound(1)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let mut tampered = encrypted.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        match old.decrypt(&tampered) {
+            Err(RobustMQError::DecryptionFailed(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn key_file() {
+        let dir = TempDir::new();
+        let path = &dir.join("encryption.keys");
+        std::fs::write(
+            path,
+            format!("active_key_id = 3\n\n[keys]\n3 = \"{}\"\n", "ab".repeat(32)),
+        )
+        .unwrap();
+        let cipher = ValueCipher::from_key_file(path).unwrap();
+        assert_eq!(encryption_key_id(&cipher.encrypt(b"v").unwrap()), Some(3));
+
+        std::fs::write(path, format!("active_key_id = 4\n\n[keys]\n3 = \"{}\"\n", "ab".repeat(32))).unwrap();
+        match ValueCipher::from_key_file(path) {
+            Err(RobustMQError::EncryptionKeyNotFound(4)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+}
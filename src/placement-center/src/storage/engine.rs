@@ -1,11 +1,18 @@
 use std::sync::Arc;
 use raft::Storage;
+use rocksdb::WriteBatch;
 use serde::Serialize;
 use serde_json::Value;
 use common_base::errors::RobustMQError;
-use crate::storage::rocksdb::{RocksDBEngine, DB_COLUMN_FAMILY_CLUSTER};
+use crate::storage::rocksdb::{RocksDBEngine, DB_COLUMN_FAMILY_CLUSTER, DB_COLUMN_FAMILY_KV};
 use crate::storage::StorageDataWrap;
 
+/// A single update of a write batch.
+pub enum EngineBatchOp {
+    Save(String, StorageDataWrap),
+    Delete(String),
+}
+
 pub fn engine_save_by_cluster<T>(
     rocksdb_engine_handler: Arc<RocksDBEngine>,
     key_name: String,
@@ -22,6 +29,19 @@ where
     )
 }
 
+pub fn engine_save_wrap_by_cluster(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    key_name: String,
+    data: StorageDataWrap,
+) -> Result<(), RobustMQError> {
+    engine_save_wrap(
+        rocksdb_engine_handler,
+        DB_COLUMN_FAMILY_CLUSTER,
+        key_name,
+        data,
+    )
+}
+
 pub fn engine_get_by_cluster(
     rocksdb_engine_handler: Arc<RocksDBEngine>,
     key_name: String,
@@ -54,20 +74,99 @@ pub fn engine_prefix_list_by_cluster(
     )
 }
 
-fn engine_save<T>(
+pub fn engine_prefix_page_by_cluster(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    prefix_key_name: String,
+    start_after: String,
+    limit: usize,
+) -> Result<(Vec<(String, StorageDataWrap)>, bool), RobustMQError> {
+    engine_prefix_page(
+        rocksdb_engine_handler,
+        DB_COLUMN_FAMILY_CLUSTER,
+        prefix_key_name,
+        start_after,
+        limit,
+    )
+}
+
+pub fn engine_batch_by_cluster(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    ops: Vec<EngineBatchOp>,
+) -> Result<(), RobustMQError> {
+    engine_batch(rocksdb_engine_handler, DB_COLUMN_FAMILY_CLUSTER, ops)
+}
+
+pub fn engine_save_by_kv<T>(
     rocksdb_engine_handler: Arc<RocksDBEngine>,
-    rocksdb_cluster: &str,
     key_name: String,
     value: T,
 ) -> Result<(), RobustMQError>
 where
     T: Serialize,
 {
-    let cf = if rocksdb_cluster.to_string() == DB_COLUMN_FAMILY_CLUSTER.to_string() {
-        rocksdb_engine_handler.cf_cluster()
-    } else {
-        return Err(RobustMQError::ClusterNoAvailableNode);
-    };
+    engine_save(rocksdb_engine_handler, DB_COLUMN_FAMILY_KV, key_name, value)
+}
+
+pub fn engine_get_by_kv(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    key_name: String,
+) -> Result<Option<StorageDataWrap>, RobustMQError> {
+    engine_get(rocksdb_engine_handler, DB_COLUMN_FAMILY_KV, key_name)
+}
+
+pub fn engine_exists_by_kv(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    key_name: String,
+) -> Result<bool, RobustMQError> {
+    engine_exists(rocksdb_engine_handler, DB_COLUMN_FAMILY_KV, key_name)
+}
+
+pub fn engine_delete_by_kv(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    key_name: String,
+) -> Result<(), RobustMQError> {
+    engine_delete(rocksdb_engine_handler, DB_COLUMN_FAMILY_KV, key_name)
+}
+
+pub fn engine_prefix_list_by_kv(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    prefix_key_name: String,
+) -> Result<Vec<StorageDataWrap>, RobustMQError> {
+    engine_prefix_list(rocksdb_engine_handler, DB_COLUMN_FAMILY_KV, prefix_key_name)
+}
+
+pub fn engine_prefix_page_by_kv(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    prefix_key_name: String,
+    start_after: String,
+    limit: usize,
+) -> Result<(Vec<(String, StorageDataWrap)>, bool), RobustMQError> {
+    engine_prefix_page(
+        rocksdb_engine_handler,
+        DB_COLUMN_FAMILY_KV,
+        prefix_key_name,
+        start_after,
+        limit,
+    )
+}
+
+pub fn engine_batch_by_kv(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    ops: Vec<EngineBatchOp>,
+) -> Result<(), RobustMQError> {
+    engine_batch(rocksdb_engine_handler, DB_COLUMN_FAMILY_KV, ops)
+}
+
+pub fn engine_save<T>(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    column_family: &str,
+    key_name: String,
+    value: T,
+) -> Result<(), RobustMQError>
+where
+    T: Serialize,
+{
+    let cf = rocksdb_engine_handler.cf_handle(column_family)?;
 
     let content = match serde_json::to_vec(&value) {
         Ok(data) => data,
@@ -83,16 +182,26 @@ where
     }
 }
 
-fn engine_get(
+pub fn engine_save_wrap(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    column_family: &str,
+    key_name: String,
+    data: StorageDataWrap,
+) -> Result<(), RobustMQError> {
+    let cf = rocksdb_engine_handler.cf_handle(column_family)?;
+
+    match rocksdb_engine_handler.write(cf, &key_name, &data) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(RobustMQError::CommonError(e))
+    }
+}
+
+pub fn engine_get(
     rocksdb_engine_handler: Arc<RocksDBEngine>,
-    rocksdb_cluster: &str,
+    column_family: &str,
     key_name: String,
 ) -> Result<Option<StorageDataWrap>, RobustMQError> {
-    let cf = if rocksdb_cluster.to_string() == DB_COLUMN_FAMILY_CLUSTER {
-        rocksdb_engine_handler.cf_cluster()
-    } else {
-        return Err(RobustMQError::ClusterNoAvailableNode);
-    };
+    let cf = rocksdb_engine_handler.cf_handle(column_family)?;
 
     match rocksdb_engine_handler.read::<StorageDataWrap>(cf, &key_name) {
         Ok(Some(data)) => Ok(Some(data)),
@@ -101,44 +210,32 @@ fn engine_get(
     }
 }
 
-fn engine_delete(
+pub fn engine_delete(
     rocksdb_engine_handler: Arc<RocksDBEngine>,
-    rocksdb_cluster: &str,
+    column_family: &str,
     key_name: String,
 ) -> Result<(), RobustMQError> {
-    let cf = if rocksdb_cluster.to_string() == DB_COLUMN_FAMILY_CLUSTER.to_string() {
-        rocksdb_engine_handler.cf_cluster()
-    } else {
-        return Err(RobustMQError::ClusterNoAvailableNode);
-    };
+    let cf = rocksdb_engine_handler.cf_handle(column_family)?;
 
     rocksdb_engine_handler.delete(cf, &key_name)
 }
 
-fn engine_exists(
+pub fn engine_exists(
     rocksdb_engine_handler: Arc<RocksDBEngine>,
-    rocksdb_cluster: &str,
+    column_family: &str,
     key_name: String,
 ) -> Result<bool, RobustMQError> {
-    let cf = if rocksdb_cluster.to_string() == DB_COLUMN_FAMILY_CLUSTER.to_string() {
-        rocksdb_engine_handler.cf_cluster()
-    } else {
-        return Err(RobustMQError::ClusterNoAvailableNode);
-    };
+    let cf = rocksdb_engine_handler.cf_handle(column_family)?;
 
     Ok(rocksdb_engine_handler.exist(cf, &key_name))
 }
 
-fn engine_prefix_list(
+pub fn engine_prefix_list(
     rocksdb_engine_handler: Arc<RocksDBEngine>,
-    rocksdb_cluster: &str,
+    column_family: &str,
     prefix_key_name: String,
 ) -> Result<Vec<StorageDataWrap>, RobustMQError> {
-    let cf = if rocksdb_cluster.to_string() == DB_COLUMN_FAMILY_CLUSTER.to_string() {
-        rocksdb_engine_handler.cf_cluster()
-    } else {
-        return Err(RobustMQError::ClusterNoAvailableNode);
-    };
+    let cf = rocksdb_engine_handler.cf_handle(column_family)?;
 
     let data_list = rocksdb_engine_handler.read_prefix(cf, &prefix_key_name);
     let mut results = Vec::new();
@@ -152,4 +249,56 @@ fn engine_prefix_list(
     }
 
     Ok(results)
-}
\ No newline at end of file
+}
+
+pub fn engine_prefix_page(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    column_family: &str,
+    prefix_key_name: String,
+    start_after: String,
+    limit: usize,
+) -> Result<(Vec<(String, StorageDataWrap)>, bool), RobustMQError> {
+    let cf = rocksdb_engine_handler.cf_handle(column_family)?;
+
+    let (data_list, more) =
+        rocksdb_engine_handler.read_prefix_page(cf, &prefix_key_name, &start_after, limit);
+    let mut results = Vec::new();
+    for (k, v) in data_list {
+        match serde_json::from_slice(v.as_ref()) {
+            Ok(v) => results.push((k, v)),
+            Err(_) => continue,
+        }
+    }
+
+    Ok((results, more))
+}
+
+pub fn engine_batch(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    column_family: &str,
+    ops: Vec<EngineBatchOp>,
+) -> Result<(), RobustMQError> {
+    engine_batch_across(rocksdb_engine_handler, vec![(column_family, ops)])
+}
+
+/// Write the updates of several column families in one atomic batch.
+pub fn engine_batch_across(
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    family_ops: Vec<(&str, Vec<EngineBatchOp>)>,
+) -> Result<(), RobustMQError> {
+    let mut batch = WriteBatch::default();
+    for (column_family, ops) in family_ops {
+        let cf = rocksdb_engine_handler.cf_handle(column_family)?;
+        for op in ops {
+            match op {
+                EngineBatchOp::Save(key_name, data) => {
+                    batch.put_cf(cf, key_name, rocksdb_engine_handler.encrypt(serde_json::to_vec(&data)?)?);
+                }
+                EngineBatchOp::Delete(key_name) => {
+                    batch.delete_cf(cf, key_name);
+                }
+            }
+        }
+    }
+    rocksdb_engine_handler.write_batch(batch)
+}
@@ -0,0 +1,257 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use common_base::errors::RobustMQError;
+use crate::storage::keys::{key_kv_prefix, key_lease_prefix, key_namespace_prefix, key_schema_version};
+use crate::storage::namespace::Namespace;
+use crate::storage::rocksdb::{RocksDBEngine, DB_COLUMN_FAMILY_CLUSTER, DB_COLUMN_FAMILY_JOURNAL, DB_COLUMN_FAMILY_KV, DB_COLUMN_FAMILY_MQTT};
+use crate::storage::StorageDataWrap;
+
+/// Number of entries replayed by one raft proposal of an import.
+pub const IMPORT_BATCH_SIZE: usize = 500;
+
+/// One line of a logical export: a stored entry with its value decoded as JSON.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportEntry {
+    pub family: String,
+    pub key: String,
+    pub value: Value,
+    pub create_time: u64,
+    pub create_revision: u64,
+    pub mod_revision: u64,
+    pub version: u64,
+    pub lease_id: u64,
+}
+
+impl ExportEntry {
+    fn new(family: &str, key: String, data: StorageDataWrap) -> Result<Self, RobustMQError> {
+        Ok(ExportEntry {
+            family: family.to_string(),
+            key,
+            value: serde_json::from_slice(&data.data)?,
+            create_time: data.create_time,
+            create_revision: data.create_revision,
+            mod_revision: data.mod_revision,
+            version: data.version,
+            lease_id: data.lease_id,
+        })
+    }
+
+    pub fn is_namespace(&self) -> bool {
+        self.family == DB_COLUMN_FAMILY_KV && self.key.starts_with(&key_namespace_prefix())
+    }
+
+    /// The limits of a namespace record. Its usage is not replayed, it is counted again
+    /// as the keys of the namespace are imported.
+    pub fn namespace(&self) -> Option<Namespace> {
+        if !self.is_namespace() {
+            return None;
+        }
+        serde_json::from_value(self.value.clone()).ok()
+    }
+
+    /// The value to replay for a key of the KV service. Keys attached to a lease are not
+    /// replayed, their lease does not exist in the cluster the dump is imported into.
+    pub fn kv_value(&self) -> Option<String> {
+        if self.family != DB_COLUMN_FAMILY_KV || !self.key.starts_with(&key_kv_prefix()) || self.lease_id != 0 {
+            return None;
+        }
+        match &self.value {
+            Value::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    /// Whether an import replays the entry. Only the namespaces and keys of the KV service are
+    /// replayed, the other domains are written by their own services.
+    pub fn is_importable(&self) -> bool {
+        self.namespace().is_some() || self.kv_value().is_some()
+    }
+}
+
+/// A batch of an import as it is proposed. It holds either namespaces or keys.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ImportBatch {
+    pub namespaces: Vec<Namespace>,
+    pub kvs: Vec<(String, String)>,
+}
+
+/// Split a batch of an import into the namespaces and the keys to replay.
+pub fn import_batch(entries: Vec<ExportEntry>) -> ImportBatch {
+    let mut namespaces = Vec::new();
+    let mut kvs = Vec::new();
+    for entry in entries {
+        if let Some(namespace) = entry.namespace() {
+            namespaces.push(namespace);
+        } else if let Some(value) = entry.kv_value() {
+            kvs.push((entry.key, value));
+        }
+    }
+    ImportBatch { namespaces, kvs }
+}
+
+/// Result of an import.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ImportSummary {
+    pub imported: u64,
+    pub skipped: u64,
+    pub batches: u64,
+}
+
+/// Pass every KV and metadata entry to `visit`, from a single point-in-time view and in the
+/// order an import replays them. The raft family is left out, it only holds consensus state.
+/// Returns the number of exported entries.
+pub fn export_entries<F>(rocksdb_engine: &RocksDBEngine, mut visit: F) -> Result<u64, RobustMQError>
+where
+    F: FnMut(ExportEntry) -> Result<(), RobustMQError>,
+{
+    let namespace_prefix = key_namespace_prefix();
+    let kv_prefix = key_kv_prefix();
+    let lease_prefix = key_lease_prefix();
+    // Namespaces come before the keys, an import has to create them first
+    let scans = [
+        (DB_COLUMN_FAMILY_CLUSTER, ""),
+        (DB_COLUMN_FAMILY_KV, namespace_prefix.as_str()),
+        (DB_COLUMN_FAMILY_KV, kv_prefix.as_str()),
+        (DB_COLUMN_FAMILY_KV, lease_prefix.as_str()),
+        (DB_COLUMN_FAMILY_JOURNAL, ""),
+        (DB_COLUMN_FAMILY_MQTT, ""),
+    ];
+    let schema_version_key = key_schema_version();
+
+    let mut count = 0;
+    rocksdb_engine.scan_consistent(&scans, |family, key, value| {
+        if key == schema_version_key {
+            return Ok(());
+        }
+        let entry = match serde_json::from_slice::<StorageDataWrap>(value) {
+            Ok(data) => ExportEntry::new(family, key.clone(), data),
+            Err(e) => Err(e.into()),
+        };
+        match entry {
+            Ok(entry) => {
+                visit(entry)?;
+                count += 1;
+            }
+            Err(e) => error!("Key {} of family {} was left out of the export: {}", key, family, e),
+        }
+        Ok(())
+    })?;
+    Ok(count)
+}
+
+/// Parse line `line_no` of an export, rejecting entries an import cannot apply.
+pub fn parse_export_line(line_no: u64, line: &str) -> Result<ExportEntry, RobustMQError> {
+    let entry: ExportEntry = match serde_json::from_str(line) {
+        Ok(entry) => entry,
+        Err(e) => return Err(RobustMQError::InvalidExportEntry(line_no, e.to_string())),
+    };
+    if entry.is_namespace() {
+        if let Err(e) = serde_json::from_value::<Namespace>(entry.value.clone()) {
+            return Err(RobustMQError::InvalidExportEntry(line_no, e.to_string()));
+        }
+    }
+    Ok(entry)
+}
+
+/// Groups the entries of an import into raft proposals. A proposal never mixes namespaces
+/// with keys, the usage of a namespace can only be counted once the namespace is stored.
+pub struct ImportBatcher {
+    batch_size: usize,
+    entries: Vec<ExportEntry>,
+}
+
+impl ImportBatcher {
+    pub fn new(batch_size: usize) -> Self {
+        ImportBatcher {
+            batch_size,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Add the entry, returning the batch to propose first when the entry cannot join it.
+    pub fn push(&mut self, entry: ExportEntry) -> Option<Vec<ExportEntry>> {
+        let full = match self.entries.first() {
+            Some(first) => {
+                self.entries.len() >= self.batch_size || first.is_namespace() != entry.is_namespace()
+            }
+            None => false,
+        };
+        let batch = if full {
+            Some(std::mem::take(&mut self.entries))
+        } else {
+            None
+        };
+        self.entries.push(entry);
+        batch
+    }
+
+    /// The last batch, None when every entry was already returned by `push`.
+    pub fn finish(self) -> Option<Vec<ExportEntry>> {
+        if self.entries.is_empty() {
+            None
+        } else {
+            Some(self.entries)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common_base::errors::RobustMQError;
+    use crate::storage::engine::engine_save_by_cluster;
+    use crate::storage::kv::KvStorage;
+    use crate::storage::namespace::{namespace_key, NamespaceStorage};
+    use crate::storage::rocksdb::DB_COLUMN_FAMILY_CLUSTER;
+    use crate::storage::test_tools::TempDir;
+    use super::{export_entries, import_batch, parse_export_line, ImportBatcher};
+
+    #[test]
+    fn export_order_and_import_batches() {
+        let dir = TempDir::new();
+        let rs = dir.rocksdb_engine();
+
+        NamespaceStorage::new(rs.clone()).set_limits("tenant", 10, 0, 0).unwrap();
+        let kv_storage = KvStorage::new(rs.clone());
+        kv_storage.set(namespace_key("tenant", "/a"), "v1".to_string(), 0, 3).unwrap();
+        kv_storage.set(namespace_key("tenant", "/b"), "v2".to_string(), 7, 4).unwrap();
+        engine_save_by_cluster(rs.clone(), "/clusters/mqtt/c1".to_string(), "c1").unwrap();
+
+        let mut lines = Vec::new();
+        let count = export_entries(&rs, |entry| {
+            lines.push(serde_json::to_string(&entry)?);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(count, 4);
+
+        let entries: Vec<_> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| parse_export_line(i as u64 + 1, line).unwrap())
+            .collect();
+        assert_eq!(entries[0].family, DB_COLUMN_FAMILY_CLUSTER);
+        assert!(!entries[0].is_importable());
+        assert_eq!(entries[1].namespace().unwrap().max_keys, 10);
+        assert_eq!(entries[2].kv_value(), Some("v1".to_string()));
+        assert_eq!(entries[2].mod_revision, 3);
+        // Keys attached to a lease are exported but not replayed
+        assert_eq!(entries[3].lease_id, 7);
+        assert!(!entries[3].is_importable());
+
+        let mut batcher = ImportBatcher::new(1);
+        assert!(batcher.push(entries[1].clone()).is_none());
+        assert_eq!(batcher.push(entries[2].clone()), Some(vec![entries[1].clone()]));
+        assert_eq!(batcher.push(entries[3].clone()), Some(vec![entries[2].clone()]));
+        assert_eq!(batcher.finish(), Some(vec![entries[3].clone()]));
+
+        let batch = import_batch(entries.clone());
+        assert_eq!(batch.namespaces.len(), 1);
+        assert_eq!(batch.kvs, vec![(namespace_key("tenant", "/a"), "v1".to_string())]);
+
+        match parse_export_line(9, "{\"family\":\"kv\"}") {
+            Err(RobustMQError::InvalidExportEntry(9, _)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -16,6 +16,11 @@ pub fn key_name_by_conf_state() -> String {
     "/raft/conf_state".to_string()
 }
 
+/// Addresses of the raft-rs peers, as of the last applied conf change.
+pub fn key_name_by_peers() -> String {
+    "/raft/peers".to_string()
+}
+
 pub fn key_name_by_entry(idx: u64) -> String {
     format!("/raft/entry/{}", idx)
 }
@@ -27,6 +32,32 @@ pub fn key_name_uncommit() -> String {
 pub fn key_name_snapshot() -> String {
     "/raft/snapshot".to_string()
 }
+
+pub fn key_name_raft_prefix() -> String {
+    "/raft/".to_string()
+}
+//</editor-fold>
+
+
+//<editor-fold desc="OpenRaft">
+pub fn key_openraft_last_applied() -> String {
+    "/openraft/last_applied_log_id".to_string()
+}
+
+pub fn key_openraft_last_membership() -> String {
+    "/openraft/last_membership".to_string()
+}
+
+pub fn key_openraft_prefix() -> String {
+    "/openraft/".to_string()
+}
+//</editor-fold>
+
+
+//<editor-fold desc="Schema">
+pub fn key_schema_version() -> String {
+    "/schema/version".to_string()
+}
 //</editor-fold>
 
 
@@ -65,6 +96,44 @@ pub fn key_resource_idempotent(cluster_name: &String, produce_id: &String, seq_n
 //</editor-fold>
 
 
+//<editor-fold desc="Namespace">
+pub fn key_namespace(namespace: &str) -> String {
+    format!("/namespace/{}", namespace)
+}
+
+pub fn key_namespace_prefix() -> String {
+    "/namespace/".to_string()
+}
+
+pub fn key_namespace_kv(namespace: &str, key: &str) -> String {
+    format!("/kv/{}/{}", namespace, key)
+}
+
+pub fn key_namespace_kv_prefix(namespace: &str) -> String {
+    format!("/kv/{}/", namespace)
+}
+
+pub fn key_kv_prefix() -> String {
+    "/kv/".to_string()
+}
+//</editor-fold>
+
+
+//<editor-fold desc="Lease">
+pub fn key_lease(lease_id: u64) -> String {
+    format!("/lease/{}", lease_id)
+}
+
+pub fn key_lease_prefix() -> String {
+    "/lease/".to_string()
+}
+
+pub fn key_lease_id_seq() -> String {
+    "/lease_id_seq".to_string()
+}
+//</editor-fold>
+
+
 //<editor-fold desc="Journal">
 #[allow(dead_code)]
 pub fn key_shard_prefix(cluster_name: &String) -> String {
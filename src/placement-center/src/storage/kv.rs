@@ -1,8 +1,51 @@
-use crate::storage::engine::{engine_delete_by_cluster, engine_exists_by_cluster, engine_get_by_cluster};
+use crate::storage::engine::{engine_batch_by_kv, engine_exists_by_kv, engine_get_by_kv, engine_prefix_page_by_kv, EngineBatchOp};
+use crate::storage::lease::LeaseStorage;
+use crate::storage::namespace::{KeyChange, NamespaceStorage};
 use crate::storage::rocksdb::RocksDBEngine;
+use crate::storage::StorageDataWrap;
 use common_base::errors::RobustMQError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Number of keys returned by a list request that does not set a limit.
+pub const DEFAULT_LIST_LIMIT: u32 = 100;
+/// Upper bound of the keys returned by a single list request.
+pub const MAX_LIST_LIMIT: u32 = 1000;
+
+/// A write that is applied together with others by `KvStorage::batch`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum KvBatchOp {
+    /// A non-zero `lease_id` attaches the key to that lease, which must exist
+    Set { key: String, value: String, lease_id: u64 },
+    Delete { key: String },
+}
+
+/// A condition on one key that a transaction checks before choosing its writes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KvCompare {
+    pub key: String,
+    pub target: KvCompareTarget,
+    pub result: KvCompareResult,
+}
+
+/// What the key is compared with. An absent key has an empty value and version 0.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum KvCompareTarget {
+    Value(String),
+    Version(u64),
+    Exists(bool),
+}
+
+/// How the current state of the key must relate to the target for the compare to hold.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum KvCompareResult {
+    Equal,
+    NotEqual,
+    Greater,
+    Less,
+}
+
 pub struct KvStorage {
     rocksdb_engine_handler: Arc<RocksDBEngine>,
 }
@@ -14,12 +57,16 @@ impl KvStorage {
         }
     }
 
-    pub fn set(&self, key: String, value: String) -> Result<(), RobustMQError> {
-        engine_delete_by_cluster(self.rocksdb_engine_handler.clone(), key)
+    /// Save the value of the key, `revision` is the raft log index of the write.
+    /// A non-zero `lease_id` attaches the key to that lease, and the key is detached from
+    /// the lease it had before.
+    pub fn set(&self, key: String, value: String, lease_id: u64, revision: u64) -> Result<(), RobustMQError> {
+        self.batch(vec![KvBatchOp::Set { key, value, lease_id }], revision)?;
+        Ok(())
     }
 
     pub fn get(&self, key: String) -> Result<Option<String>, RobustMQError> {
-        match engine_get_by_cluster(self.rocksdb_engine_handler.clone(), key) {
+        match self.get_data(key) {
             Ok(Some(data)) => match serde_json::from_slice::<String>(&data.data) {
                 Ok(data) => Ok(Some(data)),
                 Err(e) => Err(e.into()),
@@ -29,11 +76,306 @@ impl KvStorage {
         }
     }
 
+    /// Read the stored wrap of the key, including its revision metadata.
+    pub fn get_data(&self, key: String) -> Result<Option<StorageDataWrap>, RobustMQError> {
+        engine_get_by_kv(self.rocksdb_engine_handler.clone(), key)
+    }
+
+    /// Delete the key and detach it from its lease.
     pub fn delete(&self, key: String) -> Result<(), RobustMQError> {
-        engine_delete_by_cluster(self.rocksdb_engine_handler.clone(), key)
+        let previous = self.get_data(key.clone())?;
+        let mut ops = self.change_ops(&[KeyChange::new(&key, previous.as_ref(), None)])?;
+        ops.push(EngineBatchOp::Delete(key));
+        engine_batch_by_kv(self.rocksdb_engine_handler.clone(), ops)
     }
 
     pub fn exists(&self, key: String) -> Result<bool, RobustMQError> {
-        engine_exists_by_cluster(self.rocksdb_engine_handler.clone(), key)
+        engine_exists_by_kv(self.rocksdb_engine_handler.clone(), key)
+    }
+
+    /// List at most `limit` keys starting with `prefix` in key order, beginning after `start_after`.
+    /// Returns the keys with their wraps and the key to continue from, which is empty once
+    /// the whole prefix has been listed.
+    pub fn list(
+        &self,
+        prefix: String,
+        start_after: String,
+        limit: usize,
+    ) -> Result<(Vec<(String, StorageDataWrap)>, String), RobustMQError> {
+        let (results, more) = engine_prefix_page_by_kv(
+            self.rocksdb_engine_handler.clone(),
+            prefix,
+            start_after,
+            limit,
+        )?;
+        let next_token = match results.last() {
+            Some((key, _)) if more => key.clone(),
+            _ => "".to_string(),
+        };
+        Ok((results, next_token))
+    }
+
+    /// Apply the writes in order as a single RocksDB write batch, so either all of them are
+    /// stored or none is. Every written key gets `revision` as its mod revision.
+    /// Returns the writes that changed something, deletes of absent keys are left out.
+    pub fn batch(&self, ops: Vec<KvBatchOp>, revision: u64) -> Result<Vec<KvBatchOp>, RobustMQError> {
+        let (engine_ops, applied) = self.batch_ops(ops, revision)?;
+        engine_batch_by_kv(self.rocksdb_engine_handler.clone(), engine_ops)?;
+        Ok(applied)
+    }
+
+    /// Build the engine writes of `batch` without applying them, so the caller can add
+    /// its own writes to the same batch.
+    pub fn batch_ops(
+        &self,
+        ops: Vec<KvBatchOp>,
+        revision: u64,
+    ) -> Result<(Vec<EngineBatchOp>, Vec<KvBatchOp>), RobustMQError> {
+        // The state of the keys already written by this batch, None means deleted
+        let mut staged: HashMap<String, Option<StorageDataWrap>> = HashMap::new();
+        let mut engine_ops = Vec::with_capacity(ops.len());
+        let mut applied = Vec::with_capacity(ops.len());
+        let mut changes = Vec::with_capacity(ops.len());
+
+        for op in ops {
+            match &op {
+                KvBatchOp::Set { key, value, lease_id } => {
+                    let previous = match staged.get(key) {
+                        Some(data) => data.clone(),
+                        None => self.get_data(key.clone())?,
+                    };
+                    let content = serde_json::to_vec(value)?;
+                    let data = StorageDataWrap::new_revision(content, revision, *lease_id, previous.as_ref());
+                    changes.push(KeyChange::new(key, previous.as_ref(), Some(&data)));
+                    staged.insert(key.clone(), Some(data.clone()));
+                    engine_ops.push(EngineBatchOp::Save(key.clone(), data));
+                }
+                KvBatchOp::Delete { key } => {
+                    let previous = match staged.get(key) {
+                        Some(data) => data.clone(),
+                        None => self.get_data(key.clone())?,
+                    };
+                    if previous.is_none() {
+                        continue;
+                    }
+                    changes.push(KeyChange::new(key, previous.as_ref(), None));
+                    staged.insert(key.clone(), None);
+                    engine_ops.push(EngineBatchOp::Delete(key.clone()));
+                }
+            }
+            applied.push(op);
+        }
+
+        engine_ops.extend(self.change_ops(&changes)?);
+        Ok((engine_ops, applied))
+    }
+
+    // Checks the limits of the namespaces of the changed keys and updates their usage,
+    // then moves the keys between the key lists of their leases
+    fn change_ops(&self, changes: &[KeyChange]) -> Result<Vec<EngineBatchOp>, RobustMQError> {
+        let mut ops = NamespaceStorage::new(self.rocksdb_engine_handler.clone()).usage_ops(changes)?;
+        ops.extend(LeaseStorage::new(self.rocksdb_engine_handler.clone()).key_ops(changes)?);
+        Ok(ops)
+    }
+
+    /// Whether every compare holds, evaluated against the stored keys.
+    pub fn compare_all(&self, compares: &[KvCompare]) -> Result<bool, RobustMQError> {
+        for compare in compares {
+            if !self.compare(compare)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn compare(&self, compare: &KvCompare) -> Result<bool, RobustMQError> {
+        let data = self.get_data(compare.key.clone())?;
+        let ordering = match &compare.target {
+            KvCompareTarget::Value(value) => {
+                let current_value = match &data {
+                    Some(data) => serde_json::from_slice::<String>(&data.data)?,
+                    None => "".to_string(),
+                };
+                current_value.as_str().cmp(value.as_str())
+            }
+            KvCompareTarget::Version(version) => {
+                let current_version = match &data {
+                    Some(data) => data.version,
+                    None => 0,
+                };
+                current_version.cmp(version)
+            }
+            KvCompareTarget::Exists(exists) => data.is_some().cmp(exists),
+        };
+
+        Ok(match compare.result {
+            KvCompareResult::Equal => ordering.is_eq(),
+            KvCompareResult::NotEqual => ordering.is_ne(),
+            KvCompareResult::Greater => ordering.is_gt(),
+            KvCompareResult::Less => ordering.is_lt(),
+        })
     }
-}
\ No newline at end of file
+
+    /// Replace the value of the key with `new_value` only if its current value equals
+    /// `expected_value`. An empty `expected_value` matches a key that does not exist.
+    /// Returns whether the swap happened and the value of the key after the call.
+    pub fn compare_and_swap(
+        &self,
+        key: String,
+        expected_value: String,
+        new_value: String,
+        revision: u64,
+    ) -> Result<(bool, String), RobustMQError> {
+        let current_value = match self.get(key.clone())? {
+            Some(value) => value,
+            None => "".to_string(),
+        };
+
+        if current_value != expected_value {
+            return Ok((false, current_value));
+        }
+
+        self.set(key, new_value.clone(), 0, revision)?;
+        Ok((true, new_value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common_base::errors::RobustMQError;
+    use crate::storage::kv::{KvBatchOp, KvStorage};
+    use crate::storage::lease::{Lease, LeaseStorage};
+    use crate::storage::test_tools::TempDir;
+
+    #[test]
+    fn compare_and_swap() {
+        let dir = TempDir::new();
+        let kv_storage = KvStorage::new(dir.rocksdb_engine());
+        let key = "/test/cas".to_string();
+
+        let (success, current) = kv_storage
+            .compare_and_swap(key.clone(), "".to_string(), "broker-1".to_string(), 1)
+            .unwrap();
+        assert!(success);
+        assert_eq!(current, "broker-1");
+
+        let (success, current) = kv_storage
+            .compare_and_swap(key.clone(), "".to_string(), "broker-2".to_string(), 2)
+            .unwrap();
+        assert!(!success);
+        assert_eq!(current, "broker-1");
+
+        let (success, current) = kv_storage
+            .compare_and_swap(key.clone(), "broker-1".to_string(), "broker-2".to_string(), 3)
+            .unwrap();
+        assert!(success);
+        assert_eq!(current, "broker-2");
+        assert_eq!(kv_storage.get(key).unwrap().unwrap(), "broker-2");
+    }
+
+    #[test]
+    fn revision_metadata() {
+        let dir = TempDir::new();
+        let kv_storage = KvStorage::new(dir.rocksdb_engine());
+        let key = "/test/revision".to_string();
+
+        kv_storage.set(key.clone(), "v1".to_string(), 0, 10).unwrap();
+        let data = kv_storage.get_data(key.clone()).unwrap().unwrap();
+        assert_eq!(data.create_revision, 10);
+        assert_eq!(data.mod_revision, 10);
+        assert_eq!(data.version, 1);
+
+        kv_storage.set(key.clone(), "v2".to_string(), 0, 15).unwrap();
+        let data = kv_storage.get_data(key.clone()).unwrap().unwrap();
+        assert_eq!(data.create_revision, 10);
+        assert_eq!(data.mod_revision, 15);
+        assert_eq!(data.version, 2);
+
+        kv_storage.delete(key.clone()).unwrap();
+        kv_storage.set(key.clone(), "v3".to_string(), 0, 20).unwrap();
+        let data = kv_storage.get_data(key).unwrap().unwrap();
+        assert_eq!(data.create_revision, 20);
+        assert_eq!(data.version, 1);
+    }
+
+    #[test]
+    fn list_pagination() {
+        let dir = TempDir::new();
+        let kv_storage = KvStorage::new(dir.rocksdb_engine());
+        for i in 1..6 {
+            kv_storage
+                .set(format!("/test/list/{}", i), format!("v{}", i), 0, i)
+                .unwrap();
+        }
+
+        let (kvs, token) = kv_storage
+            .list("/test/list/".to_string(), "".to_string(), 3)
+            .unwrap();
+        assert_eq!(kvs.len(), 3);
+        assert_eq!(token, "/test/list/3");
+
+        let (kvs, token) = kv_storage
+            .list("/test/list/".to_string(), token, 3)
+            .unwrap();
+        assert_eq!(kvs.len(), 2);
+        assert_eq!(kvs[0].0, "/test/list/4");
+        assert!(token.is_empty());
+    }
+
+    #[test]
+    fn batch_write() {
+        let dir = TempDir::new();
+        let kv_storage = KvStorage::new(dir.rocksdb_engine());
+
+        let applied = kv_storage
+            .batch(
+                vec![
+                    KvBatchOp::Set { key: "/test/batch/1".to_string(), value: "v1".to_string(), lease_id: 0 },
+                    KvBatchOp::Set { key: "/test/batch/1".to_string(), value: "v2".to_string(), lease_id: 0 },
+                    KvBatchOp::Delete { key: "/test/batch/2".to_string() },
+                ],
+                7,
+            )
+            .unwrap();
+        assert_eq!(applied.len(), 2);
+
+        let data = kv_storage.get_data("/test/batch/1".to_string()).unwrap().unwrap();
+        assert_eq!(data.mod_revision, 7);
+        assert_eq!(data.version, 2);
+        assert_eq!(kv_storage.get("/test/batch/1".to_string()).unwrap().unwrap(), "v2");
+    }
+
+    #[test]
+    fn lease_key_lists() {
+        let dir = TempDir::new();
+        let rocksdb_engine = dir.rocksdb_engine();
+        let kv_storage = KvStorage::new(rocksdb_engine.clone());
+        let lease_storage = LeaseStorage::new(rocksdb_engine);
+        let key = "/test/lease".to_string();
+        for lease_id in [1, 2] {
+            lease_storage
+                .save(Lease { lease_id, ttl: 10, keys: Vec::new(), create_time: 0 })
+                .unwrap();
+        }
+        let keys = |lease_id| lease_storage.get(lease_id).unwrap().unwrap().keys;
+
+        kv_storage.set(key.clone(), "v1".to_string(), 1, 1).unwrap();
+        assert_eq!(keys(1), vec![key.clone()]);
+
+        // Moving the key to another lease detaches it from the first one
+        kv_storage.set(key.clone(), "v2".to_string(), 2, 2).unwrap();
+        assert!(keys(1).is_empty());
+        assert_eq!(keys(2), vec![key.clone()]);
+
+        // A missing lease rejects the whole write
+        match kv_storage.set(key.clone(), "v3".to_string(), 3, 3) {
+            Err(RobustMQError::LeaseNotFound(lease_id)) => assert_eq!(lease_id, 3),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(kv_storage.get(key.clone()).unwrap().unwrap(), "v2");
+        assert_eq!(keys(2), vec![key.clone()]);
+
+        kv_storage.delete(key).unwrap();
+        assert!(keys(2).is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use common_base::errors::RobustMQError;
+use common_base::tools::now_second;
+use crate::storage::engine::{engine_delete_by_kv, engine_get_by_kv, engine_prefix_list_by_kv, engine_save_by_kv, EngineBatchOp};
+use crate::storage::keys::{key_lease, key_lease_id_seq, key_lease_prefix};
+use crate::storage::namespace::KeyChange;
+use crate::storage::rocksdb::RocksDBEngine;
+use crate::storage::StorageDataWrap;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Lease {
+    pub lease_id: u64,
+    /// Time to live in seconds
+    pub ttl: u64,
+    /// Keys that were attached to the lease
+    pub keys: Vec<String>,
+    pub create_time: u64,
+}
+
+/// Whether the error is a write rejected because of a lease rather than a storage failure.
+pub fn is_lease_rejection(e: &RobustMQError) -> bool {
+    matches!(
+        e,
+        RobustMQError::LeaseNotFound(_) | RobustMQError::LeaseAlreadyExists(_)
+    )
+}
+
+pub struct LeaseStorage {
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+}
+
+impl LeaseStorage {
+    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>) -> Self {
+        LeaseStorage {
+            rocksdb_engine_handler,
+        }
+    }
+
+    pub fn save(&self, lease: Lease) -> Result<(), RobustMQError> {
+        engine_save_by_kv(
+            self.rocksdb_engine_handler.clone(),
+            key_lease(lease.lease_id),
+            lease,
+        )
+    }
+
+    pub fn get(&self, lease_id: u64) -> Result<Option<Lease>, RobustMQError> {
+        match engine_get_by_kv(self.rocksdb_engine_handler.clone(), key_lease(lease_id))? {
+            Some(data) => Ok(Some(serde_json::from_slice::<Lease>(&data.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn delete(&self, lease_id: u64) -> Result<(), RobustMQError> {
+        engine_delete_by_kv(self.rocksdb_engine_handler.clone(), key_lease(lease_id))
+    }
+
+    pub fn list(&self) -> Result<Vec<Lease>, RobustMQError> {
+        let mut results = Vec::new();
+        for data in engine_prefix_list_by_kv(self.rocksdb_engine_handler.clone(), key_lease_prefix())? {
+            results.push(serde_json::from_slice::<Lease>(&data.data)?);
+        }
+        Ok(results)
+    }
+
+    /// The largest lease id that was granted, allocated ids are taken above it.
+    pub fn last_lease_id(&self) -> Result<u64, RobustMQError> {
+        match engine_get_by_kv(self.rocksdb_engine_handler.clone(), key_lease_id_seq())? {
+            Some(data) => Ok(serde_json::from_slice::<u64>(&data.data)?),
+            None => Ok(0),
+        }
+    }
+
+    /// The writes that create the lease, to be added to a larger batch, and the id of the lease.
+    /// A lease id of 0 is allocated from the largest id granted so far, so every node that
+    /// applies the grant allocates the same id.
+    pub fn grant_op(&self, lease_id: u64, ttl: u64) -> Result<(u64, Vec<EngineBatchOp>), RobustMQError> {
+        let last_lease_id = self.last_lease_id()?;
+        let lease_id = if lease_id == 0 {
+            let mut lease_id = last_lease_id + 1;
+            while self.get(lease_id)?.is_some() {
+                lease_id += 1;
+            }
+            lease_id
+        } else if self.get(lease_id)?.is_some() {
+            return Err(RobustMQError::LeaseAlreadyExists(lease_id));
+        } else {
+            lease_id
+        };
+
+        let lease = Lease {
+            lease_id,
+            ttl,
+            keys: Vec::new(),
+            create_time: now_second(),
+        };
+        let mut ops = vec![EngineBatchOp::Save(
+            key_lease(lease_id),
+            StorageDataWrap::new(serde_json::to_vec(&lease)?),
+        )];
+        if lease_id > last_lease_id {
+            ops.push(EngineBatchOp::Save(
+                key_lease_id_seq(),
+                StorageDataWrap::new(serde_json::to_vec(&lease_id)?),
+            ));
+        }
+        Ok((lease_id, ops))
+    }
+
+    /// The keys that are still attached to the lease and are deleted when it is revoked.
+    /// Keys that were rewritten without the lease after being attached are left out.
+    pub fn attached_keys(&self, lease_id: u64) -> Result<Vec<String>, RobustMQError> {
+        let lease = match self.get(lease_id)? {
+            Some(lease) => lease,
+            None => return Err(RobustMQError::LeaseNotFound(lease_id)),
+        };
+        let mut results = Vec::with_capacity(lease.keys.len());
+        for key in lease.keys {
+            if let Some(data) = engine_get_by_kv(self.rocksdb_engine_handler.clone(), key.clone())? {
+                if data.lease_id == lease_id {
+                    results.push(key);
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Check the leases of the changed keys and return the writes that move the keys between
+    /// their key lists, to be added to the same batch as the keys. A key is detached from the
+    /// lease it had before the write and attached to the lease it has after it.
+    pub fn key_ops(&self, changes: &[KeyChange]) -> Result<Vec<EngineBatchOp>, RobustMQError> {
+        // The leases touched by the batch, None for a lease that no longer exists
+        let mut leases: BTreeMap<u64, Option<Lease>> = BTreeMap::new();
+        for change in changes {
+            if change.previous_lease_id == change.current_lease_id {
+                continue;
+            }
+
+            if change.previous_lease_id > 0 {
+                if let Some(lease) = self.staged(&mut leases, change.previous_lease_id)? {
+                    lease.keys.retain(|key| *key != change.key);
+                }
+            }
+
+            if change.current_lease_id > 0 {
+                let lease = match self.staged(&mut leases, change.current_lease_id)? {
+                    Some(lease) => lease,
+                    None => return Err(RobustMQError::LeaseNotFound(change.current_lease_id)),
+                };
+                if !lease.keys.contains(&change.key) {
+                    lease.keys.push(change.key.clone());
+                }
+            }
+        }
+
+        let mut ops = Vec::with_capacity(leases.len());
+        for lease in leases.into_values().flatten() {
+            ops.push(EngineBatchOp::Save(
+                key_lease(lease.lease_id),
+                StorageDataWrap::new(serde_json::to_vec(&lease)?),
+            ));
+        }
+        Ok(ops)
+    }
+
+    fn staged<'a>(
+        &self,
+        leases: &'a mut BTreeMap<u64, Option<Lease>>,
+        lease_id: u64,
+    ) -> Result<Option<&'a mut Lease>, RobustMQError> {
+        if !leases.contains_key(&lease_id) {
+            leases.insert(lease_id, self.get(lease_id)?);
+        }
+        Ok(leases.get_mut(&lease_id).and_then(|lease| lease.as_mut()))
+    }
+}
@@ -6,11 +6,34 @@ pub mod kv;
 pub mod engine;
 pub mod raft;
 pub mod keys;
+pub mod lease;
+pub mod namespace;
+pub mod schema;
+pub mod backup;
+pub mod export;
+pub mod encryption;
+pub mod compression;
+pub mod compaction;
+pub mod state_machine;
+#[cfg(test)]
+pub mod test_tools;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StorageDataWrap {
     pub data: Vec<u8>,
     pub create_time: u64,
+    /// Raft log index of the write that created the key.
+    #[serde(default)]
+    pub create_revision: u64,
+    /// Raft log index of the write that last modified the key.
+    #[serde(default)]
+    pub mod_revision: u64,
+    /// Number of writes to the key since it was created.
+    #[serde(default)]
+    pub version: u64,
+    /// Lease the key is attached to, 0 means the key never expires.
+    #[serde(default)]
+    pub lease_id: u64,
 }
 
 impl StorageDataWrap {
@@ -18,6 +41,38 @@ impl StorageDataWrap {
         StorageDataWrap {
             data,
             create_time: now_second(),
+            create_revision: 0,
+            mod_revision: 0,
+            version: 0,
+            lease_id: 0,
+        }
+    }
+
+    /// Build the wrap for a write at `revision`, carrying over the creation
+    /// metadata of the previous value of the key if there is one.
+    pub fn new_revision(
+        data: Vec<u8>,
+        revision: u64,
+        lease_id: u64,
+        previous: Option<&StorageDataWrap>,
+    ) -> Self {
+        match previous {
+            Some(previous) => StorageDataWrap {
+                data,
+                create_time: previous.create_time,
+                create_revision: previous.create_revision,
+                mod_revision: revision,
+                version: previous.version + 1,
+                lease_id,
+            },
+            None => StorageDataWrap {
+                data,
+                create_time: now_second(),
+                create_revision: revision,
+                mod_revision: revision,
+                version: 1,
+                lease_id,
+            },
         }
     }
 }
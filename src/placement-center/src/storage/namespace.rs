@@ -0,0 +1,392 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use rocksdb::WriteBatch;
+use serde::{Deserialize, Serialize};
+use common_base::errors::RobustMQError;
+use common_base::tools::now_second;
+use crate::storage::engine::{engine_batch_by_kv, engine_get_by_kv, engine_prefix_list_by_kv, EngineBatchOp};
+use crate::storage::keys::{key_kv_prefix, key_lease_prefix, key_namespace, key_namespace_kv, key_namespace_kv_prefix, key_namespace_prefix};
+use crate::storage::lease::Lease;
+use crate::storage::rocksdb::RocksDBEngine;
+use crate::storage::StorageDataWrap;
+
+/// Namespace of the requests that do not name one. It exists without being created.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Upper bound of the length of a namespace name.
+pub const MAX_NAMESPACE_LEN: usize = 128;
+
+/// A tenant of the KV service. Every key of a namespace is stored under
+/// `/kv/{namespace}/`, and the usage of the namespace is updated in the same
+/// write batch as the keys, so the limits are enforced when an entry is applied.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Namespace {
+    pub name: String,
+    /// Limits of the namespace, 0 means unlimited
+    pub max_keys: u64,
+    pub max_bytes: u64,
+    pub max_value_size: u64,
+    /// Number of keys stored in the namespace
+    pub key_count: u64,
+    /// Total size of the keys and values stored in the namespace
+    pub total_bytes: u64,
+    pub create_time: u64,
+}
+
+impl Namespace {
+    pub fn new(name: &str) -> Self {
+        Namespace {
+            name: name.to_string(),
+            max_keys: 0,
+            max_bytes: 0,
+            max_value_size: 0,
+            key_count: 0,
+            total_bytes: 0,
+            create_time: now_second(),
+        }
+    }
+}
+
+/// The change of one key written by a batch, used to update the usage of its namespace
+/// and the key lists of the leases it moves between.
+pub struct KeyChange {
+    pub key: String,
+    previous_size: Option<u64>,
+    current_size: Option<u64>,
+    /// Leases of the key before and after the write, 0 when it has none
+    pub previous_lease_id: u64,
+    pub current_lease_id: u64,
+}
+
+impl KeyChange {
+    /// `previous` and `current` are the values of the key before and after the write,
+    /// None when the key does not exist.
+    pub fn new(key: &str, previous: Option<&StorageDataWrap>, current: Option<&StorageDataWrap>) -> Self {
+        KeyChange {
+            key: key.to_string(),
+            previous_size: previous.map(|data| data.data.len() as u64),
+            current_size: current.map(|data| data.data.len() as u64),
+            previous_lease_id: previous.map(|data| data.lease_id).unwrap_or(0),
+            current_lease_id: current.map(|data| data.lease_id).unwrap_or(0),
+        }
+    }
+}
+
+/// The name of the namespace of a request, an empty name is the default namespace.
+pub fn namespace_name(namespace: &str) -> Result<String, RobustMQError> {
+    if namespace.is_empty() {
+        return Ok(DEFAULT_NAMESPACE.to_string());
+    }
+    if namespace.len() > MAX_NAMESPACE_LEN || namespace.contains('/') {
+        return Err(RobustMQError::InvalidNamespace(namespace.to_string()));
+    }
+    Ok(namespace.to_string())
+}
+
+/// The storage key of a key of the namespace.
+pub fn namespace_key(namespace: &str, key: &str) -> String {
+    key_namespace_kv(namespace, key)
+}
+
+/// The key of the namespace a storage key was built from by `namespace_key`.
+pub fn namespace_user_key(namespace: &str, storage_key: &str) -> String {
+    let prefix = key_namespace_kv_prefix(namespace);
+    match storage_key.strip_prefix(&prefix) {
+        Some(key) => key.to_string(),
+        None => storage_key.to_string(),
+    }
+}
+
+/// The namespace of a storage key, None for keys that do not belong to a namespace.
+pub fn namespace_of(storage_key: &str) -> Option<&str> {
+    let rest = storage_key.strip_prefix(key_kv_prefix().as_str())?;
+    rest.split_once('/').map(|(namespace, _)| namespace)
+}
+
+/// Whether the error is a write rejected by a namespace rather than a storage failure.
+pub fn is_namespace_rejection(e: &RobustMQError) -> bool {
+    matches!(
+        e,
+        RobustMQError::NamespaceNotFound(_)
+            | RobustMQError::NamespaceQuotaExceeded(..)
+            | RobustMQError::InvalidNamespace(_)
+    )
+}
+
+pub struct NamespaceStorage {
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+}
+
+impl NamespaceStorage {
+    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>) -> Self {
+        NamespaceStorage {
+            rocksdb_engine_handler,
+        }
+    }
+
+    /// The namespace with its limits and usage. The default namespace is returned even
+    /// before anything has been written to it.
+    pub fn get(&self, name: &str) -> Result<Option<Namespace>, RobustMQError> {
+        match engine_get_by_kv(self.rocksdb_engine_handler.clone(), key_namespace(name))? {
+            Some(data) => Ok(Some(serde_json::from_slice::<Namespace>(&data.data)?)),
+            None if name == DEFAULT_NAMESPACE => Ok(Some(Namespace::new(DEFAULT_NAMESPACE))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<Namespace>, RobustMQError> {
+        let mut results = Vec::new();
+        for data in engine_prefix_list_by_kv(self.rocksdb_engine_handler.clone(), key_namespace_prefix())? {
+            results.push(serde_json::from_slice::<Namespace>(&data.data)?);
+        }
+        Ok(results)
+    }
+
+    /// Create the namespace or replace its limits. Lowering a limit below the current usage
+    /// keeps the stored keys, but rejects every write that would grow the namespace further.
+    pub fn set_limits(
+        &self,
+        name: &str,
+        max_keys: u64,
+        max_bytes: u64,
+        max_value_size: u64,
+    ) -> Result<(), RobustMQError> {
+        let ops = self.set_limits_ops(name, max_keys, max_bytes, max_value_size)?;
+        engine_batch_by_kv(self.rocksdb_engine_handler.clone(), ops)
+    }
+
+    /// The writes of `set_limits`, to be added to a larger batch.
+    pub fn set_limits_ops(
+        &self,
+        name: &str,
+        max_keys: u64,
+        max_bytes: u64,
+        max_value_size: u64,
+    ) -> Result<Vec<EngineBatchOp>, RobustMQError> {
+        let mut namespace = match self.get(name)? {
+            Some(namespace) => namespace,
+            None => Namespace::new(name),
+        };
+        namespace.max_keys = max_keys;
+        namespace.max_bytes = max_bytes;
+        namespace.max_value_size = max_value_size;
+        Ok(vec![namespace_save_op(&namespace)?])
+    }
+
+    /// Check the changes of a write batch against the limits of their namespaces and return
+    /// the writes that record the new usage, to be added to the same batch.
+    pub fn usage_ops(&self, changes: &[KeyChange]) -> Result<Vec<EngineBatchOp>, RobustMQError> {
+        // The namespaces touched by the batch, with their key count and size before it
+        let mut namespaces: BTreeMap<String, (Namespace, u64, u64)> = BTreeMap::new();
+        for change in changes {
+            let name = match namespace_of(&change.key) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            if !namespaces.contains_key(name) {
+                let namespace = match self.get(name)? {
+                    Some(namespace) => namespace,
+                    None => return Err(RobustMQError::NamespaceNotFound(name.to_string())),
+                };
+                let (key_count, total_bytes) = (namespace.key_count, namespace.total_bytes);
+                namespaces.insert(name.to_string(), (namespace, key_count, total_bytes));
+            }
+            let (namespace, _, _) = namespaces.get_mut(name).unwrap();
+
+            let key_size = namespace_user_key(name, &change.key).len() as u64;
+            if let Some(size) = change.previous_size {
+                namespace.key_count = namespace.key_count.saturating_sub(1);
+                namespace.total_bytes = namespace.total_bytes.saturating_sub(key_size + size);
+            }
+            if let Some(size) = change.current_size {
+                if namespace.max_value_size > 0 && size > namespace.max_value_size {
+                    return Err(RobustMQError::NamespaceQuotaExceeded(
+                        name.to_string(),
+                        "value size".to_string(),
+                        namespace.max_value_size,
+                    ));
+                }
+                namespace.key_count += 1;
+                namespace.total_bytes += key_size + size;
+            }
+        }
+
+        let mut ops = Vec::with_capacity(namespaces.len());
+        for (name, (namespace, key_count, total_bytes)) in namespaces {
+            // Only a batch that grows the namespace can exceed its limits
+            if namespace.max_keys > 0
+                && namespace.key_count > namespace.max_keys
+                && namespace.key_count > key_count
+            {
+                return Err(RobustMQError::NamespaceQuotaExceeded(
+                    name,
+                    "key count".to_string(),
+                    namespace.max_keys,
+                ));
+            }
+            if namespace.max_bytes > 0
+                && namespace.total_bytes > namespace.max_bytes
+                && namespace.total_bytes > total_bytes
+            {
+                return Err(RobustMQError::NamespaceQuotaExceeded(
+                    name,
+                    "total bytes".to_string(),
+                    namespace.max_bytes,
+                ));
+            }
+            ops.push(namespace_save_op(&namespace)?);
+        }
+        Ok(ops)
+    }
+}
+
+// Prefixes of the keys written by the placement center itself rather than by the KV service
+const INTERNAL_KEY_PREFIXES: [&str; 11] = [
+    "/raft/",
+    "/openraft/",
+    "/schema/",
+    "/clusters/",
+    "/config/",
+    "/idempotent/",
+    "/journal/",
+    "/mqtt/",
+    "/lease/",
+    "/namespace/",
+    "/kv/",
+];
+
+/// Schema migration 1: keys written by the KV service before namespaces existed were stored
+/// under the key chosen by the client. They are moved into the default namespace, together
+/// with the lease key lists that refer to them, and counted in its usage.
+pub fn migrate_kv_to_default_namespace(
+    rocksdb_engine: &RocksDBEngine,
+    batch: &mut WriteBatch,
+) -> Result<(), RobustMQError> {
+    let cf = rocksdb_engine.cf_cluster();
+    let mut namespace = match rocksdb_engine.read::<StorageDataWrap>(cf, &key_namespace(DEFAULT_NAMESPACE)) {
+        Ok(Some(data)) => serde_json::from_slice::<Namespace>(&data.data)?,
+        Ok(None) => Namespace::new(DEFAULT_NAMESPACE),
+        Err(e) => return Err(RobustMQError::CommonError(e)),
+    };
+
+    let mut moved = HashMap::new();
+    let mut leases = Vec::new();
+    for (key, value) in rocksdb_engine.read_all_consistent(cf) {
+        if key.starts_with(&key_lease_prefix()) {
+            leases.push((key, value));
+            continue;
+        }
+        if INTERNAL_KEY_PREFIXES.iter().any(|prefix| key.starts_with(prefix)) {
+            continue;
+        }
+        // Values of the KV service are always wrapped, anything else is not ours to move
+        let data = match serde_json::from_slice::<StorageDataWrap>(&value) {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+
+        let new_key = namespace_key(DEFAULT_NAMESPACE, &key);
+        namespace.key_count += 1;
+        namespace.total_bytes += (key.len() + data.data.len()) as u64;
+        batch.put_cf(cf, &new_key, rocksdb_engine.encrypt(value)?);
+        batch.delete_cf(cf, &key);
+        moved.insert(key, new_key);
+    }
+
+    for (key, value) in leases {
+        let mut data = serde_json::from_slice::<StorageDataWrap>(&value)?;
+        let mut lease = serde_json::from_slice::<Lease>(&data.data)?;
+        if !lease.keys.iter().any(|lease_key| moved.contains_key(lease_key)) {
+            continue;
+        }
+        for lease_key in lease.keys.iter_mut() {
+            if let Some(new_key) = moved.get(lease_key) {
+                *lease_key = new_key.clone();
+            }
+        }
+        data.data = serde_json::to_vec(&lease)?;
+        batch.put_cf(cf, key, rocksdb_engine.encrypt(serde_json::to_vec(&data)?)?);
+    }
+
+    let data = StorageDataWrap::new(serde_json::to_vec(&namespace)?);
+    batch.put_cf(cf, key_namespace(DEFAULT_NAMESPACE), rocksdb_engine.encrypt(serde_json::to_vec(&data)?)?);
+    Ok(())
+}
+
+fn namespace_save_op(namespace: &Namespace) -> Result<EngineBatchOp, RobustMQError> {
+    Ok(EngineBatchOp::Save(
+        key_namespace(&namespace.name),
+        StorageDataWrap::new(serde_json::to_vec(namespace)?),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use common_base::errors::RobustMQError;
+    use crate::storage::kv::{KvBatchOp, KvStorage};
+    use crate::storage::test_tools::TempDir;
+    use super::{namespace_key, namespace_name, namespace_of, namespace_user_key, NamespaceStorage, DEFAULT_NAMESPACE};
+
+    #[test]
+    fn namespace_keys() {
+        assert_eq!(namespace_name("").unwrap(), DEFAULT_NAMESPACE);
+        assert!(namespace_name("tenant/a").is_err());
+
+        let key = namespace_key("tenant-a", "/broker/1");
+        assert_eq!(key, "/kv/tenant-a//broker/1");
+        assert_eq!(namespace_of(&key), Some("tenant-a"));
+        assert_eq!(namespace_user_key("tenant-a", &key), "/broker/1");
+        assert_eq!(namespace_of("/lease/1"), None);
+    }
+
+    #[test]
+    fn namespace_quota() {
+        let dir = TempDir::new();
+        let rocksdb_engine_handler = dir.rocksdb_engine();
+        let kv_storage = KvStorage::new(rocksdb_engine_handler.clone());
+        let namespace_storage = NamespaceStorage::new(rocksdb_engine_handler);
+
+        // A namespace has to be created before keys are written to it
+        let key = |i: u32| namespace_key("tenant-quota", &format!("k{}", i));
+        match kv_storage.set(key(1), "v".to_string(), 0, 1) {
+            Err(RobustMQError::NamespaceNotFound(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        namespace_storage.set_limits("tenant-quota", 2, 0, 16).unwrap();
+        kv_storage.set(key(1), "v1".to_string(), 0, 2).unwrap();
+        kv_storage.set(key(2), "v2".to_string(), 0, 3).unwrap();
+        // Overwriting a key does not count as a new key
+        kv_storage.set(key(2), "v22".to_string(), 0, 4).unwrap();
+        match kv_storage.set(key(3), "v3".to_string(), 0, 5) {
+            Err(RobustMQError::NamespaceQuotaExceeded(_, _, 2)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match kv_storage.set(key(1), "a value that is too long".to_string(), 0, 6) {
+            Err(RobustMQError::NamespaceQuotaExceeded(_, _, 16)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let namespace = namespace_storage.get("tenant-quota").unwrap().unwrap();
+        assert_eq!(namespace.key_count, 2);
+        // Keys of 2 bytes, values stored as JSON strings
+        assert_eq!(namespace.total_bytes, 2 + 4 + 2 + 5);
+
+        // A batch is rejected as a whole
+        let ops = vec![
+            KvBatchOp::Delete { key: key(1) },
+            KvBatchOp::Set { key: key(3), value: "v3".to_string(), lease_id: 0 },
+            KvBatchOp::Set { key: key(4), value: "v4".to_string(), lease_id: 0 },
+        ];
+        assert!(kv_storage.batch(ops, 7).is_err());
+        assert!(kv_storage.get(key(1)).unwrap().is_some());
+
+        kv_storage
+            .batch(vec![KvBatchOp::Delete { key: key(1) }, KvBatchOp::Delete { key: key(2) }], 8)
+            .unwrap();
+        let namespace = namespace_storage.get("tenant-quota").unwrap().unwrap();
+        assert_eq!(namespace.key_count, 0);
+        assert_eq!(namespace.total_bytes, 0);
+    }
+}
@@ -7,8 +7,10 @@ use prost::Message;
 use raft::prelude::{ConfState, Entry, HardState, Snapshot, SnapshotMetadata};
 use raft::{RaftState, Result as RaftResult, StorageError};
 use raft::Error;
-use crate::storage::keys::{key_name_by_conf_state, key_name_by_entry, key_name_by_first_index, key_name_by_hard_state, key_name_by_last_index, key_name_snapshot, key_name_uncommit};
-use crate::storage::rocksdb::RocksDBEngine;
+use crate::storage::compression::{compress_snapshot, decompress_snapshot};
+use crate::raft::node::BrokerNode;
+use crate::storage::keys::{key_name_by_conf_state, key_name_by_entry, key_name_by_first_index, key_name_by_hard_state, key_name_by_last_index, key_name_by_peers, key_name_snapshot, key_name_uncommit};
+use crate::storage::rocksdb::{RocksDBEngine, DB_COLUMN_FAMILY_RAFT};
 
 
 pub struct RaftMachineStorage {
@@ -37,7 +39,7 @@ impl RaftMachineStorage {
         let key = key_name_by_conf_state();
         let value = ConfState::encode_to_vec(&cs);
         self.rocksdb_engine_handler
-            .write(self.rocksdb_engine_handler.cf_cluster(), &key, &value)
+            .write(self.rocksdb_engine_handler.cf_raft(), &key, &value)
     }
 
     /// Return RaftState
@@ -55,7 +57,7 @@ impl RaftMachineStorage {
         let key = key_name_by_hard_state();
         let value = self
             .rocksdb_engine_handler
-            .read::<Vec<u8>>(self.rocksdb_engine_handler.cf_cluster(), &key)
+            .read::<Vec<u8>>(self.rocksdb_engine_handler.cf_raft(), &key)
             .unwrap();
         if value == None {
             HardState::default()
@@ -69,7 +71,7 @@ impl RaftMachineStorage {
         let key = key_name_by_conf_state();
         let value = self
             .rocksdb_engine_handler
-            .read::<Vec<u8>>(self.rocksdb_engine_handler.cf_cluster(), &key)
+            .read::<Vec<u8>>(self.rocksdb_engine_handler.cf_raft(), &key)
             .unwrap();
         if value.is_none() {
             ConfState::default()
@@ -80,6 +82,20 @@ impl RaftMachineStorage {
         }
     }
 
+    /// Save the peers of the membership, the ConfState only holds their ids.
+    pub fn save_peers(&self, peers: Vec<BrokerNode>) -> Result<(), String> {
+        self.rocksdb_engine_handler
+            .write(self.rocksdb_engine_handler.cf_raft(), &key_name_by_peers(), &peers)
+    }
+
+    /// The peers saved by `save_peers`, empty before the first conf change.
+    pub fn peers(&self) -> Result<Vec<BrokerNode>, String> {
+        Ok(self
+            .rocksdb_engine_handler
+            .read::<Vec<BrokerNode>>(self.rocksdb_engine_handler.cf_raft(), &key_name_by_peers())?
+            .unwrap_or_default())
+    }
+
     pub fn commit_index(&mut self, idx: u64) -> RaftResult<()> {
         let entry = self.entry_by_idx(idx);
         if entry.is_none() {
@@ -128,7 +144,7 @@ impl RaftMachineStorage {
             let data: Vec<u8> = Entry::encode_to_vec(&entry);
             let key = key_name_by_entry(entry.index);
             self.rocksdb_engine_handler
-                .write(self.rocksdb_engine_handler.cf_cluster(), &key, &data)
+                .write(self.rocksdb_engine_handler.cf_raft(), &key, &data)
                 .unwrap();
             self.uncommit_index.insert(entry.index, 1);
             self.save_last_index(entry.index).unwrap();
@@ -156,7 +172,7 @@ impl RaftMachineStorage {
         let key = key_name_by_first_index();
         match self
             .rocksdb_engine_handler
-            .read::<u64>(self.rocksdb_engine_handler.cf_cluster(), &key)
+            .read::<u64>(self.rocksdb_engine_handler.cf_raft(), &key)
         {
             Ok(value) => {
                 if let Some(fi) = value {
@@ -176,7 +192,7 @@ impl RaftMachineStorage {
         let key = key_name_by_last_index();
         match self
             .rocksdb_engine_handler
-            .read::<u64>(self.rocksdb_engine_handler.cf_cluster(), &key)
+            .read::<u64>(self.rocksdb_engine_handler.cf_raft(), &key)
         {
             Ok(value) => {
                 if let Some(li) = value {
@@ -196,7 +212,7 @@ impl RaftMachineStorage {
         let key = key_name_by_entry(idx);
         match self
             .rocksdb_engine_handler
-            .read::<Vec<u8>>(self.rocksdb_engine_handler.cf_cluster(), &key)
+            .read::<Vec<u8>>(self.rocksdb_engine_handler.cf_raft(), &key)
         {
             Ok(value) => {
                 if let Some(vl) = value {
@@ -217,20 +233,20 @@ impl RaftMachineStorage {
     pub fn save_last_index(&self, index: u64) -> Result<(), String> {
         let key = key_name_by_last_index();
         self.rocksdb_engine_handler
-            .write(self.rocksdb_engine_handler.cf_cluster(), &key, &index)
+            .write(self.rocksdb_engine_handler.cf_raft(), &key, &index)
     }
 
     pub fn save_first_index(&self, index: u64) -> Result<(), String> {
         let key = key_name_by_first_index();
         self.rocksdb_engine_handler
-            .write(self.rocksdb_engine_handler.cf_cluster(), &key, &index)
+            .write(self.rocksdb_engine_handler.cf_raft(), &key, &index)
     }
 
     pub fn save_hard_state(&self, hs: HardState) -> Result<(), String> {
         let key = key_name_by_hard_state();
         let val = HardState::encode_to_vec(&hs);
         self.rocksdb_engine_handler
-            .write(self.rocksdb_engine_handler.cf_cluster(), &key, &val)
+            .write(self.rocksdb_engine_handler.cf_raft(), &key, &val)
     }
 
     pub fn set_hard_state_commit(&self, commit: u64) -> Result<(), String> {
@@ -245,21 +261,21 @@ impl RaftMachineStorage {
         let key = key_name_uncommit();
         let _ =
             self.rocksdb_engine_handler
-                .write(self.rocksdb_engine_handler.cf_cluster(), &key, &val);
+                .write(self.rocksdb_engine_handler.cf_raft(), &key, &val);
     }
 
     pub fn save_snapshot_data(&self, snapshot: Snapshot) {
         let val = Snapshot::encode_to_vec(&snapshot);
         let key = key_name_snapshot();
         let _ = self.rocksdb_engine_handler
-                .write(self.rocksdb_engine_handler.cf_cluster(), &key, &val);
+                .write(self.rocksdb_engine_handler.cf_raft(), &key, &val);
     }
 
     pub fn uncommit_index(&self) -> HashMap<u64, i8> {
         let key = key_name_uncommit();
         match self
             .rocksdb_engine_handler
-            .read::<Vec<u8>>(self.rocksdb_engine_handler.cf_cluster(), &key)
+            .read::<Vec<u8>>(self.rocksdb_engine_handler.cf_raft(), &key)
         {
             Ok(data) => {
                 if let Some(value) = data {
@@ -279,13 +295,26 @@ impl RaftMachineStorage {
             return;
         }
 
-        match deserialize::<HashMap<String, Vec<HashMap<String, String>>>>(data) {
+        let data = match decompress_snapshot(data) {
+            Ok(data) => data,
+            Err(err) => {
+                error!("Failed to decompress the snapshot data during snapshot data recovery, error message :{}", err);
+                return;
+            }
+        };
+        match deserialize::<HashMap<String, Vec<HashMap<String, String>>>>(&data) {
             Err(err) => {
                 error!("Failed to parse the snapshot data during snapshot data recovery, error message :{}",err.to_string());
             },
             Ok(data) => {
-                for (_, value) in data {
-                    let cf = self.rocksdb_engine_handler.get_column_family();
+                for (family, value) in data {
+                    let cf = match self.rocksdb_engine_handler.cf_handle(&family) {
+                        Ok(cf) => cf,
+                        Err(err) => {
+                            error!("Failed to apply the snapshot data of column family {}, error message: {}", family, err);
+                            continue;
+                        }
+                    };
                     for raw in value {
                         for (key, val) in &raw {
                             info!("key:{:?},val{:?}", key, val.to_string());
@@ -336,7 +365,7 @@ impl RaftMachineStorage {
         let key = key_name_snapshot();
         let value = self
             .rocksdb_engine_handler
-            .read::<Vec<u8>>(self.rocksdb_engine_handler.cf_cluster(), &key)
+            .read::<Vec<u8>>(self.rocksdb_engine_handler.cf_raft(), &key)
             .unwrap();
         if value.is_none() {
             Snapshot::default()
@@ -354,8 +383,17 @@ impl RaftMachineStorage {
         let meta = self.create_snapshot_metadata();
         sns.set_metadata(meta.clone());
 
-        let all_data = self.rocksdb_engine_handler.read_all();
-        sns.set_data(serialize(&all_data).unwrap());
+        // The raft state of this node is not part of the data it replicates
+        let mut all_data = self.rocksdb_engine_handler.read_all();
+        all_data.remove(DB_COLUMN_FAMILY_RAFT);
+        let data = serialize(&all_data).unwrap();
+        match compress_snapshot(&data) {
+            Ok(compressed) => sns.set_data(compressed),
+            Err(err) => {
+                error!("Failed to compress the snapshot, it is stored uncompressed. Error message: {}", err);
+                sns.set_data(data);
+            }
+        }
 
         let _ = self.save_first_index(meta.get_index());
 
@@ -1,29 +1,56 @@
 use std::collections::HashMap;
 use std::fmt::format;
 use std::path::Path;
-use rocksdb::{ColumnFamily, DBCompactionStyle, Error, Options, SliceTransform, DB};
+use std::sync::{RwLock, RwLockReadGuard};
+use rocksdb::{ColumnFamily, ColumnFamilyDescriptor, DBCompactionStyle, Error, Options, ReadOptions, SliceTransform, WriteBatch, DB};
 use log::error;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use common_base::config::placement_center::PlacementCenterConfig;
+use common_base::config::placement_center::{PlacementCenterConfig, PlacementCenterRocksDB, RocksDBCompactionStyle};
 use common_base::errors::RobustMQError;
+use crate::storage::encryption::{encryption_key_id, ValueCipher};
+use crate::storage::schema::migrate;
 
+/// Cluster and node metadata, and everything that does not belong to another domain.
 pub const DB_COLUMN_FAMILY_CLUSTER: &str = "cluster";
+/// Raft logs, hard state and snapshots, and the applied state of the openraft state machine.
+pub const DB_COLUMN_FAMILY_RAFT: &str = "raft";
+/// Keys, leases and namespaces of the KV service.
+pub const DB_COLUMN_FAMILY_KV: &str = "kv";
+pub const DB_COLUMN_FAMILY_JOURNAL: &str = "journal";
+pub const DB_COLUMN_FAMILY_MQTT: &str = "mqtt";
+
+/// Directory of the RocksDB of the placement center under its data path.
+pub fn storage_db_path(data_path: &str) -> String {
+    format!("{}/{}", data_path, "_storage_rocksdb")
+}
 
-fn column_family_list() -> Vec<String> {
+pub fn column_family_list() -> Vec<String> {
     let mut list = Vec::new();
     list.push(DB_COLUMN_FAMILY_CLUSTER.to_string());
+    list.push(DB_COLUMN_FAMILY_RAFT.to_string());
+    list.push(DB_COLUMN_FAMILY_KV.to_string());
+    list.push(DB_COLUMN_FAMILY_JOURNAL.to_string());
+    list.push(DB_COLUMN_FAMILY_MQTT.to_string());
     list
 }
 
+/// Number of values rewritten by one write batch of `reencrypt`.
+const REENCRYPT_BATCH_SIZE: usize = 1000;
+
 pub struct RocksDBEngine {
-    pub db: DB
+    pub db: DB,
+    /// Encrypts the values when a key file is configured
+    cipher: Option<ValueCipher>,
+    /// Writes share it, `reencrypt_cf` takes it exclusively while it rewrites a batch, so a value
+    /// it read cannot be overwritten in between.
+    write_guard: RwLock<()>,
 }
 
 impl RocksDBEngine {
     pub fn new(config: &PlacementCenterConfig) -> Self {
-        let opts: Options = Self::open_db_opts();
-        let db_path = format!("{}/{}", config.data_path, "_storage_rocksdb");
+        let opts: Options = Self::open_db_opts(&config.rocksdb);
+        let db_path = storage_db_path(&config.data_path);
 
         // init RocksDB
         if !Path::new(&db_path).exists() {
@@ -31,29 +58,46 @@ impl RocksDBEngine {
         }
 
         let cf_list = rocksdb::DB::list_cf(&opts, &db_path).unwrap();
-        let mut instance = DB::open_cf(&opts, db_path.clone(), &cf_list).unwrap();
+        let descriptors: Vec<ColumnFamilyDescriptor> = cf_list
+            .iter()
+            .map(|family| ColumnFamilyDescriptor::new(family, Self::column_family_opts(&config.rocksdb, family)))
+            .collect();
+        let mut instance = DB::open_cf_descriptors(&opts, db_path.clone(), descriptors).unwrap();
 
         for family in column_family_list().iter() {
             if cf_list.iter().find(|cf| cf == &family).is_none() {
-                match instance.create_cf(&family, &opts) {
+                match instance.create_cf(&family, &Self::column_family_opts(&config.rocksdb, family)) {
                     Ok(()) => {}
                     Err(err) => panic!("{}", err),
                 }
             }
         }
 
-        RocksDBEngine {
+        let cipher = if config.encryption_key_file.is_empty() {
+            None
+        } else {
+            match ValueCipher::from_key_file(&config.encryption_key_file) {
+                Ok(cipher) => Some(cipher),
+                Err(e) => panic!("{}", e),
+            }
+        };
+
+        let engine = RocksDBEngine {
             db: instance,
+            cipher,
+            write_guard: RwLock::new(()),
+        };
+        // Refuse to start on storage this binary cannot read
+        if let Err(e) = migrate(&engine) {
+            panic!("{}", e);
         }
+        engine
     }
 
     /// Write the data serialization to RocksDB
     pub fn write<T: Serialize + std::fmt::Debug>(&self, cf: &ColumnFamily, key: &str, value: &T) -> Result<(), String> {
         match serde_json::to_string(&value) {
-            Ok(serialized) => self
-                .db
-                .put_cf(cf, key, serialized.into_bytes())
-                .map_err(|err: Error| format!("Failed to put to ColumnFamily:{:?}", err)),
+            Ok(serialized) => self.write_str(cf, key, serialized),
             Err(err) => Err(format!(
                 "Failed to serialize to String. T: {:?}, err: {:?}",
                 value, err
@@ -63,9 +107,110 @@ impl RocksDBEngine {
 
     /// Write the data serialization to RocksDB
     pub fn write_str(&self, cf: &ColumnFamily, key: &str, value: String) -> Result<(), String> {
+        let value = self.encrypt(value.into_bytes()).map_err(|err| err.to_string())?;
+        let _guard = self.write_guard.read().unwrap();
         self.db
-            .put_cf(cf, key, value.into_bytes())
-            .map_err(|err| format!("Failed to put to ColumnFamily:{:?}", err))
+            .put_cf(cf, key, value)
+            .map_err(|err: Error| format!("Failed to put to ColumnFamily:{:?}", err))
+    }
+
+    /// Write all the updates of the batch atomically. The values of the batch have to be
+    /// encrypted by the caller with `encrypt`.
+    pub fn write_batch(&self, batch: WriteBatch) -> Result<(), RobustMQError> {
+        let _guard = self.write_guard.read().unwrap();
+        Ok(self.db.write(batch)?)
+    }
+
+    /// Encrypt a value before it is written, the value is returned as it is when encryption is disabled.
+    pub fn encrypt(&self, value: Vec<u8>) -> Result<Vec<u8>, RobustMQError> {
+        match &self.cipher {
+            Some(cipher) => cipher.encrypt(&value),
+            None => Ok(value),
+        }
+    }
+
+    /// Decrypt a value read from RocksDB. Plaintext values are returned as they are, so storage
+    /// written before encryption was enabled stays readable.
+    pub fn decrypt(&self, value: &[u8]) -> Result<Vec<u8>, RobustMQError> {
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt(value),
+            None if encryption_key_id(value).is_some() => Err(RobustMQError::EncryptionNotEnabled),
+            None => Ok(value.to_vec()),
+        }
+    }
+
+    /// Whether the value needs no re-encryption: it is encrypted with the active key, or
+    /// encryption is disabled.
+    pub fn is_current(&self, value: &[u8]) -> bool {
+        match &self.cipher {
+            Some(cipher) => cipher.is_current(value),
+            None => true,
+        }
+    }
+
+    /// Shared by the writers of another RocksDB whose values are encrypted like this storage,
+    /// such as the raft log, so `reencrypt_cf` does not race with them.
+    pub fn write_lock(&self) -> RwLockReadGuard<'_, ()> {
+        self.write_guard.read().unwrap()
+    }
+
+    /// Rewrite every value that is not encrypted with the active key, after a key rotation or
+    /// after encryption was enabled on existing storage. Returns the number of rewritten values.
+    pub fn reencrypt(&self) -> Result<u64, RobustMQError> {
+        let mut count = 0;
+        for family in column_family_list() {
+            count += self.reencrypt_cf(&self.db, self.cf_handle(&family)?)?;
+        }
+        Ok(count)
+    }
+
+    /// Rewrite the values of a column family of `db` that are not encrypted with the active key,
+    /// `db` is this storage or one whose writers hold `write_lock`.
+    pub fn reencrypt_cf(&self, db: &DB, cf: &ColumnFamily) -> Result<u64, RobustMQError> {
+        let cipher = match &self.cipher {
+            Some(cipher) => cipher,
+            None => return Err(RobustMQError::EncryptionNotEnabled),
+        };
+
+        let mut count = 0;
+        let mut iter = db.raw_iterator_cf_opt(cf, Self::total_order_read_opts());
+        iter.seek_to_first();
+        let mut stale = Vec::with_capacity(REENCRYPT_BATCH_SIZE);
+        loop {
+            let valid = iter.valid();
+            if let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                if !cipher.is_current(value) {
+                    stale.push(key.to_vec());
+                }
+            }
+            if stale.len() >= REENCRYPT_BATCH_SIZE || (!valid && !stale.is_empty()) {
+                count += self.reencrypt_keys(cipher, db, cf, &stale)?;
+                stale.clear();
+            }
+            if !valid {
+                break;
+            }
+            iter.next();
+        }
+        iter.status()?;
+        Ok(count)
+    }
+
+    // The values may have changed since the keys were listed, they are read again under the guard
+    fn reencrypt_keys(&self, cipher: &ValueCipher, db: &DB, cf: &ColumnFamily, keys: &[Vec<u8>]) -> Result<u64, RobustMQError> {
+        let _guard = self.write_guard.write().unwrap();
+        let mut batch = WriteBatch::default();
+        let mut count = 0;
+        for key in keys {
+            if let Some(value) = db.get_cf(cf, key)? {
+                if !cipher.is_current(&value) {
+                    batch.put_cf(cf, key, cipher.encrypt(&cipher.decrypt(&value)?)?);
+                    count += 1;
+                }
+            }
+        }
+        db.write(batch)?;
+        Ok(count)
     }
 
     /// Read data from the RocksDB
@@ -73,7 +218,7 @@ impl RocksDBEngine {
         match self.db.get_cf(cf, key) {
             Ok(opt) => match opt {
                 None => Ok(None),
-                Some(found) => match String::from_utf8(found) {
+                Some(found) => match String::from_utf8(self.decrypt(&found).map_err(|err| err.to_string())?) {
                     Ok(s) => match serde_json::from_str::<T>(&s) {
                         Ok(t) => Ok(Some(t)),
                         Err(err) => Err(format!("Failed to deserialize: {:?}", err)),
@@ -87,7 +232,7 @@ impl RocksDBEngine {
 
     /// Search data by prefix
     pub fn read_prefix(&self, cf: &ColumnFamily, search_key: &str) -> Vec<HashMap<String, Vec<u8>>> {
-        let mut iter = self.db.raw_iterator_cf(cf);
+        let mut iter = self.db.raw_iterator_cf_opt(cf, Self::total_order_read_opts());
         iter.seek(search_key);
 
         let mut result = Vec::new();
@@ -97,28 +242,152 @@ impl RocksDBEngine {
 
             let mut raw = HashMap::new();
             if key == None || value == None {
+                iter.next();
                 continue;
             }
             let result_key = match String::from_utf8(key.unwrap().to_vec()) {
                 Ok(s) => s,
-                Err(_) => continue,
+                Err(_) => {
+                    iter.next();
+                    continue;
+                }
             };
 
             if !result_key.starts_with(search_key) {
                 break;
             }
-            raw.insert(result_key, value.unwrap().to_vec());
+            match self.decrypt(value.unwrap()) {
+                Ok(value) => raw.insert(result_key, value),
+                Err(err) => {
+                    error!("{}", err);
+                    iter.next();
+                    continue;
+                }
+            };
             result.push(raw);
             iter.next();
         }
         result
     }
 
+    /// Read at most `limit` entries whose key starts with `search_key`, in key order,
+    /// beginning after `start_after` (or at the first key of the prefix when it is empty).
+    /// Returns the entries and whether more keys of the prefix follow them.
+    pub fn read_prefix_page(
+        &self,
+        cf: &ColumnFamily,
+        search_key: &str,
+        start_after: &str,
+        limit: usize,
+    ) -> (Vec<(String, Vec<u8>)>, bool) {
+        let mut iter = self.db.raw_iterator_cf_opt(cf, Self::total_order_read_opts());
+        if start_after.is_empty() || start_after < search_key {
+            iter.seek(search_key);
+        } else {
+            iter.seek(start_after);
+        }
+
+        let mut result = Vec::new();
+        while iter.valid() {
+            let (key, value) = match (iter.key(), iter.value()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => {
+                    iter.next();
+                    continue;
+                }
+            };
+            let result_key = match String::from_utf8(key.to_vec()) {
+                Ok(s) => s,
+                Err(_) => {
+                    iter.next();
+                    continue;
+                }
+            };
+
+            if !result_key.starts_with(search_key) {
+                break;
+            }
+            if result_key.as_str() == start_after {
+                iter.next();
+                continue;
+            }
+            if result.len() >= limit {
+                return (result, true);
+            }
+            match self.decrypt(value) {
+                Ok(value) => result.push((result_key, value)),
+                Err(err) => error!("{}", err),
+            }
+            iter.next();
+        }
+        (result, false)
+    }
+
+    /// Read every entry of a ColumnFamily from a single point-in-time view,
+    /// so writes made while reading are not partially visible.
+    pub fn read_all_consistent(&self, cf: &ColumnFamily) -> Vec<(String, Vec<u8>)> {
+        self.read_all_consistent_by_cfs(&[cf]).pop().unwrap_or_default()
+    }
+
+    /// Read every entry of several ColumnFamilies from the same point-in-time view,
+    /// the entries are returned in the order of the families.
+    pub fn read_all_consistent_by_cfs(&self, cfs: &[&ColumnFamily]) -> Vec<Vec<(String, Vec<u8>)>> {
+        let snapshot = self.db.snapshot();
+        let mut results = Vec::with_capacity(cfs.len());
+        for cf in cfs {
+            let mut iter = snapshot.raw_iterator_cf_opt(*cf, Self::total_order_read_opts());
+            iter.seek_to_first();
+
+            let mut result = Vec::new();
+            while iter.valid() {
+                if let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                    match (String::from_utf8(key.to_vec()), self.decrypt(value)) {
+                        (Ok(key), Ok(value)) => result.push((key, value)),
+                        (Err(err), _) => error!("{}", err),
+                        (_, Err(err)) => error!("{}", err),
+                    }
+                }
+                iter.next();
+            }
+            results.push(result);
+        }
+        results
+    }
+
+    /// Visit the entries of several (family, prefix) scans from the same point-in-time view,
+    /// one entry at a time instead of loading them in memory. An empty prefix scans the whole
+    /// family. The first error returned by `visit` stops the scan and is returned.
+    pub fn scan_consistent<F>(&self, scans: &[(&str, &str)], mut visit: F) -> Result<(), RobustMQError>
+    where
+        F: FnMut(&str, String, &[u8]) -> Result<(), RobustMQError>,
+    {
+        let snapshot = self.db.snapshot();
+        for (family, prefix) in scans {
+            let cf = self.cf_handle(family)?;
+            let mut iter = snapshot.raw_iterator_cf_opt(cf, Self::total_order_read_opts());
+            iter.seek(prefix);
+            while iter.valid() {
+                if let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                    if !key.starts_with(prefix.as_bytes()) {
+                        break;
+                    }
+                    match String::from_utf8(key.to_vec()) {
+                        Ok(key) => visit(family, key, &self.decrypt(value)?)?,
+                        Err(err) => error!("{}", err),
+                    }
+                }
+                iter.next();
+            }
+            iter.status()?;
+        }
+        Ok(())
+    }
+
     /// read data from all ColumnFamily
     pub fn read_all(&self) -> HashMap<String, Vec<HashMap<String, String>>> {
         let mut result: HashMap<String, Vec<HashMap<String, String>>> = HashMap::new();
         for family in column_family_list().iter() {
-            let cf = self.get_column_family();
+            let cf = self.db.cf_handle(family).unwrap();
             result.insert(family.to_string(), self.read_all_by_cf(cf));
         }
         result
@@ -137,7 +406,7 @@ impl RocksDBEngine {
                         Err(err) => {
                             error!("{}", err);
                         },
-                        Ok(key) => match String::from_utf8(val.to_vec()) {
+                        Ok(key) => match self.decrypt(val).map_err(|err| err.to_string()).and_then(|val| String::from_utf8(val).map_err(|err| err.to_string())) {
                             Err(err) => {
                                 error!("{}", err)
                             },
@@ -155,43 +424,132 @@ impl RocksDBEngine {
         result
     }
 
-    fn open_db_opts() -> Options {
+    // The fixed prefix extractor only covers the first 10 bytes of a key, so range scans
+    // have to ignore it to see keys whose prefix is shorter or longer than that.
+    fn total_order_read_opts() -> ReadOptions {
+        let mut opts = ReadOptions::default();
+        opts.set_total_order_seek(true);
+        opts
+    }
+
+    fn open_db_opts(config: &PlacementCenterRocksDB) -> Options {
         let transform = SliceTransform::create_fixed_prefix(10);
         let mut opts = Options::default();
         opts.create_if_missing(true);
         opts.create_missing_column_families(true);
-        opts.set_max_open_files(1000);
+        opts.set_max_open_files(config.max_open_files);
         opts.set_use_fsync(false);
         opts.set_bytes_per_sync(8388608);
         opts.optimize_for_point_lookup(1024);
         opts.set_table_cache_num_shard_bits(6);
-        opts.set_max_write_buffer_number(32);
-        opts.set_write_buffer_size(536870912);
-        opts.set_target_file_size_base(1073741824);
-        opts.set_min_write_buffer_number_to_merge(4);
-        opts.set_level_zero_stop_writes_trigger(2000);
-        opts.set_level_zero_slowdown_writes_trigger(0);
-        opts.set_compaction_style(DBCompactionStyle::Universal);
-        opts.set_disable_auto_compactions(true);
+        opts.set_max_write_buffer_number(config.max_write_buffer_number);
+        opts.set_write_buffer_size(config.write_buffer_size);
+        opts.set_target_file_size_base(config.target_file_size_base);
+        opts.set_min_write_buffer_number_to_merge(config.min_write_buffer_number_to_merge);
+        opts.set_level_zero_stop_writes_trigger(config.level_zero_stop_writes_trigger);
+        opts.set_level_zero_slowdown_writes_trigger(config.level_zero_slowdown_writes_trigger);
+        opts.set_compaction_style(Self::compaction_style(&config.compaction_style));
+        opts.set_disable_auto_compactions(config.disable_auto_compactions);
         opts.set_prefix_extractor(transform);
         opts.set_memtable_prefix_bloom_ratio(0.2);
         opts
     }
 
+    /// Options of the families of each domain, tuned for how the domain uses its keys.
+    /// They apply on top of the options of the database.
+    fn column_family_opts(config: &PlacementCenterRocksDB, family: &str) -> Options {
+        let mut opts = Self::open_db_opts(config);
+        match family {
+            // Log entries are appended in order and purged from the front, so unlike the
+            // metadata families they need compaction to reclaim the purged entries.
+            DB_COLUMN_FAMILY_RAFT => {
+                opts.set_disable_auto_compactions(false);
+                opts.set_compaction_style(DBCompactionStyle::Level);
+                opts.set_max_write_buffer_number(4);
+                opts.set_write_buffer_size(67108864);
+                opts.set_min_write_buffer_number_to_merge(1);
+            }
+            // Point reads and prefix scans of user keys
+            DB_COLUMN_FAMILY_KV => {
+                opts.set_max_write_buffer_number(8);
+                opts.set_write_buffer_size(134217728);
+                opts.set_min_write_buffer_number_to_merge(2);
+            }
+            // Small metadata that changes rarely
+            _ => {
+                opts.set_max_write_buffer_number(4);
+                opts.set_write_buffer_size(33554432);
+                opts.set_min_write_buffer_number_to_merge(1);
+            }
+        }
+        opts
+    }
+
+    // The style is checked when the configuration file is loaded
+    fn compaction_style(style: &str) -> DBCompactionStyle {
+        match RocksDBCompactionStyle::from_name(style) {
+            Ok(RocksDBCompactionStyle::Level) => DBCompactionStyle::Level,
+            Ok(RocksDBCompactionStyle::Universal) => DBCompactionStyle::Universal,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Compact every key of the column family. It blocks until the compaction is done.
+    pub fn compact_column_family(&self, family: &str) -> Result<(), RobustMQError> {
+        let cf = self.cf_handle(family)?;
+        self.db.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+        Ok(())
+    }
+
+    /// An integer property of a column family, such as `rocksdb.estimate-pending-compaction-bytes`.
+    pub fn property_int_value(&self, family: &str, name: &str) -> Result<u64, RobustMQError> {
+        let cf = self.cf_handle(family)?;
+        Ok(self.db.property_int_value_cf(cf, name)?.unwrap_or_default())
+    }
+
     pub fn get_column_family(&self) -> &ColumnFamily {
         self.cf_cluster()
     }
 
+    /// The handle of a column family created by `new`.
+    pub fn cf_handle(&self, family: &str) -> Result<&ColumnFamily, RobustMQError> {
+        match self.db.cf_handle(family) {
+            Some(cf) => Ok(cf),
+            None => Err(RobustMQError::ColumnFamilyNotFound(family.to_string())),
+        }
+    }
+
     pub fn cf_cluster(&self) -> &ColumnFamily {
         self.db.cf_handle(&DB_COLUMN_FAMILY_CLUSTER).unwrap()
     }
 
+    pub fn cf_raft(&self) -> &ColumnFamily {
+        self.db.cf_handle(&DB_COLUMN_FAMILY_RAFT).unwrap()
+    }
+
+    pub fn cf_kv(&self) -> &ColumnFamily {
+        self.db.cf_handle(&DB_COLUMN_FAMILY_KV).unwrap()
+    }
+
+    pub fn cf_journal(&self) -> &ColumnFamily {
+        self.db.cf_handle(&DB_COLUMN_FAMILY_JOURNAL).unwrap()
+    }
+
+    pub fn cf_mqtt(&self) -> &ColumnFamily {
+        self.db.cf_handle(&DB_COLUMN_FAMILY_MQTT).unwrap()
+    }
+
     pub fn delete(&self, cf: &ColumnFamily, key: &str) -> Result<(), RobustMQError> {
+        let _guard = self.write_guard.read().unwrap();
         Ok(self.db.delete_cf(cf, key)?)
     }
 
     pub fn exist(&self, cf: &ColumnFamily, key: &str) -> bool {
-        self.db.key_may_exist_cf(cf, key)
+        // key_may_exist_cf only rules keys out, a true result still has to be confirmed by a read
+        if !self.db.key_may_exist_cf(cf, key) {
+            return false;
+        }
+        matches!(self.db.get_pinned_cf(cf, key), Ok(Some(_)))
     }
 }
 
@@ -203,7 +561,9 @@ mod tests {
     use tokio::fs::remove_dir;
     use tokio::time::sleep;
     use common_base::config::placement_center::PlacementCenterConfig;
+    use crate::storage::encryption::encryption_key_id;
     use crate::storage::rocksdb::RocksDBEngine;
+    use crate::storage::test_tools::TempDir;
 
     #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
     struct User {
@@ -339,4 +699,78 @@ mod tests {
         let result = rs.read_prefix(rs.cf_cluster(), "/v4");
         assert_eq!(result.len(), 1);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn read_prefix_page() {
+        let dir = TempDir::new();
+        let rs = dir.rocksdb_engine();
+        for i in 1..6 {
+            rs.write_str(rs.cf_cluster(), &format!("/page/k{}", i), i.to_string())
+                .unwrap();
+        }
+        rs.write_str(rs.cf_cluster(), "/pages", "0".to_string())
+            .unwrap();
+
+        let (result, more) = rs.read_prefix_page(rs.cf_cluster(), "/page/", "", 2);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].0, "/page/k1");
+        assert!(more);
+
+        let (result, more) = rs.read_prefix_page(rs.cf_cluster(), "/page/", "/page/k2", 2);
+        assert_eq!(result[0].0, "/page/k3");
+        assert_eq!(result[1].0, "/page/k4");
+        assert!(more);
+
+        let (result, more) = rs.read_prefix_page(rs.cf_cluster(), "/page/", "/page/k4", 2);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].0, "/page/k5");
+        assert!(!more);
+    }
+
+    #[tokio::test]
+    async fn exist() {
+        let dir = TempDir::new();
+        let rs = dir.rocksdb_engine();
+        rs.write_str(rs.cf_cluster(), "/exist/k1", "v1".to_string())
+            .unwrap();
+
+        assert!(rs.exist(rs.cf_cluster(), "/exist/k1"));
+        assert!(!rs.exist(rs.cf_cluster(), "/exist/k2"));
+
+        rs.delete(rs.cf_cluster(), "/exist/k1").unwrap();
+        assert!(!rs.exist(rs.cf_cluster(), "/exist/k1"));
+    }
+
+    #[test]
+    fn encrypted_values_and_reencrypt() {
+        let dir = TempDir::new();
+        let key_file = &dir.join("encryption.keys");
+        std::fs::write(key_file, format!("active_key_id = 1\n\n[keys]\n1 = \"{}\"\n", "11".repeat(32)))
+            .unwrap();
+        let mut config = dir.config();
+        config.encryption_key_file = key_file.to_string();
+        let key = "/mqtt/user/c1/u1";
+
+        {
+            let rs = RocksDBEngine::new(&config);
+            rs.write(rs.cf_mqtt(), key, &"password".to_string()).unwrap();
+            let raw = rs.db.get_cf(rs.cf_mqtt(), key).unwrap().unwrap();
+            assert_eq!(encryption_key_id(&raw), Some(1));
+            assert_eq!(rs.read::<String>(rs.cf_mqtt(), key).unwrap().unwrap(), "password");
+        }
+
+        // Rotate to key 2, values written with key 1 stay readable until they are re-encrypted
+        std::fs::write(
+            key_file,
+            format!("active_key_id = 2\n\n[keys]\n1 = \"{}\"\n2 = \"{}\"\n", "11".repeat(32), "22".repeat(32)),
+        )
+        .unwrap();
+        let rs = RocksDBEngine::new(&config);
+        assert_eq!(rs.read::<String>(rs.cf_mqtt(), key).unwrap().unwrap(), "password");
+        assert!(rs.reencrypt().unwrap() > 0);
+        let raw = rs.db.get_cf(rs.cf_mqtt(), key).unwrap().unwrap();
+        assert_eq!(encryption_key_id(&raw), Some(2));
+        assert_eq!(rs.reencrypt().unwrap(), 0);
+        assert_eq!(rs.read::<String>(rs.cf_mqtt(), key).unwrap().unwrap(), "password");
+    }
+}
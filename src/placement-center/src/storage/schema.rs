@@ -0,0 +1,261 @@
+use log::info;
+use rocksdb::WriteBatch;
+use common_base::errors::RobustMQError;
+use crate::storage::keys::{key_kv_prefix, key_lease_prefix, key_name_raft_prefix, key_namespace_prefix, key_openraft_prefix, key_schema_version};
+use crate::storage::namespace::migrate_kv_to_default_namespace;
+use crate::storage::rocksdb::{RocksDBEngine, DB_COLUMN_FAMILY_CLUSTER, DB_COLUMN_FAMILY_JOURNAL, DB_COLUMN_FAMILY_KV, DB_COLUMN_FAMILY_MQTT, DB_COLUMN_FAMILY_RAFT};
+
+/// Version of the key layout and value encodings written by this binary.
+/// It is bumped together with every migration added to `migrations`.
+pub const SCHEMA_VERSION: u64 = 2;
+
+/// A step that upgrades the storage from schema `version - 1` to schema `version`.
+pub struct Migration {
+    pub version: u64,
+    pub name: &'static str,
+    /// Adds the writes of the migration to the batch. The batch also records the new schema
+    /// version, so a migration is either applied as a whole or not at all.
+    pub run: fn(&RocksDBEngine, &mut WriteBatch) -> Result<(), RobustMQError>,
+}
+
+/// Every migration, in the order they have to run.
+pub fn migrations() -> Vec<Migration> {
+    vec![
+        Migration {
+            version: 1,
+            name: "kv_default_namespace",
+            run: migrate_kv_to_default_namespace,
+        },
+        Migration {
+            version: 2,
+            name: "domain_column_families",
+            run: migrate_domain_column_families,
+        },
+    ]
+}
+
+/// The schema version stored in RocksDB, None for storage written before versions existed.
+pub fn schema_version(rocksdb_engine: &RocksDBEngine) -> Result<Option<u64>, RobustMQError> {
+    match rocksdb_engine.read::<u64>(rocksdb_engine.cf_cluster(), &key_schema_version()) {
+        Ok(version) => Ok(version),
+        Err(e) => Err(RobustMQError::CommonError(e)),
+    }
+}
+
+/// Bring the storage up to `SCHEMA_VERSION` by running the migrations it has not seen yet.
+/// Storage written by a newer binary is refused, as this one cannot know its layout.
+pub fn migrate(rocksdb_engine: &RocksDBEngine) -> Result<(), RobustMQError> {
+    migrate_to(rocksdb_engine, SCHEMA_VERSION, &migrations())
+}
+
+fn migrate_to(
+    rocksdb_engine: &RocksDBEngine,
+    target_version: u64,
+    migrations: &[Migration],
+) -> Result<(), RobustMQError> {
+    let current_version = match schema_version(rocksdb_engine)? {
+        Some(version) => version,
+        // New storage is created with the current layout
+        None if is_empty(rocksdb_engine) => {
+            let mut batch = WriteBatch::default();
+            put_schema_version(rocksdb_engine, &mut batch, target_version)?;
+            return rocksdb_engine.write_batch(batch);
+        }
+        None => 0,
+    };
+
+    if current_version > target_version {
+        return Err(RobustMQError::SchemaVersionTooNew(current_version, target_version));
+    }
+
+    for migration in migrations
+        .iter()
+        .filter(|migration| migration.version > current_version && migration.version <= target_version)
+    {
+        info!(
+            "Migrating storage to schema version {} ({})",
+            migration.version, migration.name
+        );
+        let mut batch = WriteBatch::default();
+        if let Err(e) = (migration.run)(rocksdb_engine, &mut batch) {
+            return Err(RobustMQError::MigrationFailed(
+                migration.version,
+                migration.name.to_string(),
+                e.to_string(),
+            ));
+        }
+        put_schema_version(rocksdb_engine, &mut batch, migration.version)?;
+        rocksdb_engine.write_batch(batch)?;
+    }
+    Ok(())
+}
+
+fn put_schema_version(
+    rocksdb_engine: &RocksDBEngine,
+    batch: &mut WriteBatch,
+    version: u64,
+) -> Result<(), RobustMQError> {
+    batch.put_cf(
+        rocksdb_engine.cf_cluster(),
+        key_schema_version(),
+        rocksdb_engine.encrypt(serde_json::to_vec(&version)?)?,
+    );
+    Ok(())
+}
+
+/// Schema migration 2: every key used to be stored in the cluster column family. The keys of
+/// the raft, KV, journal and MQTT domains are moved to the column family of their domain.
+fn migrate_domain_column_families(
+    rocksdb_engine: &RocksDBEngine,
+    batch: &mut WriteBatch,
+) -> Result<(), RobustMQError> {
+    let cf_cluster = rocksdb_engine.cf_cluster();
+    for (key, value) in rocksdb_engine.read_all_consistent(cf_cluster) {
+        let family = domain_column_family(&key);
+        if family == DB_COLUMN_FAMILY_CLUSTER {
+            continue;
+        }
+        batch.put_cf(rocksdb_engine.cf_handle(family)?, &key, rocksdb_engine.encrypt(value)?);
+        batch.delete_cf(cf_cluster, &key);
+    }
+    Ok(())
+}
+
+fn domain_column_family(key: &str) -> &'static str {
+    if key.starts_with(&key_name_raft_prefix()) || key.starts_with(&key_openraft_prefix()) {
+        DB_COLUMN_FAMILY_RAFT
+    } else if key.starts_with(&key_kv_prefix())
+        || key.starts_with(&key_lease_prefix())
+        || key.starts_with(&key_namespace_prefix())
+    {
+        DB_COLUMN_FAMILY_KV
+    } else if key.starts_with("/journal/") {
+        DB_COLUMN_FAMILY_JOURNAL
+    } else if key.starts_with("/mqtt/") {
+        DB_COLUMN_FAMILY_MQTT
+    } else {
+        DB_COLUMN_FAMILY_CLUSTER
+    }
+}
+
+fn is_empty(rocksdb_engine: &RocksDBEngine) -> bool {
+    let mut iter = rocksdb_engine.db.raw_iterator_cf(rocksdb_engine.cf_cluster());
+    iter.seek_to_first();
+    !iter.valid()
+}
+
+#[cfg(test)]
+mod tests {
+    use rocksdb::WriteBatch;
+    use common_base::errors::RobustMQError;
+    use crate::storage::keys::{key_lease, key_schema_version};
+    use crate::storage::lease::{Lease, LeaseStorage};
+    use crate::storage::namespace::{namespace_key, NamespaceStorage, DEFAULT_NAMESPACE};
+    use crate::storage::rocksdb::RocksDBEngine;
+    use crate::storage::test_tools::TempDir;
+    use crate::storage::StorageDataWrap;
+    use super::{migrate, migrate_to, migrations, schema_version, Migration, SCHEMA_VERSION};
+
+    #[test]
+    fn migrations_are_ordered() {
+        let versions: Vec<u64> = migrations().iter().map(|migration| migration.version).collect();
+        let expected: Vec<u64> = (1..=SCHEMA_VERSION).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn new_storage_and_newer_schema() {
+        let dir = TempDir::new();
+        let rs = dir.rocksdb_engine();
+        assert_eq!(schema_version(&rs).unwrap(), Some(SCHEMA_VERSION));
+
+        rs.write(rs.cf_cluster(), &key_schema_version(), &(SCHEMA_VERSION + 1))
+            .unwrap();
+        match migrate(&rs) {
+            Err(RobustMQError::SchemaVersionTooNew(version, supported)) => {
+                assert_eq!(version, SCHEMA_VERSION + 1);
+                assert_eq!(supported, SCHEMA_VERSION);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn failed_migration_keeps_version() {
+        fn first(rocksdb_engine: &RocksDBEngine, batch: &mut WriteBatch) -> Result<(), RobustMQError> {
+            batch.put_cf(rocksdb_engine.cf_cluster(), "/migrated/1", b"1");
+            Ok(())
+        }
+        fn second(_: &RocksDBEngine, _: &mut WriteBatch) -> Result<(), RobustMQError> {
+            Err(RobustMQError::CommonError("broken".to_string()))
+        }
+        let steps = vec![
+            Migration { version: 1, name: "first", run: first },
+            Migration { version: 2, name: "second", run: second },
+        ];
+
+        let dir = TempDir::new();
+        let rs = dir.rocksdb_engine();
+        rs.write(rs.cf_cluster(), &key_schema_version(), &0u64).unwrap();
+        match migrate_to(&rs, 2, &steps) {
+            Err(RobustMQError::MigrationFailed(2, _, _)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(schema_version(&rs).unwrap(), Some(1));
+        assert!(rs.exist(rs.cf_cluster(), "/migrated/1"));
+    }
+
+    #[test]
+    fn legacy_kv_moved_to_default_namespace() {
+        let dir = TempDir::new();
+        let rs = dir.rocksdb_engine();
+        let cf = rs.cf_cluster();
+        // Keys written by the KV service before namespaces and column families existed
+        let data = StorageDataWrap::new(serde_json::to_vec("broker-1").unwrap());
+        rs.write(cf, "/broker/1", &data).unwrap();
+        let lease = Lease {
+            lease_id: 7,
+            ttl: 30,
+            keys: vec!["/broker/1".to_string()],
+            create_time: 0,
+        };
+        let lease = StorageDataWrap::new(serde_json::to_vec(&lease).unwrap());
+        rs.write(cf, &key_lease(7), &lease).unwrap();
+        rs.delete(cf, &key_schema_version()).unwrap();
+
+        migrate(&rs).unwrap();
+        assert_eq!(schema_version(&rs).unwrap(), Some(SCHEMA_VERSION));
+
+        let key = namespace_key(DEFAULT_NAMESPACE, "/broker/1");
+        assert!(!rs.exist(cf, "/broker/1"));
+        assert!(!rs.exist(cf, &key));
+        assert!(rs.exist(rs.cf_kv(), &key));
+        assert!(rs.exist(rs.cf_kv(), &key_lease(7)));
+        let lease = LeaseStorage::new(rs.clone()).get(7).unwrap().unwrap();
+        assert_eq!(lease.keys, vec![key]);
+
+        let namespace = NamespaceStorage::new(rs.clone()).get(DEFAULT_NAMESPACE).unwrap().unwrap();
+        assert_eq!(namespace.key_count, 1);
+        assert_eq!(namespace.total_bytes, ("/broker/1".len() + data.data.len()) as u64);
+    }
+
+    #[test]
+    fn keys_moved_to_domain_column_families() {
+        let dir = TempDir::new();
+        let rs = dir.rocksdb_engine();
+        let cf = rs.cf_cluster();
+        for key in ["/raft/entry/1", "/openraft/last_membership", "/journal/shard/c1", "/mqtt/user/c1/u1", "/clusters/mqtt/c1"] {
+            rs.write_str(cf, key, "1".to_string()).unwrap();
+        }
+        rs.write(cf, &key_schema_version(), &1u64).unwrap();
+
+        migrate(&rs).unwrap();
+        assert!(rs.exist(rs.cf_raft(), "/raft/entry/1"));
+        assert!(rs.exist(rs.cf_raft(), "/openraft/last_membership"));
+        assert!(rs.exist(rs.cf_journal(), "/journal/shard/c1"));
+        assert!(rs.exist(rs.cf_mqtt(), "/mqtt/user/c1/u1"));
+        assert!(rs.exist(cf, "/clusters/mqtt/c1"));
+        assert!(!rs.exist(cf, "/raft/entry/1"));
+        assert!(!rs.exist(cf, "/mqtt/user/c1/u1"));
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use openraft::{LogId, StoredMembership};
+use rocksdb::WriteBatch;
+use common_base::errors::RobustMQError;
+use crate::openraft::raft_node::NodeId;
+use crate::openraft::typeconfig::TypeConfig;
+use crate::storage::compression::{compress_snapshot, decompress_snapshot};
+use crate::storage::engine::{engine_batch_across, engine_get, EngineBatchOp};
+use crate::storage::keys::{key_openraft_last_applied, key_openraft_last_membership};
+use crate::storage::rocksdb::{RocksDBEngine, DB_COLUMN_FAMILY_KV, DB_COLUMN_FAMILY_RAFT};
+use crate::storage::StorageDataWrap;
+
+/// First byte of a snapshot file written frame by frame. It starts neither a JSON document nor
+/// a compressed or an encrypted payload, which tells it apart from the snapshot files written
+/// as a single payload before frames existed.
+const SNAPSHOT_FILE_MAGIC: u8 = 0xFB;
+const SNAPSHOT_FILE_VERSION: u8 = 1;
+
+/// Keys of one frame of a snapshot file. A frame is read and written in memory at once, and
+/// installed with one write batch.
+const SNAPSHOT_FRAME_KEYS: usize = 1000;
+
+/// The keys of one frame of a snapshot file.
+pub type SnapshotFrame = Vec<(String, StorageDataWrap)>;
+
+/// Keeps the applied state of the openraft state machine in the same RocksDB as the data it
+/// applies, so an entry and its log id are always written by the same write batch.
+/// The applied state lives in the raft column family, the data in the KV column family.
+///
+/// A snapshot file is the magic byte, the version, then frames of keys each written as its
+/// length as a big endian u32 and the frame, compressed then encrypted like stored values.
+/// A frame of length 0 ends the file.
+pub struct StateMachineStorage {
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+}
+
+impl StateMachineStorage {
+    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>) -> Self {
+        StateMachineStorage {
+            rocksdb_engine_handler,
+        }
+    }
+
+    pub fn last_applied(&self) -> Result<Option<LogId<NodeId>>, RobustMQError> {
+        match engine_get(self.rocksdb_engine_handler.clone(), DB_COLUMN_FAMILY_RAFT, key_openraft_last_applied())? {
+            Some(data) => Ok(serde_json::from_slice(&data.data)?),
+            None => Ok(None),
+        }
+    }
+
+    pub fn last_membership(&self) -> Result<StoredMembership<TypeConfig>, RobustMQError> {
+        match engine_get(self.rocksdb_engine_handler.clone(), DB_COLUMN_FAMILY_RAFT, key_openraft_last_membership())? {
+            Some(data) => Ok(serde_json::from_slice(&data.data)?),
+            None => Ok(StoredMembership::default()),
+        }
+    }
+
+    /// The writes that record the applied state, to be added to the batch of the applied entry.
+    pub fn applied_state_ops(
+        &self,
+        last_applied_log_id: &Option<LogId<NodeId>>,
+        last_membership: &StoredMembership<TypeConfig>,
+    ) -> Result<Vec<EngineBatchOp>, RobustMQError> {
+        Ok(vec![
+            EngineBatchOp::Save(
+                key_openraft_last_applied(),
+                StorageDataWrap::new(serde_json::to_vec(last_applied_log_id)?),
+            ),
+            EngineBatchOp::Save(
+                key_openraft_last_membership(),
+                StorageDataWrap::new(serde_json::to_vec(last_membership)?),
+            ),
+        ])
+    }
+
+    /// Write every key of the state machine to the snapshot file at `path` and return the
+    /// applied state they were read with. The keys and the applied state are read from one
+    /// RocksDB snapshot, one frame at a time.
+    pub fn write_snapshot_file(
+        &self,
+        path: &Path,
+    ) -> Result<(Option<LogId<NodeId>>, StoredMembership<TypeConfig>), RobustMQError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let state = self.write_snapshot(&mut writer)?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        Ok(state)
+    }
+
+    pub fn write_snapshot<W: Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(Option<LogId<NodeId>>, StoredMembership<TypeConfig>), RobustMQError> {
+        let last_applied_key = key_openraft_last_applied();
+        let last_membership_key = key_openraft_last_membership();
+        let mut last_applied_log_id = None;
+        let mut last_membership = StoredMembership::default();
+
+        writer.write_all(&[SNAPSHOT_FILE_MAGIC, SNAPSHOT_FILE_VERSION])?;
+        let mut frame = Vec::with_capacity(SNAPSHOT_FRAME_KEYS);
+        let scans = [
+            (DB_COLUMN_FAMILY_RAFT, last_applied_key.as_str()),
+            (DB_COLUMN_FAMILY_RAFT, last_membership_key.as_str()),
+            (DB_COLUMN_FAMILY_KV, ""),
+        ];
+        self.rocksdb_engine_handler.scan_consistent(&scans, |family, key, value| {
+            if family == DB_COLUMN_FAMILY_KV {
+                // Values that are not wrapped are not written by the state machine
+                if let Ok(wrap) = serde_json::from_slice::<StorageDataWrap>(value) {
+                    frame.push((key, wrap));
+                }
+                if frame.len() >= SNAPSHOT_FRAME_KEYS {
+                    self.write_frame(writer, &frame)?;
+                    frame.clear();
+                }
+            } else if key == last_applied_key {
+                let wrap: StorageDataWrap = serde_json::from_slice(value)?;
+                last_applied_log_id = serde_json::from_slice(&wrap.data)?;
+            } else if key == last_membership_key {
+                let wrap: StorageDataWrap = serde_json::from_slice(value)?;
+                last_membership = serde_json::from_slice(&wrap.data)?;
+            }
+            Ok(())
+        })?;
+        if !frame.is_empty() {
+            self.write_frame(writer, &frame)?;
+        }
+        writer.write_all(&0u32.to_be_bytes())?;
+        writer.flush()?;
+        Ok((last_applied_log_id, last_membership))
+    }
+
+    /// Rewrite a snapshot file whose frames are not all encrypted with the active key, after a
+    /// key rotation. The file is written next to it and renamed over it. Returns whether the
+    /// file was rewritten.
+    pub fn reencrypt_snapshot_file(&self, path: &Path) -> Result<bool, RobustMQError> {
+        if self.snapshot_file_is_current(path)? {
+            return Ok(false);
+        }
+        let rewritten = path.with_extension("reencrypt");
+        let mut writer = BufWriter::new(File::create(&rewritten)?);
+        writer.write_all(&[SNAPSHOT_FILE_MAGIC, SNAPSHOT_FILE_VERSION])?;
+        self.read_snapshot(BufReader::new(File::open(path)?), |frame| {
+            // A snapshot file written as a single payload is split into frames
+            for keys in frame.chunks(SNAPSHOT_FRAME_KEYS) {
+                self.write_frame(&mut writer, keys)?;
+            }
+            Ok(())
+        })?;
+        writer.write_all(&0u32.to_be_bytes())?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        std::fs::rename(&rewritten, path)?;
+        Ok(true)
+    }
+
+    fn snapshot_file_is_current(&self, path: &Path) -> Result<bool, RobustMQError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = [0u8; 2];
+        read_exact(&mut reader, &mut header)?;
+        if header != [SNAPSHOT_FILE_MAGIC, SNAPSHOT_FILE_VERSION] {
+            return Ok(false);
+        }
+        loop {
+            let mut len = [0u8; 4];
+            read_exact(&mut reader, &mut len)?;
+            let len = u32::from_be_bytes(len) as usize;
+            if len == 0 {
+                return Ok(true);
+            }
+            let mut data = vec![0; len];
+            read_exact(&mut reader, &mut data)?;
+            if !self.rocksdb_engine_handler.is_current(&data) {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn write_frame<W: Write>(&self, writer: &mut W, frame: &[(String, StorageDataWrap)]) -> Result<(), RobustMQError> {
+        let data = compress_snapshot(&serde_json::to_vec(frame)?)
+            .and_then(|data| self.rocksdb_engine_handler.encrypt(data))?;
+        writer.write_all(&(data.len() as u32).to_be_bytes())?;
+        writer.write_all(&data)?;
+        Ok(())
+    }
+
+    /// Visit the frames of a snapshot file one at a time. A snapshot file written as a single
+    /// payload before frames existed is visited as one frame.
+    pub fn read_snapshot<R, F>(&self, mut reader: R, mut visit: F) -> Result<(), RobustMQError>
+    where
+        R: Read,
+        F: FnMut(SnapshotFrame) -> Result<(), RobustMQError>,
+    {
+        let mut magic = [0u8; 1];
+        read_exact(&mut reader, &mut magic)?;
+        if magic[0] != SNAPSHOT_FILE_MAGIC {
+            let mut data = magic.to_vec();
+            reader.read_to_end(&mut data)?;
+            let data = self
+                .rocksdb_engine_handler
+                .decrypt(&data)
+                .and_then(|data| decompress_snapshot(&data))?;
+            return visit(decode_snapshot_data(&data)?.into_iter().collect());
+        }
+
+        let mut version = [0u8; 1];
+        read_exact(&mut reader, &mut version)?;
+        if version[0] != SNAPSHOT_FILE_VERSION {
+            return Err(RobustMQError::UnsupportedSnapshotVersion(version[0]));
+        }
+        loop {
+            let mut len = [0u8; 4];
+            read_exact(&mut reader, &mut len)?;
+            let len = u32::from_be_bytes(len) as usize;
+            if len == 0 {
+                return Ok(());
+            }
+            let mut data = vec![0; len];
+            read_exact(&mut reader, &mut data)?;
+            let data = self
+                .rocksdb_engine_handler
+                .decrypt(&data)
+                .and_then(|data| decompress_snapshot(&data))?;
+            visit(serde_json::from_slice(&data)?)?;
+        }
+    }
+
+    /// Replace every key of the state machine with the keys of a snapshot file, one frame per
+    /// write batch. The applied state is removed together with the old keys and written again
+    /// after the last frame, a node stopped in between installs the snapshot again when it starts.
+    pub fn install_snapshot<R: Read>(
+        &self,
+        reader: R,
+        last_applied_log_id: &Option<LogId<NodeId>>,
+        last_membership: &StoredMembership<TypeConfig>,
+    ) -> Result<(), RobustMQError> {
+        let engine = &self.rocksdb_engine_handler;
+        let mut batch = WriteBatch::default();
+        batch.delete_cf(engine.cf_raft(), key_openraft_last_applied());
+        batch.delete_cf(engine.cf_raft(), key_openraft_last_membership());
+        // Keys are UTF-8 strings, none of them starts with the byte 0xFF
+        batch.delete_range_cf(engine.cf_kv(), b"".as_slice(), [0xFFu8].as_slice());
+        engine.write_batch(batch)?;
+
+        self.read_snapshot(reader, |frame| {
+            let ops = frame
+                .into_iter()
+                .map(|(key, data)| EngineBatchOp::Save(key, data))
+                .collect();
+            engine_batch_across(engine.clone(), vec![(DB_COLUMN_FAMILY_KV, ops)])
+        })?;
+
+        let applied_ops = self.applied_state_ops(last_applied_log_id, last_membership)?;
+        engine_batch_across(engine.clone(), vec![(DB_COLUMN_FAMILY_RAFT, applied_ops)])
+    }
+}
+
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), RobustMQError> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Err(RobustMQError::SnapshotTruncated),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Snapshots built before the state machine moved to RocksDB hold plain string values
+fn decode_snapshot_data(data: &[u8]) -> Result<BTreeMap<String, StorageDataWrap>, serde_json::Error> {
+    match serde_json::from_slice::<BTreeMap<String, StorageDataWrap>>(data) {
+        Ok(data) => Ok(data),
+        Err(_) => {
+            let kvs: BTreeMap<String, String> = serde_json::from_slice(data)?;
+            let mut results = BTreeMap::new();
+            for (key, value) in kvs {
+                results.insert(key, StorageDataWrap::new(serde_json::to_vec(&value)?));
+            }
+            Ok(results)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::sync::Arc;
+    use openraft::StoredMembership;
+    use crate::storage::kv::KvStorage;
+    use crate::storage::rocksdb::RocksDBEngine;
+    use crate::storage::test_tools::TempDir;
+    use super::StateMachineStorage;
+
+    #[test]
+    fn reencrypt_snapshot_file_after_key_rotation() {
+        let dir = TempDir::new();
+        let key_file = dir.join("encryption.keys");
+        let key = |key_id: u32| format!("{} = \"{}\"\n", key_id, format!("{}{}", key_id, key_id).repeat(32));
+        let mut config = dir.config();
+        config.encryption_key_file = key_file.clone();
+        let path = dir.join("1-1-2-1.snap");
+        let path = std::path::Path::new(&path);
+
+        std::fs::write(&key_file, format!("active_key_id = 1\n\n[keys]\n{}", key(1))).unwrap();
+        {
+            let rs = Arc::new(RocksDBEngine::new(&config));
+            let kv_storage = KvStorage::new(rs.clone());
+            kv_storage.set("/kv/default/k1".to_string(), "v1".to_string(), 0, 1).unwrap();
+            kv_storage.set("/kv/default/k2".to_string(), "v2".to_string(), 0, 2).unwrap();
+            StateMachineStorage::new(rs).write_snapshot_file(path).unwrap();
+        }
+
+        std::fs::write(&key_file, format!("active_key_id = 2\n\n[keys]\n{}{}", key(1), key(2))).unwrap();
+        {
+            let storage = StateMachineStorage::new(Arc::new(RocksDBEngine::new(&config)));
+            assert!(storage.reencrypt_snapshot_file(path).unwrap());
+            assert!(!storage.reencrypt_snapshot_file(path).unwrap());
+        }
+
+        // Key 1 is no longer needed to install the snapshot
+        std::fs::write(&key_file, format!("active_key_id = 2\n\n[keys]\n{}", key(2))).unwrap();
+        let rs = Arc::new(RocksDBEngine::new(&config));
+        StateMachineStorage::new(rs.clone())
+            .install_snapshot(BufReader::new(File::open(path).unwrap()), &None, &StoredMembership::default())
+            .unwrap();
+        let kv_storage = KvStorage::new(rs);
+        assert_eq!(kv_storage.get("/kv/default/k1".to_string()).unwrap().unwrap(), "v1");
+        assert_eq!(kv_storage.get("/kv/default/k2".to_string()).unwrap().unwrap(), "v2");
+    }
+}
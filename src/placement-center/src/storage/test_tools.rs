@@ -0,0 +1,52 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use common_base::config::placement_center::PlacementCenterConfig;
+use crate::storage::rocksdb::RocksDBEngine;
+
+static NEXT_TEMP_DIR: AtomicU64 = AtomicU64::new(0);
+
+/// A directory of its own under /tmp for one test, removed with its content when it is dropped.
+/// Tests create it first, so it is dropped after the storages opened in it.
+pub struct TempDir {
+    path: String,
+}
+
+impl TempDir {
+    pub fn new() -> Self {
+        let path = format!(
+            "/tmp/tmp_test_{}_{}",
+            std::process::id(),
+            NEXT_TEMP_DIR.fetch_add(1, Ordering::Relaxed)
+        );
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// A path inside the directory.
+    pub fn join(&self, name: &str) -> String {
+        format!("{}/{}", self.path, name)
+    }
+
+    /// The default configuration with its data path in the directory.
+    pub fn config(&self) -> PlacementCenterConfig {
+        let mut config = PlacementCenterConfig::default();
+        config.data_path = self.path.clone();
+        config
+    }
+
+    /// A storage opened with `config`.
+    pub fn rocksdb_engine(&self) -> Arc<RocksDBEngine> {
+        Arc::new(RocksDBEngine::new(&self.config()))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+use std::sync::RwLock;
+use log::warn;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use tonic::codegen::tokio_stream::wrappers::ReceiverStream;
+use tonic::Status;
+use common_base::errors::RobustMQError;
+use protocol::kv::{WatchEventType, WatchReply, WatchRequest};
+use crate::storage::namespace::{namespace_key, namespace_name, namespace_user_key};
+
+/// Fans out the changes applied by the state machine to the watch streams.
+///
+/// The most recent events are kept in memory so that a watcher can resume from a
+/// `start_revision` instead of re-reading every key after a reconnect. Events older
+/// than the history are compacted and can no longer be watched.
+#[derive(Debug)]
+pub struct WatchHub {
+    sender: broadcast::Sender<WatchReply>,
+    history: RwLock<WatchHistory>,
+}
+
+#[derive(Debug)]
+struct WatchHistory {
+    capacity: usize,
+    events: VecDeque<WatchReply>,
+    // Revision of the latest event dropped from the history
+    compacted_revision: u64,
+}
+
+impl WatchHub {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(1000);
+        WatchHub {
+            sender,
+            history: RwLock::new(WatchHistory {
+                capacity,
+                events: VecDeque::with_capacity(capacity),
+                compacted_revision: 0,
+            }),
+        }
+    }
+
+    pub fn put(&self, key: String, value: String, revision: u64) {
+        self.publish(WatchReply {
+            event_type: WatchEventType::Put.into(),
+            key,
+            value,
+            revision,
+        });
+    }
+
+    pub fn delete(&self, key: String, revision: u64) {
+        self.publish(WatchReply {
+            event_type: WatchEventType::Delete.into(),
+            key,
+            value: String::new(),
+            revision,
+        });
+    }
+
+    fn publish(&self, event: WatchReply) {
+        // The event is broadcast while the history lock is held, so a watcher that
+        // subscribes concurrently sees it either in the history or in the channel, never both.
+        let mut history = self.history.write().unwrap();
+        if history.capacity > 0 {
+            if history.events.len() >= history.capacity {
+                if let Some(evicted) = history.events.pop_front() {
+                    history.compacted_revision = evicted.revision;
+                }
+            }
+            history.events.push_back(event.clone());
+        }
+        // An error only means that nobody is watching right now
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribe to the changes of `key`, or of every key starting with `key` when `prefix` is set.
+    /// Returns the retained events with a revision of at least `start_revision` together with the
+    /// receiver of the events applied afterwards. A `start_revision` of 0 only watches new events.
+    pub fn subscribe(
+        &self,
+        key: &str,
+        prefix: bool,
+        start_revision: u64,
+    ) -> Result<(Vec<WatchReply>, broadcast::Receiver<WatchReply>), RobustMQError> {
+        let history = self.history.read().unwrap();
+        if start_revision > 0 && start_revision <= history.compacted_revision {
+            return Err(RobustMQError::WatchRevisionCompacted(
+                start_revision,
+                history.compacted_revision + 1,
+            ));
+        }
+
+        let receiver = self.sender.subscribe();
+        let mut events = Vec::new();
+        if start_revision > 0 {
+            for event in history.events.iter() {
+                if event.revision >= start_revision && watch_match(key, prefix, &event.key) {
+                    events.push(event.clone());
+                }
+            }
+        }
+        Ok((events, receiver))
+    }
+}
+
+/// Start a watcher for the request and return the stream of its events. The stream ends with a
+/// `data_loss` status if the watcher falls too far behind, the client should watch again from
+/// the revision after the last event it received.
+///
+/// The watcher only sees the keys of the namespace of the request, and the events carry the
+/// keys without the namespace prefix.
+pub fn watch_stream(
+    watch_hub: &WatchHub,
+    req: WatchRequest,
+) -> Result<ReceiverStream<Result<WatchReply, Status>>, RobustMQError> {
+    let namespace = namespace_name(&req.namespace)?;
+    let key = namespace_key(&namespace, &req.key);
+    let (history, mut receiver) = watch_hub.subscribe(&key, req.prefix, req.start_revision)?;
+    let (sx, rx) = mpsc::channel(100);
+    tokio::spawn(async move {
+        for mut event in history {
+            event.key = namespace_user_key(&namespace, &event.key);
+            if sx.send(Ok(event)).await.is_err() {
+                return;
+            }
+        }
+
+        loop {
+            match receiver.recv().await {
+                Ok(mut event) => {
+                    if !watch_match(&key, req.prefix, &event.key) {
+                        continue;
+                    }
+                    event.key = namespace_user_key(&namespace, &event.key);
+                    if sx.send(Ok(event)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(num)) => {
+                    warn!("Watcher of key {} lagged behind by {} events and was closed", key, num);
+                    let _ = sx
+                        .send(Err(Status::data_loss(format!(
+                            "Watcher lagged behind by {} events",
+                            num
+                        ))))
+                        .await;
+                    return;
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+    Ok(ReceiverStream::new(rx))
+}
+
+pub fn watch_match(key: &str, prefix: bool, event_key: &str) -> bool {
+    if prefix {
+        event_key.starts_with(key)
+    } else {
+        event_key == key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::kv::WatchEventType;
+    use super::{watch_match, WatchHub};
+
+    #[tokio::test]
+    async fn watch_history_and_stream() {
+        let hub = WatchHub::new(3);
+        hub.put("/broker/1".to_string(), "v1".to_string(), 1);
+        hub.put("/topic/1".to_string(), "v1".to_string(), 2);
+        hub.delete("/broker/1".to_string(), 3);
+
+        let (events, mut receiver) = hub.subscribe("/broker/", true, 1).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].revision, 1);
+        assert_eq!(events[1].event_type, i32::from(WatchEventType::Delete));
+
+        hub.put("/broker/2".to_string(), "v2".to_string(), 4);
+        let event = receiver.recv().await.unwrap();
+        assert_eq!(event.key, "/broker/2");
+        assert_eq!(event.revision, 4);
+
+        // Revision 1 has been evicted from a history of 3 events
+        assert!(hub.subscribe("/broker/", true, 1).is_err());
+        assert!(hub.subscribe("/broker/", true, 2).is_ok());
+    }
+
+    #[test]
+    fn watch_match_key_and_prefix() {
+        assert!(watch_match("/broker/1", false, "/broker/1"));
+        assert!(!watch_match("/broker/1", false, "/broker/10"));
+        assert!(watch_match("/broker/", true, "/broker/10"));
+        assert!(!watch_match("/broker/", true, "/topic/1"));
+    }
+}
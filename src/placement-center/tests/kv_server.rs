@@ -2,7 +2,8 @@
 #[cfg(test)]
 mod tests {
     use protocol::kv::kv_service_client::KvServiceClient;
-    use protocol::kv::{DeleteRequest, ExistsRequest, GetRequest, SetRequest};
+    use protocol::kv::txn_op::Op;
+    use protocol::kv::{BatchDeleteRequest, BatchSetRequest, Compare, CompareAndSwapRequest, CompareResult, CompareTarget, DeleteRequest, ExistsRequest, GetRequest, LeaseGrantRequest, LeaseRevokeRequest, ListRequest, NamespaceGetRequest, NamespaceSetRequest, ReadConsistency, SetRequest, TxnOp, TxnRequest};
 
     #[tokio::test]
     async fn kv_test() {
@@ -14,31 +15,375 @@ mod tests {
         let request = tonic::Request::new(SetRequest {
             key: key.clone(),
             value: value.clone(),
+            lease_id: 0,
+            namespace: "".to_string(),
         });
 
         let _ = client.set(request).await.unwrap();
 
         let request = tonic::Request::new(ExistsRequest {
             key: key.clone(),
+            consistency: ReadConsistency::Linearizable.into(),
+            namespace: "".to_string(),
         });
         let exist_reply = client.exists(request).await.unwrap().into_inner();
         assert!(exist_reply.flag);
 
         let request = tonic::Request::new(GetRequest {
             key: key.clone(),
+            consistency: ReadConsistency::Linearizable.into(),
+            namespace: "".to_string(),
         });
         let get_reply = client.get(request).await.unwrap().into_inner();
         assert_eq!(get_reply.value, value);
+        assert!(get_reply.mod_revision > 0);
+        assert!(get_reply.create_revision <= get_reply.mod_revision);
 
         let request = tonic::Request::new(DeleteRequest {
             key: key.clone(),
+            namespace: "".to_string(),
         });
         let _ = client.delete(request).await.unwrap().into_inner();
 
         let request = tonic::Request::new(ExistsRequest {
             key: key.clone(),
+            consistency: ReadConsistency::Linearizable.into(),
+            namespace: "".to_string(),
         });
         let exist_reply = client.exists(request).await.unwrap().into_inner();
         assert!(!exist_reply.flag);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn compare_and_swap_test() {
+        let mut client = KvServiceClient::connect("http://127.0.0.1:8871")
+            .await
+            .unwrap();
+        let key = "mq_cas".to_string();
+
+        let request = tonic::Request::new(CompareAndSwapRequest {
+            key: key.clone(),
+            expected_value: "".to_string(),
+            new_value: "broker-1".to_string(),
+            namespace: "".to_string(),
+        });
+        let reply = client.compare_and_swap(request).await.unwrap().into_inner();
+        assert!(reply.success);
+        assert_eq!(reply.current_value, "broker-1");
+
+        let request = tonic::Request::new(CompareAndSwapRequest {
+            key: key.clone(),
+            expected_value: "".to_string(),
+            new_value: "broker-2".to_string(),
+            namespace: "".to_string(),
+        });
+        let reply = client.compare_and_swap(request).await.unwrap().into_inner();
+        assert!(!reply.success);
+        assert_eq!(reply.current_value, "broker-1");
+
+        let request = tonic::Request::new(DeleteRequest {
+            key: key.clone(),
+            namespace: "".to_string(),
+        });
+        let _ = client.delete(request).await.unwrap().into_inner();
+    }
+
+    #[tokio::test]
+    async fn lease_test() {
+        let mut client = KvServiceClient::connect("http://127.0.0.1:8871")
+            .await
+            .unwrap();
+        let key = "mq_lease".to_string();
+
+        let request = tonic::Request::new(LeaseGrantRequest {
+            ttl: 30,
+            lease_id: 0,
+        });
+        let grant_reply = client.lease_grant(request).await.unwrap().into_inner();
+        assert!(grant_reply.lease_id > 0);
+        assert_eq!(grant_reply.ttl, 30);
+
+        let request = tonic::Request::new(SetRequest {
+            key: key.clone(),
+            value: "broker-1".to_string(),
+            lease_id: grant_reply.lease_id,
+            namespace: "".to_string(),
+        });
+        let _ = client.set(request).await.unwrap();
+
+        let request = tonic::Request::new(ExistsRequest {
+            key: key.clone(),
+            consistency: ReadConsistency::Linearizable.into(),
+            namespace: "".to_string(),
+        });
+        let exist_reply = client.exists(request).await.unwrap().into_inner();
+        assert!(exist_reply.flag);
+
+        let request = tonic::Request::new(LeaseRevokeRequest {
+            lease_id: grant_reply.lease_id,
+        });
+        let _ = client.lease_revoke(request).await.unwrap();
+
+        let request = tonic::Request::new(ExistsRequest {
+            key: key.clone(),
+            consistency: ReadConsistency::Linearizable.into(),
+            namespace: "".to_string(),
+        });
+        let exist_reply = client.exists(request).await.unwrap().into_inner();
+        assert!(!exist_reply.flag);
+    }
+
+    #[tokio::test]
+    async fn list_test() {
+        let mut client = KvServiceClient::connect("http://127.0.0.1:8871")
+            .await
+            .unwrap();
+        for i in 1..4 {
+            let request = tonic::Request::new(SetRequest {
+                key: format!("/mq_list/{}", i),
+                value: format!("v{}", i),
+                lease_id: 0,
+                namespace: "".to_string(),
+            });
+            let _ = client.set(request).await.unwrap();
+        }
+
+        let request = tonic::Request::new(ListRequest {
+            prefix: "/mq_list/".to_string(),
+            start_after: "".to_string(),
+            limit: 2,
+            keys_only: false,
+            namespace: "".to_string(),
+        });
+        let reply = client.list(request).await.unwrap().into_inner();
+        assert_eq!(reply.kvs.len(), 2);
+        assert_eq!(reply.kvs[0].value, "v1");
+        assert_eq!(reply.next_token, "/mq_list/2");
+
+        let request = tonic::Request::new(ListRequest {
+            prefix: "/mq_list/".to_string(),
+            start_after: reply.next_token,
+            limit: 2,
+            keys_only: true,
+            namespace: "".to_string(),
+        });
+        let reply = client.list(request).await.unwrap().into_inner();
+        assert_eq!(reply.kvs.len(), 1);
+        assert_eq!(reply.kvs[0].key, "/mq_list/3");
+        assert!(reply.kvs[0].value.is_empty());
+        assert!(reply.next_token.is_empty());
+
+        for i in 1..4 {
+            let request = tonic::Request::new(DeleteRequest {
+                key: format!("/mq_list/{}", i),
+                namespace: "".to_string(),
+            });
+            let _ = client.delete(request).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn txn_test() {
+        let mut client = KvServiceClient::connect("http://127.0.0.1:8871")
+            .await
+            .unwrap();
+        let node_key = "/mq_txn/node".to_string();
+        let config_key = "/mq_txn/config".to_string();
+
+        let set_op = |key: &String, value: &str| TxnOp {
+            op: Some(Op::Set(SetRequest {
+                key: key.clone(),
+                value: value.to_string(),
+                lease_id: 0,
+                namespace: "".to_string(),
+            })),
+        };
+        let not_exists = Compare {
+            key: node_key.clone(),
+            target: CompareTarget::Exists.into(),
+            result: CompareResult::Equal.into(),
+            value: "".to_string(),
+            version: 0,
+            exists: false,
+        };
+
+        let request = tonic::Request::new(TxnRequest {
+            compare: vec![not_exists.clone()],
+            success: vec![set_op(&node_key, "node-1"), set_op(&config_key, "config-1")],
+            failure: vec![],
+            namespace: "".to_string(),
+        });
+        let reply = client.txn(request).await.unwrap().into_inner();
+        assert!(reply.succeeded);
+
+        let request = tonic::Request::new(TxnRequest {
+            compare: vec![not_exists],
+            success: vec![set_op(&node_key, "node-2")],
+            failure: vec![set_op(&config_key, "config-2")],
+            namespace: "".to_string(),
+        });
+        let reply = client.txn(request).await.unwrap().into_inner();
+        assert!(!reply.succeeded);
+
+        let request = tonic::Request::new(GetRequest {
+            key: node_key.clone(),
+            consistency: ReadConsistency::Linearizable.into(),
+            namespace: "".to_string(),
+        });
+        let get_reply = client.get(request).await.unwrap().into_inner();
+        assert_eq!(get_reply.value, "node-1");
+
+        let request = tonic::Request::new(GetRequest {
+            key: config_key.clone(),
+            consistency: ReadConsistency::Linearizable.into(),
+            namespace: "".to_string(),
+        });
+        let get_reply = client.get(request).await.unwrap().into_inner();
+        assert_eq!(get_reply.value, "config-2");
+        assert_eq!(get_reply.version, 2);
+
+        for key in [node_key, config_key] {
+            let request = tonic::Request::new(DeleteRequest {
+                key,
+                namespace: "".to_string(),
+            });
+            let _ = client.delete(request).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_test() {
+        let mut client = KvServiceClient::connect("http://127.0.0.1:8871")
+            .await
+            .unwrap();
+        let kvs: Vec<SetRequest> = (1..=100)
+            .map(|i| SetRequest {
+                key: format!("/mq_batch/{}", i),
+                value: format!("v{}", i),
+                lease_id: 0,
+                namespace: "".to_string(),
+            })
+            .collect();
+        let keys: Vec<String> = kvs.iter().map(|kv| kv.key.clone()).collect();
+
+        let request = tonic::Request::new(BatchSetRequest {
+            kvs,
+            namespace: "".to_string(),
+        });
+        let _ = client.batch_set(request).await.unwrap();
+
+        let request = tonic::Request::new(ListRequest {
+            prefix: "/mq_batch/".to_string(),
+            start_after: "".to_string(),
+            limit: 1000,
+            keys_only: true,
+            namespace: "".to_string(),
+        });
+        let reply = client.list(request).await.unwrap().into_inner();
+        assert_eq!(reply.kvs.len(), 100);
+
+        // Every key of the batch is written by the same raft entry
+        let request = tonic::Request::new(GetRequest {
+            key: keys[0].clone(),
+            consistency: ReadConsistency::Linearizable.into(),
+            namespace: "".to_string(),
+        });
+        let first = client.get(request).await.unwrap().into_inner();
+        let request = tonic::Request::new(GetRequest {
+            key: keys[99].clone(),
+            consistency: ReadConsistency::Linearizable.into(),
+            namespace: "".to_string(),
+        });
+        let last = client.get(request).await.unwrap().into_inner();
+        assert_eq!(last.value, "v100");
+        assert_eq!(first.mod_revision, last.mod_revision);
+
+        let request = tonic::Request::new(BatchDeleteRequest {
+            keys,
+            namespace: "".to_string(),
+        });
+        let _ = client.batch_delete(request).await.unwrap();
+
+        let request = tonic::Request::new(ListRequest {
+            prefix: "/mq_batch/".to_string(),
+            start_after: "".to_string(),
+            limit: 1000,
+            keys_only: true,
+            namespace: "".to_string(),
+        });
+        let reply = client.list(request).await.unwrap().into_inner();
+        assert!(reply.kvs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn namespace_test() {
+        let mut client = KvServiceClient::connect("http://127.0.0.1:8871")
+            .await
+            .unwrap();
+        let namespace = "tenant-mq-test".to_string();
+        let key = "/broker/1".to_string();
+
+        let request = tonic::Request::new(NamespaceSetRequest {
+            namespace: namespace.clone(),
+            max_keys: 1,
+            max_bytes: 0,
+            max_value_size: 16,
+        });
+        let _ = client.namespace_set(request).await.unwrap();
+
+        let request = tonic::Request::new(SetRequest {
+            key: key.clone(),
+            value: "tenant".to_string(),
+            lease_id: 0,
+            namespace: namespace.clone(),
+        });
+        let _ = client.set(request).await.unwrap();
+
+        // The same key of another namespace is a different key
+        let request = tonic::Request::new(GetRequest {
+            key: key.clone(),
+            consistency: ReadConsistency::Linearizable.into(),
+            namespace: "".to_string(),
+        });
+        let reply = client.get(request).await.unwrap().into_inner();
+        assert_ne!(reply.value, "tenant");
+
+        let request = tonic::Request::new(ListRequest {
+            prefix: "/broker/".to_string(),
+            start_after: "".to_string(),
+            limit: 10,
+            keys_only: false,
+            namespace: namespace.clone(),
+        });
+        let reply = client.list(request).await.unwrap().into_inner();
+        assert_eq!(reply.kvs.len(), 1);
+        assert_eq!(reply.kvs[0].key, key);
+
+        // The namespace is full
+        let request = tonic::Request::new(SetRequest {
+            key: "/broker/2".to_string(),
+            value: "tenant".to_string(),
+            lease_id: 0,
+            namespace: namespace.clone(),
+        });
+        assert!(client.set(request).await.is_err());
+
+        let request = tonic::Request::new(NamespaceGetRequest {
+            namespace: namespace.clone(),
+        });
+        let reply = client.namespace_get(request).await.unwrap().into_inner();
+        assert_eq!(reply.key_count, 1);
+        assert_eq!(reply.max_keys, 1);
+
+        let request = tonic::Request::new(DeleteRequest {
+            key,
+            namespace: namespace.clone(),
+        });
+        let _ = client.delete(request).await.unwrap();
+
+        let request = tonic::Request::new(NamespaceGetRequest { namespace });
+        let reply = client.namespace_get(request).await.unwrap().into_inner();
+        assert_eq!(reply.key_count, 0);
+        assert_eq!(reply.total_bytes, 0);
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use protocol::kv::kv_service_client::KvServiceClient;
+    use protocol::kv::{GetRequest, ReadConsistency, SetRequest};
+    use protocol::openraft::open_raft_service_client::OpenRaftServiceClient;
+    use protocol::openraft::{AddLearnerRequest, MembershipRequest, PromoteVoterRequest, ReadIndexRequest, RemoveNodeRequest};
+
+    #[tokio::test]
+    async fn membership_test() {
+        let mut client = OpenRaftServiceClient::connect("http://127.0.0.1:8871")
+            .await
+            .unwrap();
+
+        let reply = client.membership(MembershipRequest {}).await.unwrap().into_inner();
+        let node = reply.nodes.iter().find(|node| node.node_id == 1).unwrap();
+        assert!(node.voter);
+        assert!(!node.rpc_addr.is_empty());
+
+        // Only learners can be promoted and only members removed
+        assert!(client.promote_voter(PromoteVoterRequest { node_id: 99 }).await.is_err());
+        assert!(client.remove_node(RemoveNodeRequest { node_id: 99 }).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn assign_node_id_test() {
+        let mut client = OpenRaftServiceClient::connect("http://127.0.0.1:8871")
+            .await
+            .unwrap();
+
+        let assigned_id = |reply: &protocol::openraft::MembershipReply, rpc_addr: &str| {
+            reply.nodes.iter().find(|node| node.rpc_addr == rpc_addr).map(|node| node.node_id)
+        };
+        let request = |rpc_addr: &str| AddLearnerRequest {
+            node_id: 0,
+            rpc_addr: rpc_addr.to_string(),
+        };
+
+        // Concurrent joins get distinct ids, a retry from the same address gets the same id
+        let (first, second) = tokio::join!(
+            client.clone().add_learner(request("127.0.0.1:1901")),
+            client.clone().add_learner(request("127.0.0.1:1902"))
+        );
+        let first_id = assigned_id(&first.unwrap().into_inner(), "127.0.0.1:1901").unwrap();
+        let second_id = assigned_id(&second.unwrap().into_inner(), "127.0.0.1:1902").unwrap();
+        assert_ne!(first_id, second_id);
+        let retry = client.add_learner(request("127.0.0.1:1901")).await.unwrap().into_inner();
+        assert_eq!(assigned_id(&retry, "127.0.0.1:1901"), Some(first_id));
+
+        for node_id in [first_id, second_id] {
+            client.remove_node(RemoveNodeRequest { node_id }).await.unwrap();
+        }
+    }
+
+    /// Needs a second node that joined the cluster of the node on 8871 with grpc_port = 8872.
+    #[tokio::test]
+    async fn follower_read_test() {
+        let mut leader = KvServiceClient::connect("http://127.0.0.1:8871")
+            .await
+            .unwrap();
+        let mut follower = KvServiceClient::connect("http://127.0.0.1:8872")
+            .await
+            .unwrap();
+
+        let key = "follower_read".to_string();
+        for i in 0..10 {
+            let value = format!("v{}", i);
+            let request = tonic::Request::new(SetRequest {
+                key: key.clone(),
+                value: value.clone(),
+                lease_id: 0,
+                namespace: "".to_string(),
+            });
+            let _ = leader.set(request).await.unwrap();
+
+            // The follower serves the read itself and observes the write acknowledged by the leader
+            let request = tonic::Request::new(GetRequest {
+                key: key.clone(),
+                consistency: ReadConsistency::Linearizable.into(),
+                namespace: "".to_string(),
+            });
+            let reply = follower.get(request).await.unwrap().into_inner();
+            assert_eq!(reply.value, value);
+        }
+
+        // Only the leader hands out read indexes
+        let mut leader = OpenRaftServiceClient::connect("http://127.0.0.1:8871")
+            .await
+            .unwrap();
+        let reply = leader.read_index(ReadIndexRequest {}).await.unwrap().into_inner();
+        assert!(reply.log_index > 0);
+        let mut follower = OpenRaftServiceClient::connect("http://127.0.0.1:8872")
+            .await
+            .unwrap();
+        assert!(follower.read_index(ReadIndexRequest {}).await.is_err());
+    }
+}
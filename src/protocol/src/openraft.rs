@@ -25,9 +25,24 @@ pub struct AppendReply {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct SnapshotRequest {
+pub struct SnapshotChunk {
+    /// bincode-serialized Vote of the leader, only read from the first chunk of a stream
     #[prost(bytes = "vec", tag = "1")]
-    pub value: ::prost::alloc::vec::Vec<u8>,
+    pub vote: ::prost::alloc::vec::Vec<u8>,
+    /// bincode-serialized SnapshotMeta, only read from the first chunk of a stream
+    #[prost(bytes = "vec", tag = "2")]
+    pub meta: ::prost::alloc::vec::Vec<u8>,
+    /// Position of data in the snapshot
+    #[prost(uint64, tag = "3")]
+    pub offset: u64,
+    #[prost(bytes = "vec", tag = "4")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+    /// CRC32 of data
+    #[prost(uint32, tag = "5")]
+    pub checksum: u32,
+    /// Set on the last chunk of the snapshot
+    #[prost(bool, tag = "6")]
+    pub done: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -35,6 +50,73 @@ pub struct SnapshotReply {
     #[prost(bytes = "vec", tag = "1")]
     pub value: ::prost::alloc::vec::Vec<u8>,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SnapshotOffsetRequest {
+    #[prost(string, tag = "1")]
+    pub snapshot_id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SnapshotOffsetReply {
+    /// Number of bytes of the snapshot already received, the sender resumes from here
+    #[prost(uint64, tag = "1")]
+    pub offset: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AddLearnerRequest {
+    #[prost(uint64, tag = "1")]
+    pub node_id: u64,
+    /// Address of the gRPC server of the node
+    #[prost(string, tag = "2")]
+    pub rpc_addr: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PromoteVoterRequest {
+    #[prost(uint64, tag = "1")]
+    pub node_id: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoveNodeRequest {
+    #[prost(uint64, tag = "1")]
+    pub node_id: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MembershipRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MembershipNode {
+    #[prost(uint64, tag = "1")]
+    pub node_id: u64,
+    #[prost(string, tag = "2")]
+    pub rpc_addr: ::prost::alloc::string::String,
+    /// Whether the node votes, learners only replicate the log
+    #[prost(bool, tag = "3")]
+    pub voter: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MembershipReply {
+    /// Index of the log holding the membership config
+    #[prost(uint64, tag = "1")]
+    pub log_index: u64,
+    #[prost(message, repeated, tag = "2")]
+    pub nodes: ::prost::alloc::vec::Vec<MembershipNode>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReadIndexRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReadIndexReply {
+    /// Index of the log a read must wait for, confirmed by the leader to be committed
+    #[prost(uint64, tag = "1")]
+    pub log_index: u64,
+}
 /// Generated client implementations.
 pub mod open_raft_service_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -164,9 +246,9 @@ pub mod open_raft_service_client {
                 .insert(GrpcMethod::new("openraft.OpenRaftService", "append"));
             self.inner.unary(req, path, codec).await
         }
-        pub async fn snapshot(
+        pub async fn snapshot_stream(
             &mut self,
-            request: impl tonic::IntoRequest<super::SnapshotRequest>,
+            request: impl tonic::IntoStreamingRequest<Message = super::SnapshotChunk>,
         ) -> std::result::Result<tonic::Response<super::SnapshotReply>, tonic::Status> {
             self.inner
                 .ready()
@@ -179,11 +261,161 @@ pub mod open_raft_service_client {
                 })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/openraft.OpenRaftService/snapshot",
+                "/openraft.OpenRaftService/snapshot_stream",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("openraft.OpenRaftService", "snapshot_stream"));
+            self.inner.client_streaming(req, path, codec).await
+        }
+        pub async fn snapshot_offset(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SnapshotOffsetRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SnapshotOffsetReply>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/openraft.OpenRaftService/snapshot_offset",
             );
             let mut req = request.into_request();
             req.extensions_mut()
-                .insert(GrpcMethod::new("openraft.OpenRaftService", "snapshot"));
+                .insert(GrpcMethod::new("openraft.OpenRaftService", "snapshot_offset"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn add_learner(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AddLearnerRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MembershipReply>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/openraft.OpenRaftService/add_learner",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("openraft.OpenRaftService", "add_learner"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn promote_voter(
+            &mut self,
+            request: impl tonic::IntoRequest<super::PromoteVoterRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MembershipReply>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/openraft.OpenRaftService/promote_voter",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("openraft.OpenRaftService", "promote_voter"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn remove_node(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RemoveNodeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MembershipReply>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/openraft.OpenRaftService/remove_node",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("openraft.OpenRaftService", "remove_node"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn membership(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MembershipRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MembershipReply>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/openraft.OpenRaftService/membership",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("openraft.OpenRaftService", "membership"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn read_index(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ReadIndexRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReadIndexReply>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/openraft.OpenRaftService/read_index",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("openraft.OpenRaftService", "read_index"));
             self.inner.unary(req, path, codec).await
         }
     }
@@ -203,10 +435,52 @@ pub mod open_raft_service_server {
             &self,
             request: tonic::Request<super::AppendRequest>,
         ) -> std::result::Result<tonic::Response<super::AppendReply>, tonic::Status>;
-        async fn snapshot(
+        async fn snapshot_stream(
             &self,
-            request: tonic::Request<super::SnapshotRequest>,
+            request: tonic::Request<tonic::Streaming<super::SnapshotChunk>>,
         ) -> std::result::Result<tonic::Response<super::SnapshotReply>, tonic::Status>;
+        async fn snapshot_offset(
+            &self,
+            request: tonic::Request<super::SnapshotOffsetRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SnapshotOffsetReply>,
+            tonic::Status,
+        >;
+        async fn add_learner(
+            &self,
+            request: tonic::Request<super::AddLearnerRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MembershipReply>,
+            tonic::Status,
+        >;
+        async fn promote_voter(
+            &self,
+            request: tonic::Request<super::PromoteVoterRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MembershipReply>,
+            tonic::Status,
+        >;
+        async fn remove_node(
+            &self,
+            request: tonic::Request<super::RemoveNodeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MembershipReply>,
+            tonic::Status,
+        >;
+        async fn membership(
+            &self,
+            request: tonic::Request<super::MembershipRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MembershipReply>,
+            tonic::Status,
+        >;
+        async fn read_index(
+            &self,
+            request: tonic::Request<super::ReadIndexRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReadIndexReply>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct OpenRaftServiceServer<T: OpenRaftService> {
@@ -378,13 +652,13 @@ pub mod open_raft_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/openraft.OpenRaftService/snapshot" => {
+                "/openraft.OpenRaftService/snapshot_stream" => {
                     #[allow(non_camel_case_types)]
-                    struct snapshotSvc<T: OpenRaftService>(pub Arc<T>);
+                    struct snapshot_streamSvc<T: OpenRaftService>(pub Arc<T>);
                     impl<
                         T: OpenRaftService,
-                    > tonic::server::UnaryService<super::SnapshotRequest>
-                    for snapshotSvc<T> {
+                    > tonic::server::ClientStreamingService<super::SnapshotChunk>
+                    for snapshot_streamSvc<T> {
                         type Response = super::SnapshotReply;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
@@ -392,11 +666,296 @@ pub mod open_raft_service_server {
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::SnapshotRequest>,
+                            request: tonic::Request<
+                                tonic::Streaming<super::SnapshotChunk>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as OpenRaftService>::snapshot_stream(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = snapshot_streamSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/openraft.OpenRaftService/snapshot_offset" => {
+                    #[allow(non_camel_case_types)]
+                    struct snapshot_offsetSvc<T: OpenRaftService>(pub Arc<T>);
+                    impl<
+                        T: OpenRaftService,
+                    > tonic::server::UnaryService<super::SnapshotOffsetRequest>
+                    for snapshot_offsetSvc<T> {
+                        type Response = super::SnapshotOffsetReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SnapshotOffsetRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as OpenRaftService>::snapshot_offset(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = snapshot_offsetSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/openraft.OpenRaftService/add_learner" => {
+                    #[allow(non_camel_case_types)]
+                    struct add_learnerSvc<T: OpenRaftService>(pub Arc<T>);
+                    impl<
+                        T: OpenRaftService,
+                    > tonic::server::UnaryService<super::AddLearnerRequest>
+                    for add_learnerSvc<T> {
+                        type Response = super::MembershipReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AddLearnerRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as OpenRaftService>::add_learner(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = add_learnerSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/openraft.OpenRaftService/promote_voter" => {
+                    #[allow(non_camel_case_types)]
+                    struct promote_voterSvc<T: OpenRaftService>(pub Arc<T>);
+                    impl<
+                        T: OpenRaftService,
+                    > tonic::server::UnaryService<super::PromoteVoterRequest>
+                    for promote_voterSvc<T> {
+                        type Response = super::MembershipReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PromoteVoterRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as OpenRaftService>::promote_voter(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = promote_voterSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/openraft.OpenRaftService/remove_node" => {
+                    #[allow(non_camel_case_types)]
+                    struct remove_nodeSvc<T: OpenRaftService>(pub Arc<T>);
+                    impl<
+                        T: OpenRaftService,
+                    > tonic::server::UnaryService<super::RemoveNodeRequest>
+                    for remove_nodeSvc<T> {
+                        type Response = super::MembershipReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RemoveNodeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as OpenRaftService>::remove_node(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = remove_nodeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/openraft.OpenRaftService/membership" => {
+                    #[allow(non_camel_case_types)]
+                    struct membershipSvc<T: OpenRaftService>(pub Arc<T>);
+                    impl<
+                        T: OpenRaftService,
+                    > tonic::server::UnaryService<super::MembershipRequest>
+                    for membershipSvc<T> {
+                        type Response = super::MembershipReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MembershipRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as OpenRaftService>::membership(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = membershipSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/openraft.OpenRaftService/read_index" => {
+                    #[allow(non_camel_case_types)]
+                    struct read_indexSvc<T: OpenRaftService>(pub Arc<T>);
+                    impl<
+                        T: OpenRaftService,
+                    > tonic::server::UnaryService<super::ReadIndexRequest>
+                    for read_indexSvc<T> {
+                        type Response = super::ReadIndexReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ReadIndexRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as OpenRaftService>::snapshot(&inner, request).await
+                                <T as OpenRaftService>::read_index(&inner, request)
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -408,7 +967,7 @@ pub mod open_raft_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = snapshotSvc(inner);
+                        let method = read_indexSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(